@@ -0,0 +1,435 @@
+use crate::notes::Note;
+use crate::NotesState;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::ipc::InvokeBody;
+use tauri::Manager;
+
+/// Markdown links to attachments look like `![alt](attachment:<name>)`.
+pub const LINK_PREFIX: &str = "attachment:";
+/// URI scheme webviews load attachments from, e.g. `attachment://localhost/<name>`.
+pub const PROTOCOL: &str = "attachment";
+/// Header carrying the file extension for `add_attachment`'s raw body.
+const EXTENSION_HEADER: &str = "x-attachment-extension";
+/// Unreferenced attachments younger than this survive garbage collection, so a
+/// pasted image is not removed before its note is saved.
+pub const GC_GRACE: Duration = Duration::from_secs(24 * 60 * 60);
+const MAX_EXTENSION_LEN: usize = 10;
+
+/// `<app data>/attachments`, created if needed.
+///
+/// Attachments stay on this device: sync providers only carry the notes, so their links
+/// show as missing images on other devices.
+pub fn attachments_dir(app: &tauri::AppHandle) -> PathBuf {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .expect("Failed to get app data dir")
+        .join("attachments");
+    fs::create_dir_all(&dir).ok();
+    dir
+}
+
+/// Content-addressed name: the SHA-256 of the bytes plus the lower-cased extension.
+pub fn attachment_name(bytes: &[u8], extension: &str) -> String {
+    let digest: String = Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let extension = extension.trim_start_matches('.').to_ascii_lowercase();
+    if extension.is_empty() {
+        digest
+    } else {
+        format!("{}.{}", digest, extension)
+    }
+}
+
+/// Whether `name` could have come from [`attachment_name`]; guards every path built from input.
+pub fn is_valid_name(name: &str) -> bool {
+    let (digest, extension) = match name.split_once('.') {
+        Some((digest, extension)) => (digest, Some(extension)),
+        None => (name, None),
+    };
+    let hex = digest.len() == 64
+        && digest
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c));
+    hex && extension.map_or(true, |ext| {
+        (1..=MAX_EXTENSION_LEN).contains(&ext.len())
+            && ext
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+    })
+}
+
+/// Stores `bytes` in `dir` and returns the attachment name; identical content is stored once.
+pub fn store_bytes(dir: &Path, bytes: &[u8], extension: &str) -> Result<String, String> {
+    let name = attachment_name(bytes, extension);
+    if !is_valid_name(&name) {
+        return Err(format!("Unsupported file extension: {}", extension));
+    }
+    let path = dir.join(&name);
+    if !path.exists() {
+        let tmp = dir.join(format!("{}.tmp", name));
+        fs::write(&tmp, bytes).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, &path).map_err(|e| e.to_string())?;
+    }
+    Ok(name)
+}
+
+/// Copies the file at `source` into the store.
+pub fn store_file(dir: &Path, source: &Path) -> Result<String, String> {
+    let bytes = fs::read(source).map_err(|e| format!("{}: {}", source.display(), e))?;
+    let extension = source
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_string())
+        .unwrap_or_default();
+    store_bytes(dir, &bytes, &extension)
+}
+
+/// Markdown image link to a stored attachment.
+pub fn markdown_link(name: &str, alt: &str) -> String {
+    let alt: String = alt.chars().filter(|c| !matches!(c, '[' | ']')).collect();
+    format!("![{}]({}{})", alt, LINK_PREFIX, name)
+}
+
+/// Attachment names linked from `content`, in order of appearance.
+pub fn references(content: &str) -> Vec<String> {
+    content
+        .match_indices(LINK_PREFIX)
+        .filter_map(|(start, _)| {
+            let rest = &content[start + LINK_PREFIX.len()..];
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '.'))
+                .unwrap_or(rest.len());
+            let name = rest[..end].trim_end_matches('.');
+            is_valid_name(name).then(|| name.to_string())
+        })
+        .collect()
+}
+
+/// How many notes link to each attachment.
+///
+/// Deleted notes count too: their tombstones keep the content and can still win a sync merge.
+pub fn reference_counts(notes: &[Note]) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for note in notes {
+        let mut names = references(&note.content);
+        names.sort();
+        names.dedup();
+        for name in names {
+            *counts.entry(name).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct AttachmentInfo {
+    pub name: String,
+    pub size: u64,
+    /// Number of notes linking to it.
+    pub references: usize,
+}
+
+/// Stored attachments with their reference counts, by name.
+pub fn list(dir: &Path, counts: &BTreeMap<String, usize>) -> Result<Vec<AttachmentInfo>, String> {
+    let mut attachments = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !is_valid_name(&name) {
+            continue;
+        }
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        let references = counts.get(&name).copied().unwrap_or(0);
+        attachments.push(AttachmentInfo {
+            name,
+            size,
+            references,
+        });
+    }
+    attachments.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(attachments)
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct GcReport {
+    pub removed: Vec<String>,
+    pub freed_bytes: u64,
+}
+
+/// Removes unreferenced attachments and leftover temporary files older than `grace`.
+pub fn collect_garbage(
+    dir: &Path,
+    counts: &BTreeMap<String, usize>,
+    grace: Duration,
+    now: SystemTime,
+) -> Result<GcReport, String> {
+    let mut report = GcReport::default();
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let stray_tmp = name.strip_suffix(".tmp").is_some_and(is_valid_name);
+        if !stray_tmp && (!is_valid_name(&name) || counts.contains_key(&name)) {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .unwrap_or_default();
+        if age < grace {
+            continue;
+        }
+        fs::remove_file(entry.path()).map_err(|e| e.to_string())?;
+        report.freed_bytes += metadata.len();
+        report.removed.push(name);
+    }
+    report.removed.sort();
+    Ok(report)
+}
+
+fn mime_type(name: &str) -> &'static str {
+    match name.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("svg") => "image/svg+xml",
+        Some("bmp") => "image/bmp",
+        Some("pdf") => "application/pdf",
+        Some("txt" | "md") => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+fn counts_for(app: &tauri::AppHandle) -> Result<BTreeMap<String, usize>, String> {
+    let state = app.state::<NotesState>();
+    let store = state.0.lock().map_err(|e| e.to_string())?;
+    Ok(reference_counts(&store.notes))
+}
+
+/// Collects garbage unless note history is on: its revisions are kept indefinitely
+/// and may link to any attachment, so nothing is removed then.
+fn collect_for(app: &tauri::AppHandle) -> Result<GcReport, String> {
+    if crate::history::HistorySettings::load(app).enabled {
+        log::info!("Attachments: kept while note history is enabled");
+        return Ok(GcReport::default());
+    }
+    collect_garbage(
+        &attachments_dir(app),
+        &counts_for(app)?,
+        GC_GRACE,
+        SystemTime::now(),
+    )
+}
+
+/// Serves `attachment://localhost/<name>` to the webviews.
+pub fn protocol(app: &tauri::AppHandle, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let name = request.uri().path().trim_start_matches('/');
+    let bytes = is_valid_name(name)
+        .then(|| fs::read(attachments_dir(app).join(name)).ok())
+        .flatten();
+    let response = match bytes {
+        Some(bytes) => Response::builder()
+            .header(header::CONTENT_TYPE, mime_type(name))
+            // Content-addressed, so a name always maps to the same bytes
+            .header(header::CACHE_CONTROL, "max-age=31536000, immutable")
+            .body(bytes),
+        None => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Vec::new()),
+    };
+    response.unwrap_or_default()
+}
+
+/// Collects garbage once the app has started.
+pub fn init(app: &tauri::AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || match collect_for(&app) {
+        Ok(report) if !report.removed.is_empty() => log::info!(
+            "Attachments: removed {} unreferenced ({} bytes)",
+            report.removed.len(),
+            report.freed_bytes
+        ),
+        Ok(_) => {}
+        Err(e) => log::warn!("Attachments: {}", e),
+    });
+}
+
+/// Stores the raw request body; the extension comes in the `x-attachment-extension` header.
+#[tauri::command]
+pub fn add_attachment(
+    app: tauri::AppHandle,
+    request: tauri::ipc::Request<'_>,
+) -> Result<String, String> {
+    log::debug!("Command: add_attachment called");
+    let InvokeBody::Raw(bytes) = request.body() else {
+        return Err("Expected raw attachment bytes".to_string());
+    };
+    let extension = request
+        .headers()
+        .get(EXTENSION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    store_bytes(&attachments_dir(&app), bytes, extension)
+}
+
+#[tauri::command]
+pub fn read_attachment(
+    app: tauri::AppHandle,
+    name: String,
+) -> Result<tauri::ipc::Response, String> {
+    log::debug!("Command: read_attachment called for {}", name);
+    if !is_valid_name(&name) {
+        return Err(format!("Invalid attachment name: {}", name));
+    }
+    let bytes = fs::read(attachments_dir(&app).join(&name)).map_err(|e| e.to_string())?;
+    Ok(tauri::ipc::Response::new(bytes))
+}
+
+/// Deletes an attachment no note links to.
+#[tauri::command]
+pub fn delete_attachment(app: tauri::AppHandle, name: String) -> Result<(), String> {
+    log::debug!("Command: delete_attachment called for {}", name);
+    if !is_valid_name(&name) {
+        return Err(format!("Invalid attachment name: {}", name));
+    }
+    if let Some(count) = counts_for(&app)?.get(&name) {
+        return Err(format!("Attachment is still used by {} notes", count));
+    }
+    fs::remove_file(attachments_dir(&app).join(&name)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_attachments(app: tauri::AppHandle) -> Result<Vec<AttachmentInfo>, String> {
+    log::debug!("Command: list_attachments called");
+    list(&attachments_dir(&app), &counts_for(&app)?)
+}
+
+#[tauri::command]
+pub fn collect_attachments(app: tauri::AppHandle) -> Result<GcReport, String> {
+    log::debug!("Command: collect_attachments called");
+    collect_for(&app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_content_addressed() {
+        let a = attachment_name(b"png bytes", "PNG");
+        assert_eq!(a, attachment_name(b"png bytes", ".png"));
+        assert_ne!(a, attachment_name(b"other bytes", "png"));
+        assert!(a.ends_with(".png"));
+        assert_eq!(a.len(), 64 + 4);
+    }
+
+    #[test]
+    fn identical_content_is_stored_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = store_bytes(dir.path(), b"image", "png").unwrap();
+        let second = store_bytes(dir.path(), b"image", "png").unwrap();
+        assert_eq!(first, second);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn only_generated_names_are_valid() {
+        let name = attachment_name(b"x", "png");
+        assert!(is_valid_name(&name));
+        assert!(is_valid_name(&attachment_name(b"x", "")));
+        assert!(!is_valid_name("../notes.json"));
+        assert!(!is_valid_name(&format!("{}.PNG", &name[..64])));
+        assert!(!is_valid_name(&format!("{}.png/..", &name[..64])));
+        assert!(!is_valid_name(&name[1..]));
+    }
+
+    #[test]
+    fn unsupported_extensions_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(store_bytes(dir.path(), b"x", "../../evil").is_err());
+        assert!(store_bytes(dir.path(), b"x", "averyverylongext").is_err());
+    }
+
+    #[test]
+    fn references_are_counted_once_per_note_including_deleted() {
+        let a = attachment_name(b"a", "png");
+        let b = attachment_name(b"b", "jpg");
+        let note = |content: String, deleted: bool| Note {
+            content,
+            deleted,
+            ..Note::new()
+        };
+        let notes = [
+            note(
+                format!("![x](attachment:{a}) and ![y](attachment:{a})."),
+                false,
+            ),
+            note(
+                format!("![x]({LINK_PREFIX}{a})\n![z]({LINK_PREFIX}{b})"),
+                false,
+            ),
+            note(format!("![z](attachment:{b})"), true),
+            note("attachment:nothing-here".to_string(), false),
+        ];
+        assert_eq!(references(&notes[0].content), [a.clone(), a.clone()]);
+        let counts = reference_counts(&notes);
+        assert_eq!(counts.get(&a), Some(&2));
+        assert_eq!(counts.get(&b), Some(&2));
+        assert_eq!(counts.len(), 2);
+    }
+
+    #[test]
+    fn garbage_collection_keeps_referenced_and_recent_attachments() {
+        let dir = tempfile::tempdir().unwrap();
+        let used = store_bytes(dir.path(), b"used", "png").unwrap();
+        let unused = store_bytes(dir.path(), b"unused", "png").unwrap();
+        fs::write(dir.path().join("notes.txt"), "not ours").unwrap();
+        let counts = BTreeMap::from([(used.clone(), 1)]);
+
+        let now = SystemTime::now();
+        let report = collect_garbage(dir.path(), &counts, GC_GRACE, now).unwrap();
+        assert_eq!(report, GcReport::default());
+
+        let later = now + GC_GRACE + Duration::from_secs(1);
+        let report = collect_garbage(dir.path(), &counts, GC_GRACE, later).unwrap();
+        assert_eq!(report.removed, [unused]);
+        assert_eq!(report.freed_bytes, 6);
+        assert!(dir.path().join(&used).exists());
+        assert!(dir.path().join("notes.txt").exists());
+    }
+
+    #[test]
+    fn listing_reports_sizes_and_references() {
+        let dir = tempfile::tempdir().unwrap();
+        let name = store_bytes(dir.path(), b"12345", "gif").unwrap();
+        let counts = BTreeMap::from([(name.clone(), 3)]);
+        assert_eq!(
+            list(dir.path(), &counts).unwrap(),
+            [AttachmentInfo {
+                name,
+                size: 5,
+                references: 3
+            }]
+        );
+    }
+
+    #[test]
+    fn links_escape_brackets_in_alt_text() {
+        assert_eq!(
+            markdown_link("abc.png", "shot [1]"),
+            "![shot 1](attachment:abc.png)"
+        );
+    }
+}
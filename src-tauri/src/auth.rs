@@ -1,38 +1,23 @@
+use crate::diagnostics::{self, Level};
+use crate::launcher::{self, CustomLauncher, LauncherSettings};
+use base64::Engine;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::State;
 use url::Url;
 
-#[tauri::command]
-pub fn open_external_url(url: String) -> Result<(), String> {
-    log::debug!("Attempting to open URL: {}", url);
-    std::fs::write("DEBUG_URL.txt", format!("Attempting to open: {}", url)).ok();
-
-    if let Err(e) = opener::open_browser(&url) {
-        log::warn!(
-            "opener::open_browser failed: {}. Trying WSL fallback via powershell.exe",
-            e
-        );
-        std::fs::write("DEBUG_ERROR_OPENER.txt", format!("opener failed: {}", e)).ok();
-
-        // WSL2 specific fallback: Use powershell.exe to open the browser on the Windows host
-        std::process::Command::new("powershell.exe")
-            .arg("-NoProfile")
-            .arg("-Command")
-            .arg(format!("Start-Process '{}'", url.replace("'", "''")))
-            .spawn()
-            .map_err(|err| {
-                log::error!("WSL fallback failed: {}", err);
-                std::fs::write(
-                    "DEBUG_ERROR_FALLBACK.txt",
-                    format!("fallback failed: {}", err),
-                )
-                .ok();
-                format!(
-                    "Failed to open browser (opener error: {}, fallback error: {})",
-                    e, err
-                )
-            })?;
-    }
-    Ok(())
-}
+const AUTH_ENDPOINT: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+const AUTH_SCOPES: &str = "https://www.googleapis.com/auth/drive.file";
+const AUTH_SERVER_ADDR: &str = "127.0.0.1:51737";
+const REDIRECT_URI: &str = "http://localhost:51737/";
+const AUTH_TIMEOUT: Duration = Duration::from_secs(180);
+/// How often the wait loop checks for timeout and cancellation.
+const AUTH_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// How long to wait for a just-cancelled server to release the fixed port.
+const AUTH_REBIND_WINDOW: Duration = Duration::from_secs(3);
 
 #[tauri::command]
 pub fn frontend_log(level: String, message: String) {
@@ -43,73 +28,667 @@ pub fn frontend_log(level: String, message: String) {
     }
 }
 
-#[tauri::command]
-pub async fn start_google_auth_server() -> Result<String, String> {
-    log::debug!("Command: start_google_auth_server called");
-    log::debug!("Attempting to bind auth server to 127.0.0.1:51737");
-    std::fs::write("DEBUG_SERVER_INIT.txt", "Attempting bind").ok();
-    let server = tiny_http::Server::http("127.0.0.1:51737").map_err(|e| {
-        log::error!("Failed to start auth server: {}", e);
-        std::fs::write("DEBUG_SERVER_ERROR.txt", e.to_string()).ok();
-        format!("Failed to start server: {}", e)
-    })?;
-
-    log::info!(
-        "Auth server started on http://127.0.0.1:51737 (accessible via localhost from host)"
-    );
-    std::fs::write("DEBUG_SERVER_STARTED.txt", "Server up and waiting").ok();
+/// PKCE verifier and its S256 challenge (RFC 7636).
+pub struct Pkce {
+    pub verifier: String,
+    pub challenge: String,
+}
 
-    let start_time = std::time::Instant::now();
-    let timeout = std::time::Duration::from_secs(180); // 3 minutes timeout
+fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).expect("OS random number generator unavailable");
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+pub fn pkce_challenge(verifier: &str) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
 
+impl Pkce {
+    pub fn generate() -> Self {
+        let verifier = random_token();
+        let challenge = pkce_challenge(&verifier);
+        Self {
+            verifier,
+            challenge,
+        }
+    }
+}
+
+pub fn build_auth_url(client_id: &str, redirect_uri: &str, challenge: &str, state: &str) -> String {
+    let mut url = Url::parse(AUTH_ENDPOINT).expect("auth endpoint is a valid URL");
+    url.query_pairs_mut()
+        .append_pair("client_id", client_id)
+        .append_pair("redirect_uri", redirect_uri)
+        .append_pair("response_type", "code")
+        .append_pair("scope", AUTH_SCOPES)
+        .append_pair("prompt", "select_account")
+        .append_pair("code_challenge", challenge)
+        .append_pair("code_challenge_method", "S256")
+        .append_pair("state", state);
+    url.into()
+}
+
+/// What the loopback server makes of one incoming request.
+#[derive(Debug, PartialEq)]
+pub enum Callback {
+    Code(String),
+    Denied {
+        error: String,
+        description: Option<String>,
+    },
+    /// Looks like a callback but does not carry our `state`; keep waiting.
+    Forged,
+    /// Anything else (`/favicon.ico`, ...); keep waiting.
+    NotFound,
+}
+
+/// Interprets a request path, accepting it only if `state` matches ours.
+pub fn parse_callback(path: &str, expected_state: &str) -> Callback {
+    let Ok(url) = Url::parse(&format!("http://localhost{}", path)) else {
+        return Callback::NotFound;
+    };
+    let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+    if url.path() != "/" || !(params.contains_key("code") || params.contains_key("error")) {
+        return Callback::NotFound;
+    }
+    if params.get("state").map(String::as_str) != Some(expected_state) {
+        return Callback::Forged;
+    }
+    if let Some(code) = params.get("code") {
+        return Callback::Code(code.clone());
+    }
+    Callback::Denied {
+        error: params.get("error").cloned().unwrap_or_default(),
+        description: params.get("error_description").cloned(),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PageLanguage {
+    English,
+    Japanese,
+}
+
+impl PageLanguage {
+    /// Picks the first supported language from an `Accept-Language` header.
+    pub fn from_accept_language(header: Option<&str>) -> Self {
+        header
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|part| {
+                let tag = part.split(';').next()?.trim().to_ascii_lowercase();
+                if tag.starts_with("ja") {
+                    Some(Self::Japanese)
+                } else if tag.starts_with("en") {
+                    Some(Self::English)
+                } else {
+                    None
+                }
+            })
+            .next()
+            .unwrap_or(Self::English)
+    }
+
+    fn code(self) -> &'static str {
+        match self {
+            Self::English => "en",
+            Self::Japanese => "ja",
+        }
+    }
+}
+
+/// Which page the loopback server shows in the browser.
+#[derive(Debug, PartialEq)]
+pub enum CallbackPage<'a> {
+    Success,
+    Denied {
+        error: &'a str,
+        description: Option<&'a str>,
+    },
+    Invalid,
+    NotFound,
+}
+
+const CALLBACK_PAGE_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title} - Markdown Notes</title>
+<style>
+  body { font-family: system-ui, sans-serif; background: #fef3c7; color: #1f2937;
+         display: flex; align-items: center; justify-content: center; min-height: 100vh; margin: 0; }
+  main { background: #fff; border-radius: 12px; padding: 2rem 2.5rem; max-width: 28rem;
+         box-shadow: 0 4px 16px rgba(0, 0, 0, 0.1); text-align: center; }
+  h1 { font-size: 1.25rem; margin: 0 0 0.75rem; color: {accent}; }
+  p { margin: 0.5rem 0; line-height: 1.5; }
+  code { background: #f3f4f6; border-radius: 4px; padding: 0.1rem 0.3rem; word-break: break-all; }
+</style>
+</head>
+<body>
+<main>
+<h1>{title}</h1>
+<p>{message}</p>
+{detail}
+</main>
+{script}
+</body>
+</html>
+"#;
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Renders a self-contained HTML page for the browser tab that completed the redirect.
+pub fn render_callback_page(page: &CallbackPage, language: PageLanguage) -> String {
+    use PageLanguage::{English, Japanese};
+    let (title, message) = match (page, language) {
+        (CallbackPage::Success, English) => (
+            "Signed in",
+            "You can close this tab and return to Markdown Notes.",
+        ),
+        (CallbackPage::Success, Japanese) => (
+            "サインインしました",
+            "このタブを閉じて Markdown Notes に戻ってください。",
+        ),
+        (CallbackPage::Denied { .. }, English) => (
+            "Sign-in failed",
+            "Google did not grant access. Return to Markdown Notes and try again.",
+        ),
+        (CallbackPage::Denied { .. }, Japanese) => (
+            "サインインできませんでした",
+            "Google からアクセスが許可されませんでした。Markdown Notes に戻ってもう一度お試しください。",
+        ),
+        (CallbackPage::Invalid, English) => (
+            "Invalid request",
+            "This sign-in link does not belong to the current sign-in attempt.",
+        ),
+        (CallbackPage::Invalid, Japanese) => (
+            "無効なリクエスト",
+            "このリンクは現在のサインイン操作のものではありません。",
+        ),
+        (CallbackPage::NotFound, English) => ("Not found", "There is nothing here."),
+        (CallbackPage::NotFound, Japanese) => ("見つかりません", "このページは存在しません。"),
+    };
+
+    let detail = match page {
+        CallbackPage::Denied { error, description } => {
+            let label = match language {
+                English => "Error",
+                Japanese => "エラー",
+            };
+            let mut detail = format!("<p>{}: <code>{}</code></p>", label, escape_html(error));
+            if let Some(description) = description {
+                detail.push_str(&format!("\n<p>{}</p>", escape_html(description)));
+            }
+            detail
+        }
+        _ => String::new(),
+    };
+    // Browsers only let scripts close tabs they opened; harmless otherwise.
+    let script = match page {
+        CallbackPage::Success => "<script>setTimeout(() => window.close(), 1500);</script>",
+        _ => "",
+    };
+    let accent = match page {
+        CallbackPage::Success => "#047857",
+        _ => "#b91c1c",
+    };
+
+    CALLBACK_PAGE_TEMPLATE
+        .replace("{lang}", language.code())
+        .replace("{accent}", accent)
+        .replace("{title}", title)
+        .replace("{message}", message)
+        .replace("{detail}", &detail)
+        .replace("{script}", script)
+}
+
+fn respond_with_page(request: tiny_http::Request, status: u16, page: &CallbackPage) {
+    let accept_language = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Accept-Language"))
+        .map(|h| h.value.as_str().to_string());
+    let language = PageLanguage::from_accept_language(accept_language.as_deref());
+    let response = tiny_http::Response::from_string(render_callback_page(page, language))
+        .with_status_code(status)
+        .with_header(
+            tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=utf-8"[..])
+                .unwrap(),
+        );
+    request.respond(response).ok();
+}
+
+/// Serves the redirect until a callback carrying our `state` arrives.
+pub fn wait_for_code(
+    server: &tiny_http::Server,
+    state: &str,
+    timeout: Duration,
+    cancelled: &AtomicBool,
+) -> Result<String, String> {
+    let start_time = Instant::now();
     loop {
+        if cancelled.load(Ordering::SeqCst) {
+            log::info!("Auth server cancelled");
+            diagnostics::record(Level::Info, "auth", "Sign-in cancelled", &[]);
+            return Err("Authentication was cancelled.".to_string());
+        }
         if start_time.elapsed() > timeout {
-            log::error!("Auth server timed out after 3 minutes");
-            std::fs::write("DEBUG_SERVER_TIMEOUT.txt", "Timed out").ok();
+            log::error!("Auth server timed out after {:?}", timeout);
+            diagnostics::record(Level::Error, "auth", "Sign-in timed out", &[]);
             return Err("Authentication timed out. Please try again.".to_string());
         }
 
         // Use recv_timeout to avoid blocking forever, allowing us to check the timeout condition
-        if let Ok(Some(request)) = server.recv_timeout(std::time::Duration::from_secs(1)) {
-            let url_str = request.url();
-            log::info!("Auth server received request: {}", url_str);
-
-            // In Authorization Code flow, the code comes in the query string: /?code=...
-            if url_str.contains("code=") {
-                log::info!("Auth server: Code parameter found");
-                let full_url = format!("http://localhost{}", url_str);
-                let parsed_url = Url::parse(&full_url).map_err(|e| {
-                    log::error!("Auth server: URL parse error: {}", e);
-                    e.to_string()
-                })?;
-                let params: std::collections::HashMap<_, _> =
-                    parsed_url.query_pairs().into_owned().collect();
-
-                if let Some(code) = params.get("code") {
-                    log::info!("Auth server: Code extracted successfully");
-                    let response = tiny_http::Response::from_string(
-                        "Authentication successful! You can close this tab and return to the app.",
-                    )
-                    .with_header(
-                        tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/plain"[..])
-                            .unwrap(),
-                    );
-                    request.respond(response).ok();
-                    return Ok(code.clone());
-                }
-            } else if url_str.contains("error=") {
-                log::warn!("Auth server: Error parameter found: {}", url_str);
-                let response = tiny_http::Response::from_string(
-                    "Authentication failed. Please check the app for details.",
+        let Ok(Some(request)) = server.recv_timeout(AUTH_POLL_INTERVAL) else {
+            continue;
+        };
+        let path = request.url().to_string();
+        log::info!(
+            "Auth server received request: {}",
+            path.split('?').next().unwrap_or("")
+        );
+
+        match parse_callback(&path, state) {
+            Callback::Code(code) => {
+                log::info!("Auth server: Code extracted successfully");
+                diagnostics::record(Level::Info, "auth", "Authorization code received", &[]);
+                respond_with_page(request, 200, &CallbackPage::Success);
+                return Ok(code);
+            }
+            Callback::Denied { error, description } => {
+                log::warn!("Auth server: Authorization denied: {}", error);
+                diagnostics::record(
+                    Level::Warn,
+                    "auth",
+                    "Authorization denied",
+                    &[("error", &error)],
                 );
-                request.respond(response).ok();
-                return Err("Authentication failed or was denied.".to_string());
-            } else {
-                log::debug!("Auth server: Non-auth request received: {}", url_str);
-                // Fallback for favicon.ico or other requests
-                let response = tiny_http::Response::from_string("Waiting for authentication...");
-                request.respond(response).ok();
+                let page = CallbackPage::Denied {
+                    error: &error,
+                    description: description.as_deref(),
+                };
+                respond_with_page(request, 200, &page);
+                return Err(match description {
+                    Some(description) => format!("Authentication failed: {}", description),
+                    None => format!("Authentication failed: {}", error),
+                });
+            }
+            Callback::Forged => {
+                log::warn!("Auth server: Rejected callback with a missing or wrong state");
+                diagnostics::record(Level::Warn, "auth", "Rejected callback with bad state", &[]);
+                respond_with_page(request, 400, &CallbackPage::Invalid);
+            }
+            Callback::NotFound => {
+                log::debug!("Auth server: Non-auth request received");
+                respond_with_page(request, 404, &CallbackPage::NotFound);
+            }
+        }
+    }
+}
+
+/// Result of a completed browser sign-in, ready for the token exchange.
+#[derive(Serialize, Debug)]
+pub struct AuthCode {
+    pub code: String,
+    pub code_verifier: String,
+    pub redirect_uri: String,
+}
+
+/// Tracks the in-flight sign-in so only one loopback server runs at a time.
+#[derive(Default)]
+pub struct AuthServerState {
+    active: Mutex<Option<Arc<AtomicBool>>>,
+}
+
+impl AuthServerState {
+    /// Cancels any running flow and registers a new one.
+    pub fn begin(&self) -> Arc<AtomicBool> {
+        let flag = Arc::new(AtomicBool::new(false));
+        if let Ok(mut active) = self.active.lock() {
+            if let Some(previous) = active.replace(Arc::clone(&flag)) {
+                log::info!("Auth server: cancelling previous sign-in attempt");
+                previous.store(true, Ordering::SeqCst);
+            }
+        }
+        flag
+    }
+
+    /// Cancels the running flow, returning whether there was one.
+    pub fn cancel(&self) -> bool {
+        match self.active.lock().ok().and_then(|mut active| active.take()) {
+            Some(flag) => {
+                flag.store(true, Ordering::SeqCst);
+                true
             }
+            None => false,
         }
     }
+
+    /// Unregisters `flag` unless a newer flow has replaced it.
+    pub fn finish(&self, flag: &Arc<AtomicBool>) {
+        if let Ok(mut active) = self.active.lock() {
+            if active.as_ref().is_some_and(|a| Arc::ptr_eq(a, flag)) {
+                *active = None;
+            }
+        }
+    }
+}
+
+/// Binds the loopback server and returns it with the matching redirect URI.
+///
+/// The fixed port is retried briefly because a cancelled server releases it
+/// only when its wait loop notices the cancellation.
+pub fn bind_auth_server(ephemeral_port: bool) -> Result<(tiny_http::Server, String), String> {
+    if ephemeral_port {
+        let server = tiny_http::Server::http("127.0.0.1:0")
+            .map_err(|e| format!("Failed to start server: {}", e))?;
+        let port = server
+            .server_addr()
+            .to_ip()
+            .map(|addr| addr.port())
+            .ok_or("Auth server has no TCP address")?;
+        return Ok((server, format!("http://localhost:{}/", port)));
+    }
+
+    let start_time = Instant::now();
+    loop {
+        match tiny_http::Server::http(AUTH_SERVER_ADDR) {
+            Ok(server) => return Ok((server, REDIRECT_URI.to_string())),
+            Err(e) if start_time.elapsed() > AUTH_REBIND_WINDOW => {
+                log::error!("Failed to start auth server: {}", e);
+                diagnostics::record(
+                    Level::Error,
+                    "auth",
+                    "Cannot bind loopback server",
+                    &[("error", &e.to_string())],
+                );
+                return Err(format!(
+                    "Failed to start server: {} (is port {} in use?)",
+                    e,
+                    AUTH_SERVER_ADDR.rsplit(':').next().unwrap_or_default()
+                ));
+            }
+            Err(_) => std::thread::sleep(AUTH_POLL_INTERVAL),
+        }
+    }
+}
+
+/// Runs the whole authorization-code flow: PKCE, browser, loopback callback.
+///
+/// Starting a new flow cancels the previous one.
+#[tauri::command]
+pub async fn start_google_auth_server(
+    app: tauri::AppHandle,
+    auth: State<'_, AuthServerState>,
+    client_id: String,
+    ephemeral_port: Option<bool>,
+) -> Result<AuthCode, String> {
+    log::debug!("Command: start_google_auth_server called");
+    let cancelled = auth.begin();
+    let launcher = LauncherSettings::load(&app).custom;
+    let result = run_auth_flow(
+        &client_id,
+        ephemeral_port.unwrap_or(false),
+        launcher.as_ref(),
+        &cancelled,
+    );
+    auth.finish(&cancelled);
+    result
+}
+
+fn run_auth_flow(
+    client_id: &str,
+    ephemeral_port: bool,
+    launcher: Option<&CustomLauncher>,
+    cancelled: &AtomicBool,
+) -> Result<AuthCode, String> {
+    let (server, redirect_uri) = bind_auth_server(ephemeral_port)?;
+    log::info!("Auth server started, redirect URI {}", redirect_uri);
+    diagnostics::record(
+        Level::Info,
+        "auth",
+        "Sign-in started",
+        &[("redirect_uri", &redirect_uri)],
+    );
+
+    let pkce = Pkce::generate();
+    let state = random_token();
+    launcher::open_url(
+        &build_auth_url(client_id, &redirect_uri, &pkce.challenge, &state),
+        launcher,
+    )?;
+
+    let code = wait_for_code(&server, &state, AUTH_TIMEOUT, cancelled)?;
+    Ok(AuthCode {
+        code,
+        code_verifier: pkce.verifier,
+        redirect_uri,
+    })
+}
+
+/// Stops an in-flight sign-in; its `start_google_auth_server` call returns an error.
+#[tauri::command]
+pub fn cancel_google_auth(auth: State<'_, AuthServerState>) -> bool {
+    log::debug!("Command: cancel_google_auth called");
+    auth.cancel()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ── PKCE ──
+
+    #[test]
+    fn pkce_challenge_matches_rfc7636_example() {
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[test]
+    fn pkce_verifier_is_url_safe_and_long_enough() {
+        let pkce = Pkce::generate();
+        assert!(pkce.verifier.len() >= 43);
+        assert!(pkce
+            .verifier
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_ne!(pkce.verifier, Pkce::generate().verifier);
+    }
+
+    #[test]
+    fn auth_url_carries_challenge_and_state() {
+        let url = Url::parse(&build_auth_url("client", REDIRECT_URI, "chal", "st8")).unwrap();
+        let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(params["code_challenge"], "chal");
+        assert_eq!(params["code_challenge_method"], "S256");
+        assert_eq!(params["state"], "st8");
+        assert_eq!(params["redirect_uri"], REDIRECT_URI);
+    }
+
+    // ── Callback validation ──
+
+    #[test]
+    fn callback_with_matching_state_yields_code() {
+        assert_eq!(
+            parse_callback("/?state=abc&code=4%2F0Ax", "abc"),
+            Callback::Code("4/0Ax".to_string())
+        );
+    }
+
+    #[test]
+    fn callback_with_wrong_or_missing_state_is_forged() {
+        assert_eq!(
+            parse_callback("/?state=evil&code=x", "abc"),
+            Callback::Forged
+        );
+        assert_eq!(parse_callback("/?code=x", "abc"), Callback::Forged);
+        assert_eq!(
+            parse_callback("/?error=access_denied", "abc"),
+            Callback::Forged
+        );
+    }
+
+    #[test]
+    fn callback_error_is_reported() {
+        assert_eq!(
+            parse_callback(
+                "/?state=abc&error=access_denied&error_description=User+said+no",
+                "abc"
+            ),
+            Callback::Denied {
+                error: "access_denied".to_string(),
+                description: Some("User said no".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn unrelated_paths_are_not_found() {
+        assert_eq!(parse_callback("/favicon.ico", "abc"), Callback::NotFound);
+        assert_eq!(parse_callback("/", "abc"), Callback::NotFound);
+        assert_eq!(
+            parse_callback("/other?state=abc&code=x", "abc"),
+            Callback::NotFound
+        );
+    }
+
+    // ── Callback pages ──
+
+    #[test]
+    fn language_follows_accept_language() {
+        let pick = PageLanguage::from_accept_language;
+        assert_eq!(
+            pick(Some("ja-JP,ja;q=0.9,en;q=0.8")),
+            PageLanguage::Japanese
+        );
+        assert_eq!(pick(Some("en-US,ja;q=0.5")), PageLanguage::English);
+        assert_eq!(pick(Some("fr-FR,ja;q=0.5")), PageLanguage::Japanese);
+        assert_eq!(pick(Some("de")), PageLanguage::English);
+        assert_eq!(pick(None), PageLanguage::English);
+    }
+
+    #[test]
+    fn success_page_is_localized_and_auto_closes() {
+        let en = render_callback_page(&CallbackPage::Success, PageLanguage::English);
+        assert!(en.contains("<html lang=\"en\">"));
+        assert!(en.contains("Signed in"));
+        assert!(en.contains("window.close()"));
+
+        let ja = render_callback_page(&CallbackPage::Success, PageLanguage::Japanese);
+        assert!(ja.contains("<html lang=\"ja\">"));
+        assert!(ja.contains("サインインしました"));
+    }
+
+    #[test]
+    fn error_page_shows_escaped_details() {
+        let page = CallbackPage::Denied {
+            error: "access_denied",
+            description: Some("<script>alert(1)</script>"),
+        };
+        let html = render_callback_page(&page, PageLanguage::English);
+        assert!(html.contains("<code>access_denied</code>"));
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(!html.contains("<script>alert(1)"));
+        assert!(!html.contains("window.close()"));
+    }
+
+    #[test]
+    fn server_rejects_injected_code_and_accepts_real_callback() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", server.server_addr().to_ip().unwrap());
+
+        let client = std::thread::spawn(move || {
+            let favicon = ureq::get(&format!("{}/favicon.ico", base)).call();
+            assert!(matches!(favicon, Err(ureq::Error::Status(404, _))));
+            let forged = ureq::get(&format!("{}/?code=injected&state=guess", base)).call();
+            assert!(matches!(forged, Err(ureq::Error::Status(400, _))));
+            ureq::get(&format!("{}/?code=real&state=s3cret", base))
+                .call()
+                .unwrap();
+        });
+
+        let cancelled = AtomicBool::new(false);
+        let code = wait_for_code(&server, "s3cret", Duration::from_secs(10), &cancelled).unwrap();
+        client.join().unwrap();
+        assert_eq!(code, "real");
+    }
+
+    #[test]
+    fn server_reports_denial_with_localized_page() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", server.server_addr().to_ip().unwrap());
+
+        let client = std::thread::spawn(move || {
+            let response = ureq::get(&format!(
+                "{}/?state=s&error=access_denied&error_description=Denied+by+user",
+                base
+            ))
+            .set("Accept-Language", "ja")
+            .call()
+            .unwrap();
+            assert!(response.content_type().starts_with("text/html"));
+            response.into_string().unwrap()
+        });
+
+        let cancelled = AtomicBool::new(false);
+        let err = wait_for_code(&server, "s", Duration::from_secs(10), &cancelled).unwrap_err();
+        let html = client.join().unwrap();
+        assert!(err.contains("Denied by user"));
+        assert!(html.contains("サインインできませんでした"));
+        assert!(html.contains("Denied by user"));
+    }
+
+    // ── Port selection and cancellation ──
+
+    #[test]
+    fn ephemeral_port_reports_its_redirect_uri() {
+        let (server, redirect_uri) = bind_auth_server(true).unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+        assert_ne!(port, 0);
+        assert_eq!(redirect_uri, format!("http://localhost:{}/", port));
+    }
+
+    #[test]
+    fn cancelled_wait_returns_promptly() {
+        let (server, _) = bind_auth_server(true).unwrap();
+        let state = AuthServerState::default();
+        let cancelled = state.begin();
+
+        let started = Instant::now();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            assert!(state.cancel());
+            assert!(!state.cancel());
+        });
+        let result = wait_for_code(&server, "s", Duration::from_secs(30), &cancelled);
+        canceller.join().unwrap();
+
+        assert!(result.unwrap_err().contains("cancelled"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn new_flow_cancels_the_previous_one() {
+        let state = AuthServerState::default();
+        let first = state.begin();
+        let second = state.begin();
+        assert!(first.load(Ordering::SeqCst));
+        assert!(!second.load(Ordering::SeqCst));
+
+        // A finishing stale flow must not unregister the newer one
+        state.finish(&first);
+        assert!(state.cancel());
+        assert!(second.load(Ordering::SeqCst));
+    }
 }
@@ -0,0 +1,206 @@
+use crate::linked::{write_back_saved, WriteBack};
+use crate::notes::{app_data_file, Note};
+use crate::NotesState;
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::{Emitter, Manager, State, WebviewUrl, WebviewWindowBuilder};
+
+const CAPTURE_WINDOW_LABEL: &str = "capture";
+const CAPTURE_WIDTH: f64 = 420.0;
+const CAPTURE_HEIGHT: f64 = 200.0;
+const INBOX_TITLE: &str = "Inbox";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct CaptureSettings {
+    /// Note that captured text is appended to; created on first use.
+    #[serde(default)]
+    pub inbox_note_id: Option<String>,
+}
+
+impl CaptureSettings {
+    fn get_settings_path(app: &tauri::AppHandle) -> PathBuf {
+        app_data_file(app, "capture.json")
+    }
+
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        fs::read_to_string(Self::get_settings_path(app))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, app: &tauri::AppHandle) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(Self::get_settings_path(app), content).map_err(|e| e.to_string())
+    }
+}
+
+/// Appends `text` under a `## <timestamp>` heading, separated by a blank line.
+pub fn append_entry(content: &str, text: &str, timestamp: &str) -> String {
+    let body = content.trim_end();
+    let entry = format!("## {}\n\n{}\n", timestamp, text.trim());
+    if body.is_empty() {
+        entry
+    } else {
+        format!("{}\n\n{}", body, entry)
+    }
+}
+
+fn local_timestamp(now: DateTime<Utc>) -> String {
+    now.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+fn new_inbox() -> Note {
+    Note {
+        title: INBOX_TITLE.to_string(),
+        content: format!("# {}\n", INBOX_TITLE),
+        ..Note::new()
+    }
+}
+
+/// Appends to `note_id`, or to the inbox note when `None`, and returns the note.
+#[tauri::command]
+pub fn append_to_note(
+    app: tauri::AppHandle,
+    state: State<'_, NotesState>,
+    note_id: Option<String>,
+    text: String,
+) -> Result<Note, String> {
+    log::debug!("Command: append_to_note called for id: {:?}", note_id);
+    if text.trim().is_empty() {
+        return Err("Nothing to append".to_string());
+    }
+
+    let mut store = state.0.lock().map_err(|e| e.to_string())?;
+    let target_id = match note_id {
+        Some(id) => id,
+        None => {
+            let mut settings = CaptureSettings::load(&app);
+            let existing = settings
+                .inbox_note_id
+                .clone()
+                .filter(|id| store.get_note(id).is_some_and(|note| !note.deleted));
+            match existing {
+                Some(id) => id,
+                None => {
+                    let inbox = new_inbox();
+                    settings.inbox_note_id = Some(inbox.id.clone());
+                    settings.save(&app)?;
+                    let id = inbox.id.clone();
+                    store.add_note(inbox);
+                    id
+                }
+            }
+        }
+    };
+
+    let now = Utc::now();
+    let note = store
+        .notes
+        .iter_mut()
+        .find(|n| n.id == target_id && !n.deleted)
+        .ok_or("Note not found")?;
+    let entry_time = local_timestamp(now);
+    note.content = append_entry(&note.content, &text, &entry_time);
+    note.updated_at = now.to_rfc3339();
+    // A linked file edited elsewhere wins; add the entry to its text instead
+    if let WriteBack::Conflict(_) = write_back_saved(&app, note)? {
+        note.content = append_entry(&note.content, &text, &entry_time);
+        write_back_saved(&app, note)?;
+    }
+    let note = note.clone();
+    store.save(&app)?;
+
+    // An open editor would otherwise save its stale copy over the new entry
+    if app.get_webview_window(&note.id).is_some() {
+        app.emit_to(note.id.as_str(), "note-updated", &note).ok();
+    }
+    Ok(note)
+}
+
+#[tauri::command]
+pub fn get_capture_settings(app: tauri::AppHandle) -> CaptureSettings {
+    log::debug!("Command: get_capture_settings called");
+    CaptureSettings::load(&app)
+}
+
+#[tauri::command]
+pub fn set_inbox_note(
+    app: tauri::AppHandle,
+    state: State<'_, NotesState>,
+    note_id: Option<String>,
+) -> Result<(), String> {
+    log::debug!("Command: set_inbox_note called for id: {:?}", note_id);
+    if let Some(id) = &note_id {
+        let store = state.0.lock().map_err(|e| e.to_string())?;
+        store.get_note(id).ok_or("Note not found")?;
+    }
+    let mut settings = CaptureSettings::load(&app);
+    settings.inbox_note_id = note_id;
+    settings.save(&app)
+}
+
+/// Shows the capture window, creating it on first use.
+pub fn open_capture_window(app: &tauri::AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(CAPTURE_WINDOW_LABEL) {
+        window.show().map_err(|e| e.to_string())?;
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+    let window = WebviewWindowBuilder::new(
+        app,
+        CAPTURE_WINDOW_LABEL,
+        WebviewUrl::App("capture.html".into()),
+    )
+    .title("Inbox")
+    .inner_size(CAPTURE_WIDTH, CAPTURE_HEIGHT)
+    .center()
+    .resizable(false)
+    .always_on_top(true)
+    .skip_taskbar(true)
+    .focused(true)
+    .build()
+    .map_err(|e| e.to_string())?;
+    window.set_focus().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn open_capture(app: tauri::AppHandle) -> Result<(), String> {
+    log::debug!("Command: open_capture called");
+    open_capture_window(&app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_is_appended_after_a_blank_line() {
+        let content = append_entry("# Inbox\n", "  buy milk \n", "2026-10-19 09:30");
+        assert_eq!(content, "# Inbox\n\n## 2026-10-19 09:30\n\nbuy milk\n");
+
+        let content = append_entry(&content, "call Bob", "2026-10-19 10:00");
+        assert!(content.ends_with("buy milk\n\n## 2026-10-19 10:00\n\ncall Bob\n"));
+    }
+
+    #[test]
+    fn entry_in_empty_note_has_no_leading_blank_lines() {
+        assert_eq!(append_entry("\n\n", "idea", "t"), "## t\n\nidea\n");
+    }
+
+    #[test]
+    fn multi_line_text_is_kept() {
+        let content = append_entry("", "- one\n- two", "t");
+        assert_eq!(content, "## t\n\n- one\n- two\n");
+    }
+
+    #[test]
+    fn new_inbox_has_title_heading() {
+        let inbox = new_inbox();
+        assert_eq!(inbox.title, INBOX_TITLE);
+        assert!(inbox.content.starts_with("# Inbox"));
+    }
+}
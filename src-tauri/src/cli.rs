@@ -0,0 +1,437 @@
+//! Headless access to the note store for scripts, used by the `notes-cli` binary.
+
+use crate::notes::{with_store_lock, Note, NotesStore};
+use chrono::Utc;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Must match `identifier` in tauri.conf.json so both use the same data dir.
+const APP_IDENTIFIER: &str = "com.tauri.markdown-notes";
+const STORE_ENV: &str = "MARKDOWN_NOTES_STORE";
+
+pub const USAGE: &str = "\
+Usage: notes-cli [--store <notes.json>] <command>
+
+Commands:
+  list [--all]                          List notes (--all includes deleted ones)
+  show <id>                             Print a note's content
+  new [--title <t>] [--content <c>]     Create a note and print its id
+  edit <id> [--title <t>] [--content <c>] [--append <text>]
+  delete <id>                           Delete a note
+  search <query>                        List notes whose title or content match
+  export <file.json | dir>              Write all notes as JSON or as .md files
+  import <file.json | file.md>...       Add notes from JSON exports or Markdown files
+
+A content or append value of `-` is read from standard input.";
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    List {
+        all: bool,
+    },
+    Show {
+        id: String,
+    },
+    New {
+        title: Option<String>,
+        content: Option<String>,
+    },
+    Edit {
+        id: String,
+        title: Option<String>,
+        content: Option<String>,
+        append: Option<String>,
+    },
+    Delete {
+        id: String,
+    },
+    Search {
+        query: String,
+    },
+    Export {
+        path: PathBuf,
+    },
+    Import {
+        paths: Vec<PathBuf>,
+    },
+    Help,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Invocation {
+    pub store: Option<PathBuf>,
+    pub command: Command,
+}
+
+/// Parses the arguments after the program name.
+pub fn parse(args: &[String]) -> Result<Invocation, String> {
+    let mut store = None;
+    let mut positional: Vec<String> = Vec::new();
+    let mut options: Vec<(String, String)> = Vec::new();
+    let mut all = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                return Ok(Invocation {
+                    store,
+                    command: Command::Help,
+                });
+            }
+            "--all" => all = true,
+            "--store" | "--title" | "--content" | "--append" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| format!("{} needs a value", arg))?
+                    .clone();
+                if arg == "--store" {
+                    store = Some(PathBuf::from(value));
+                } else {
+                    options.push((arg.trim_start_matches('-').to_string(), value));
+                }
+            }
+            flag if flag.starts_with("--") => return Err(format!("Unknown option {}", flag)),
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    let option = |name: &str| {
+        options
+            .iter()
+            .rev()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+    };
+    let mut positional = positional.into_iter();
+    let Some(name) = positional.next() else {
+        return Ok(Invocation {
+            store,
+            command: Command::Help,
+        });
+    };
+    let mut required = |what: &str| {
+        positional
+            .next()
+            .ok_or_else(|| format!("{} needs {}", name, what))
+    };
+    let command = match name.as_str() {
+        "list" => Command::List { all },
+        "show" => Command::Show {
+            id: required("a note id")?,
+        },
+        "new" => Command::New {
+            title: option("title"),
+            content: option("content"),
+        },
+        "edit" => Command::Edit {
+            id: required("a note id")?,
+            title: option("title"),
+            content: option("content"),
+            append: option("append"),
+        },
+        "delete" => Command::Delete {
+            id: required("a note id")?,
+        },
+        "search" => Command::Search {
+            query: required("a query")?,
+        },
+        "export" => Command::Export {
+            path: required("a path")?.into(),
+        },
+        "import" => {
+            let paths: Vec<PathBuf> = positional.by_ref().map(PathBuf::from).collect();
+            if paths.is_empty() {
+                return Err("import needs at least one file".to_string());
+            }
+            Command::Import { paths }
+        }
+        "help" => Command::Help,
+        other => return Err(format!("Unknown command '{}'", other)),
+    };
+    Ok(Invocation { store, command })
+}
+
+/// `--store`, then `$MARKDOWN_NOTES_STORE`, then the app's own notes.json.
+pub fn store_path(explicit: Option<PathBuf>) -> Result<PathBuf, String> {
+    if let Some(path) = explicit {
+        return Ok(path);
+    }
+    if let Some(path) = std::env::var_os(STORE_ENV).filter(|p| !p.is_empty()) {
+        return Ok(PathBuf::from(path));
+    }
+    dirs::data_dir()
+        .map(|dir| dir.join(APP_IDENTIFIER).join("notes.json"))
+        .ok_or_else(|| "Cannot find the data directory; pass --store".to_string())
+}
+
+fn read_value(value: String, stdin: &mut dyn Read) -> Result<String, String> {
+    if value != "-" {
+        return Ok(value);
+    }
+    let mut text = String::new();
+    stdin.read_to_string(&mut text).map_err(|e| e.to_string())?;
+    Ok(text)
+}
+
+/// Replaces `-` values with standard input, so no pipe is read while the store is locked.
+fn read_inputs(command: Command, stdin: &mut dyn Read) -> Result<Command, String> {
+    Ok(match command {
+        Command::New { title, content } => Command::New {
+            title,
+            content: content.map(|c| read_value(c, stdin)).transpose()?,
+        },
+        Command::Edit {
+            id,
+            title,
+            content,
+            append,
+        } => Command::Edit {
+            id,
+            title,
+            content: content.map(|c| read_value(c, stdin)).transpose()?,
+            append: append.map(|a| read_value(a, stdin)).transpose()?,
+        },
+        other => other,
+    })
+}
+
+fn find<'a>(store: &'a mut NotesStore, id: &str) -> Result<&'a mut Note, String> {
+    store
+        .notes
+        .iter_mut()
+        .find(|n| n.id == id && !n.deleted)
+        .ok_or_else(|| format!("Note not found: {}", id))
+}
+
+fn write_line(out: &mut dyn Write, note: &Note) -> Result<(), String> {
+    writeln!(out, "{}\t{}\t{}", note.id, note.updated_at, note.title).map_err(|e| e.to_string())
+}
+
+/// Characters that are unsafe in file names on some platform become `_`.
+fn file_name_for(note: &Note) -> String {
+    let title: String = note
+        .title
+        .chars()
+        .map(|c| {
+            if r#"/\:*?"<>|"#.contains(c) || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    let title = title.trim();
+    let short_id: String = note.id.chars().take(8).collect();
+    if title.is_empty() {
+        format!("{}.md", short_id)
+    } else {
+        format!("{}-{}.md", title, short_id)
+    }
+}
+
+fn import_file(path: &Path) -> Result<Vec<Note>, String> {
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    if !is_json {
+        return crate::import::note_from_file(path).map(|note| vec![note]);
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Runs one command against the store at `path`, holding its lock once all input is read.
+pub fn execute(
+    path: &Path,
+    command: Command,
+    stdin: &mut dyn Read,
+    out: &mut dyn Write,
+) -> Result<(), String> {
+    let command = read_inputs(command, stdin)?;
+    let imported = match &command {
+        Command::Import { paths } => paths
+            .iter()
+            .map(|file| import_file(file))
+            .collect::<Result<Vec<_>, _>>()?
+            .concat(),
+        _ => Vec::new(),
+    };
+    let path = path.to_path_buf();
+    with_store_lock(&path, || {
+        let mut store = NotesStore::load_from_path(&path);
+        let now = Utc::now().to_rfc3339();
+        match command {
+            Command::Help => writeln!(out, "{}", USAGE).map_err(|e| e.to_string()),
+            Command::List { all } => {
+                for note in store.notes.iter().filter(|n| all || !n.deleted) {
+                    write_line(out, note)?;
+                }
+                Ok(())
+            }
+            Command::Show { id } => {
+                let note = find(&mut store, &id)?;
+                write!(out, "{}", note.content).map_err(|e| e.to_string())
+            }
+            Command::Search { query } => {
+                let query = query.to_lowercase();
+                for note in store.notes.iter().filter(|n| {
+                    !n.deleted
+                        && (n.title.to_lowercase().contains(&query)
+                            || n.content.to_lowercase().contains(&query))
+                }) {
+                    write_line(out, note)?;
+                }
+                Ok(())
+            }
+            Command::New { title, content } => {
+                let mut note = Note::new();
+                if let Some(title) = title {
+                    note.title = title;
+                }
+                if let Some(content) = content {
+                    note.content = content;
+                }
+                writeln!(out, "{}", note.id).map_err(|e| e.to_string())?;
+                store.add_note(note);
+                store.save_to_path(&path)
+            }
+            Command::Edit {
+                id,
+                title,
+                content,
+                append,
+            } => {
+                let note = find(&mut store, &id)?;
+                if let Some(title) = title {
+                    note.title = title;
+                }
+                if let Some(content) = content {
+                    note.content = content;
+                }
+                if let Some(append) = append {
+                    if !note.content.is_empty() && !note.content.ends_with('\n') {
+                        note.content.push('\n');
+                    }
+                    note.content.push_str(&append);
+                }
+                note.updated_at = now;
+                store.save_to_path(&path)
+            }
+            Command::Delete { id } => {
+                // A tombstone, so sync and the running app drop the note too
+                let note = find(&mut store, &id)?;
+                note.deleted = true;
+                note.updated_at = now;
+                store.save_to_path(&path)
+            }
+            Command::Export { path: target } => {
+                let notes: Vec<&Note> = store.notes.iter().filter(|n| !n.deleted).collect();
+                let is_json = target
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+                if is_json {
+                    let content =
+                        serde_json::to_string_pretty(&notes).map_err(|e| e.to_string())?;
+                    fs::write(&target, content).map_err(|e| e.to_string())?;
+                } else {
+                    fs::create_dir_all(&target).map_err(|e| e.to_string())?;
+                    for note in &notes {
+                        fs::write(target.join(file_name_for(note)), &note.content)
+                            .map_err(|e| e.to_string())?;
+                    }
+                }
+                writeln!(out, "Exported {} notes", notes.len()).map_err(|e| e.to_string())
+            }
+            Command::Import { .. } => {
+                let count = imported.len();
+                store.notes = crate::sync::merge_notes(&store.notes, &imported);
+                store.save_to_path(&path)?;
+                writeln!(out, "Imported {} notes", count).map_err(|e| e.to_string())
+            }
+        }
+    })
+}
+
+/// Entry point of the `notes-cli` binary; returns the process exit code.
+pub fn main_with_args(args: &[String]) -> i32 {
+    let result = parse(args).and_then(|invocation| {
+        let path = store_path(invocation.store)?;
+        execute(
+            &path,
+            invocation.command,
+            &mut std::io::stdin(),
+            &mut std::io::stdout(),
+        )
+    });
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("notes-cli: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn commands_and_options_are_parsed() {
+        let invocation = parse(&args(&[
+            "--store",
+            "/tmp/n.json",
+            "edit",
+            "abc",
+            "--append",
+            "-",
+        ]))
+        .unwrap();
+        assert_eq!(invocation.store, Some(PathBuf::from("/tmp/n.json")));
+        assert_eq!(
+            invocation.command,
+            Command::Edit {
+                id: "abc".to_string(),
+                title: None,
+                content: None,
+                append: Some("-".to_string())
+            }
+        );
+        assert_eq!(parse(&[]).unwrap().command, Command::Help);
+        assert_eq!(
+            parse(&args(&["list", "--all"])).unwrap().command,
+            Command::List { all: true }
+        );
+    }
+
+    #[test]
+    fn missing_and_unknown_arguments_are_errors() {
+        assert!(parse(&args(&["show"])).unwrap_err().contains("note id"));
+        assert!(parse(&args(&["new", "--title"]))
+            .unwrap_err()
+            .contains("--title"));
+        assert!(parse(&args(&["list", "--bogus"]))
+            .unwrap_err()
+            .contains("--bogus"));
+        assert!(parse(&args(&["frobnicate"]))
+            .unwrap_err()
+            .contains("frobnicate"));
+        assert!(parse(&args(&["import"])).is_err());
+    }
+
+    #[test]
+    fn export_file_names_are_safe() {
+        let note = Note {
+            id: "12345678-aaaa".to_string(),
+            title: "a/b: c?".to_string(),
+            ..Note::new()
+        };
+        assert_eq!(file_name_for(&note), "a_b_ c_-12345678.md");
+    }
+}
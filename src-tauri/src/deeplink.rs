@@ -0,0 +1,179 @@
+use crate::notes::Note;
+use crate::NotesState;
+use tauri::Manager;
+use tauri_plugin_deep_link::DeepLinkExt;
+use url::Url;
+
+/// Must match `plugins.deep-link.desktop.schemes` in tauri.conf.json.
+pub const SCHEME: &str = "markdown-notes";
+
+#[derive(Debug, PartialEq)]
+pub enum DeepLink {
+    /// `markdown-notes://note/<id>`
+    OpenNote(String),
+    /// `markdown-notes://new?title=…&content=…`
+    NewNote {
+        title: Option<String>,
+        content: Option<String>,
+    },
+    /// `markdown-notes://search?q=…`
+    Search(String),
+}
+
+/// True for arguments that are deep links rather than files or note ids.
+pub fn is_deep_link(arg: &str) -> bool {
+    arg.get(..SCHEME.len())
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case(SCHEME))
+        && arg[SCHEME.len()..].starts_with(':')
+}
+
+pub fn parse(url: &Url) -> Result<DeepLink, String> {
+    if url.scheme() != SCHEME {
+        return Err(format!("Unsupported scheme: {}", url.scheme()));
+    }
+    let query = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    match url.host_str() {
+        Some("note") => {
+            let id = url.path().trim_matches('/');
+            if id.is_empty() || id.contains('/') {
+                return Err(format!("Invalid note link: {}", url));
+            }
+            Ok(DeepLink::OpenNote(id.to_string()))
+        }
+        Some("new") => Ok(DeepLink::NewNote {
+            title: query("title"),
+            content: query("content"),
+        }),
+        Some("search") => Ok(DeepLink::Search(query("q").unwrap_or_default())),
+        _ => Err(format!("Unknown link: {}", url)),
+    }
+}
+
+fn handle(app: &tauri::AppHandle, link: DeepLink) -> Result<(), String> {
+    log::info!("Deep link: {:?}", link);
+    match link {
+        DeepLink::OpenNote(id) => crate::show_note_window(app, &id),
+        DeepLink::NewNote { title, content } => {
+            let mut note = Note::new();
+            if let Some(title) = title {
+                note.title = title;
+            }
+            if let Some(content) = content {
+                note.content = content;
+            }
+            {
+                let state = app.state::<NotesState>();
+                let mut store = state.0.lock().map_err(|e| e.to_string())?;
+                store.add_note(note.clone());
+                store.save(app)?;
+            }
+            crate::show_note_window(app, &note.id)
+        }
+        DeepLink::Search(query) => {
+            let main = app
+                .get_webview_window("main")
+                .ok_or("Main window not found")?;
+            main.unminimize().map_err(|e| e.to_string())?;
+            main.show().map_err(|e| e.to_string())?;
+            main.set_focus().map_err(|e| e.to_string())?;
+            // On a cold start the dashboard is not listening yet
+            crate::launch::deliver(app, "open-search", query);
+            Ok(())
+        }
+    }
+}
+
+fn open_urls(app: &tauri::AppHandle, urls: Vec<Url>) {
+    for url in urls {
+        crate::open_window_later(app, move |app| {
+            if let Err(e) = parse(&url).and_then(|link| handle(app, link)) {
+                log::error!("Deep link: {}", e);
+            }
+        });
+    }
+}
+
+/// Routes `markdown-notes://` links, including the one the app was started with.
+pub fn init(app: &tauri::AppHandle) -> tauri::Result<()> {
+    app.plugin(tauri_plugin_deep_link::init())?;
+    let deep_link = app.deep_link();
+
+    // Installers register the scheme; this covers portable and development builds
+    #[cfg(any(windows, target_os = "linux"))]
+    if let Err(e) = deep_link.register_all() {
+        log::warn!("Deep link: cannot register scheme: {}", e);
+    }
+
+    let handle = app.clone();
+    deep_link.on_open_url(move |event| open_urls(&handle, event.urls()));
+    match deep_link.get_current() {
+        Ok(Some(urls)) => open_urls(app, urls),
+        Ok(None) => {}
+        Err(e) => log::warn!("Deep link: {}", e),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(url: &str) -> Result<DeepLink, String> {
+        parse(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn note_links_open_the_note() {
+        assert_eq!(
+            link("markdown-notes://note/abc-123"),
+            Ok(DeepLink::OpenNote("abc-123".to_string()))
+        );
+        assert!(link("markdown-notes://note/").is_err());
+        assert!(link("markdown-notes://note/a/b").is_err());
+    }
+
+    #[test]
+    fn new_links_decode_title_and_content() {
+        assert_eq!(
+            link("markdown-notes://new?title=Call%20Bob&content=%23%20Agenda%0A-%20one"),
+            Ok(DeepLink::NewNote {
+                title: Some("Call Bob".to_string()),
+                content: Some("# Agenda\n- one".to_string()),
+            })
+        );
+        assert_eq!(
+            link("markdown-notes://new"),
+            Ok(DeepLink::NewNote {
+                title: None,
+                content: None
+            })
+        );
+    }
+
+    #[test]
+    fn search_links_carry_the_query() {
+        assert_eq!(
+            link("markdown-notes://search?q=meeting+notes"),
+            Ok(DeepLink::Search("meeting notes".to_string()))
+        );
+    }
+
+    #[test]
+    fn other_links_are_rejected() {
+        assert!(link("markdown-notes://delete/abc").is_err());
+        assert!(link("https://note/abc").is_err());
+    }
+
+    #[test]
+    fn deep_link_arguments_are_recognised() {
+        assert!(is_deep_link("markdown-notes://note/abc"));
+        assert!(is_deep_link("Markdown-Notes://search?q=x"));
+        assert!(!is_deep_link("markdown-notes.md"));
+        assert!(!is_deep_link("notes/todo.md"));
+        assert!(!is_deep_link("メモ帳のノート.md"));
+    }
+}
@@ -0,0 +1,453 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tauri::Manager;
+
+const DIAGNOSTICS_FILE_NAME: &str = "diagnostics.jsonl";
+/// Events kept in memory and, after compaction, on disk.
+const CAPACITY: usize = 500;
+/// Only the tail of the regular app log goes into a bundle.
+const MAX_LOG_BYTES: usize = 512 * 1024;
+
+/// Query parameters, JSON keys and form fields whose values never leave the machine.
+const SENSITIVE_KEYS: &[&str] = &[
+    "code",
+    "code_verifier",
+    "code_challenge",
+    "state",
+    "access_token",
+    "refresh_token",
+    "id_token",
+    "token",
+    "client_secret",
+    "password",
+    "secret",
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Info,
+    Warn,
+    Error,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DiagnosticEvent {
+    pub timestamp: String,
+    pub level: Level,
+    /// Subsystem, e.g. `auth` or `sync`.
+    pub category: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+}
+
+impl DiagnosticEvent {
+    pub fn new(level: Level, category: &str, message: &str, fields: &[(&str, &str)]) -> Self {
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            level,
+            category: category.to_string(),
+            message: redact(message),
+            fields: fields
+                .iter()
+                .map(|(key, value)| {
+                    let value = if is_sensitive(key) {
+                        "[redacted]".to_string()
+                    } else {
+                        redact(value)
+                    };
+                    (key.to_string(), value)
+                })
+                .collect(),
+        }
+    }
+}
+
+fn is_sensitive(key: &str) -> bool {
+    SENSITIVE_KEYS.iter().any(|k| k.eq_ignore_ascii_case(key))
+}
+
+/// Masks secrets in free text: `key=value` pairs, `"key": "value"` JSON
+/// members and `Bearer` / `Basic` credentials.
+pub fn redact(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let lower = rest.to_ascii_lowercase();
+        if lower.starts_with("bearer ") || lower.starts_with("basic ") {
+            let scheme_len = rest.find(' ').unwrap_or(0) + 1;
+            out.push_str(&rest[..scheme_len]);
+            out.push_str("[redacted]");
+            rest = &rest[scheme_len..];
+            let end = rest
+                .find(|c: char| c.is_whitespace() || c == '"' || c == '\'')
+                .unwrap_or(rest.len());
+            rest = &rest[end..];
+            continue;
+        }
+
+        // Try `key=value` / `key": "value` at a word boundary.
+        let at_boundary = out
+            .chars()
+            .last()
+            .map_or(true, |c| !(c.is_ascii_alphanumeric() || c == '_'));
+        let key_len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        if at_boundary && key_len > 0 && is_sensitive(&rest[..key_len]) {
+            let after_key = &rest[key_len..];
+            let separator_len = if after_key.starts_with('=') {
+                Some(1)
+            } else if let Some(value) = after_key.strip_prefix("\":") {
+                let value = value.trim_start();
+                let quote = usize::from(value.starts_with('"'));
+                Some(after_key.len() - value.len() + quote)
+            } else {
+                None
+            };
+            if let Some(separator_len) = separator_len {
+                out.push_str(&rest[..key_len + separator_len]);
+                out.push_str("[redacted]");
+                rest = &after_key[separator_len..];
+                let end = rest
+                    .find(|c: char| matches!(c, '&' | '"' | '\'' | ',' | '}') || c.is_whitespace())
+                    .unwrap_or(rest.len());
+                rest = &rest[end..];
+                continue;
+            }
+        }
+
+        let ch = rest.chars().next().expect("rest is not empty");
+        // Copy a whole word at once so keys are only matched at word starts.
+        let word_len = if ch.is_ascii_alphanumeric() || ch == '_' {
+            key_len
+        } else {
+            ch.len_utf8()
+        };
+        out.push_str(&rest[..word_len]);
+        rest = &rest[word_len..];
+    }
+    out
+}
+
+/// Bounded event buffer mirrored to a JSON-lines file.
+pub struct DiagnosticsLog {
+    events: VecDeque<DiagnosticEvent>,
+    capacity: usize,
+    file: Option<PathBuf>,
+    lines_on_disk: usize,
+}
+
+impl DiagnosticsLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::with_capacity(capacity),
+            capacity,
+            file: None,
+            lines_on_disk: 0,
+        }
+    }
+
+    /// Loads the tail of an existing file and persists all further events to it.
+    pub fn attach(&mut self, path: PathBuf) -> Result<(), String> {
+        let previous: Vec<DiagnosticEvent> = fs::read_to_string(&path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        let pending: Vec<DiagnosticEvent> = self.events.drain(..).collect();
+
+        for event in previous {
+            self.push_memory(event);
+        }
+        self.file = Some(path);
+        self.compact()?;
+        for event in pending {
+            self.push(event)?;
+        }
+        Ok(())
+    }
+
+    fn push_memory(&mut self, event: DiagnosticEvent) {
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    fn compact(&mut self) -> Result<(), String> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        let mut buffer = String::new();
+        for event in &self.events {
+            buffer.push_str(&serde_json::to_string(event).map_err(|e| e.to_string())?);
+            buffer.push('\n');
+        }
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, buffer).map_err(|e| e.to_string())?;
+        fs::rename(&tmp_path, path).map_err(|e| e.to_string())?;
+        self.lines_on_disk = self.events.len();
+        Ok(())
+    }
+
+    pub fn push(&mut self, event: DiagnosticEvent) -> Result<(), String> {
+        let line = serde_json::to_string(&event).map_err(|e| e.to_string())?;
+        self.push_memory(event);
+
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        if self.lines_on_disk >= self.capacity * 2 {
+            return self.compact();
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| e.to_string())?;
+        writeln!(file, "{}", line).map_err(|e| e.to_string())?;
+        self.lines_on_disk += 1;
+        Ok(())
+    }
+
+    pub fn events(&self) -> Vec<DiagnosticEvent> {
+        self.events.iter().cloned().collect()
+    }
+}
+
+fn global() -> &'static Mutex<DiagnosticsLog> {
+    static LOG: OnceLock<Mutex<DiagnosticsLog>> = OnceLock::new();
+    LOG.get_or_init(|| Mutex::new(DiagnosticsLog::new(CAPACITY)))
+}
+
+/// Records an event; usable from anywhere, before and after `init`.
+pub fn record(level: Level, category: &str, message: &str, fields: &[(&str, &str)]) {
+    let event = DiagnosticEvent::new(level, category, message, fields);
+    if let Ok(mut log) = global().lock() {
+        if let Err(e) = log.push(event) {
+            log::warn!("Diagnostics: cannot persist event: {}", e);
+        }
+    }
+}
+
+/// Starts persisting events to the app log dir.
+pub fn init(app: &tauri::AppHandle) {
+    let Ok(dir) = app.path().app_log_dir() else {
+        log::warn!("Diagnostics: no app log dir, keeping events in memory");
+        return;
+    };
+    let attached = fs::create_dir_all(&dir)
+        .map_err(|e| e.to_string())
+        .and_then(|_| match global().lock() {
+            Ok(mut log) => log.attach(dir.join(DIAGNOSTICS_FILE_NAME)),
+            Err(e) => Err(e.to_string()),
+        });
+    if let Err(e) = attached {
+        log::warn!("Diagnostics: cannot use {:?}: {}", dir, e);
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct SystemInfo {
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub exported_at: String,
+}
+
+impl SystemInfo {
+    pub fn current(app_version: &str) -> Self {
+        Self {
+            app_version: app_version.to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            exported_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+fn read_log_tail(path: &Path) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+    let start = bytes.len().saturating_sub(MAX_LOG_BYTES);
+    Some(String::from_utf8_lossy(&bytes[start..]).into_owned())
+}
+
+/// Writes a zip with the events, the tail of the app log and system info.
+///
+/// Everything is passed through `redact` again on the way out.
+pub fn write_bundle(
+    dest: &Path,
+    events: &[DiagnosticEvent],
+    app_log: Option<&Path>,
+    system: &SystemInfo,
+) -> Result<(), String> {
+    let file = fs::File::create(dest).map_err(|e| e.to_string())?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    let mut entries = vec![(
+        "system.json",
+        serde_json::to_string_pretty(system).map_err(|e| e.to_string())?,
+    )];
+    let mut jsonl = String::new();
+    for event in events {
+        jsonl.push_str(&serde_json::to_string(event).map_err(|e| e.to_string())?);
+        jsonl.push('\n');
+    }
+    entries.push((DIAGNOSTICS_FILE_NAME, jsonl));
+    if let Some(log) = app_log.and_then(read_log_tail) {
+        entries.push(("app.log", log));
+    }
+
+    for (name, content) in entries {
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        zip.write_all(redact(&content).as_bytes())
+            .map_err(|e| e.to_string())?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_diagnostics() -> Result<Vec<DiagnosticEvent>, String> {
+    log::debug!("Command: get_diagnostics called");
+    let log = global().lock().map_err(|e| e.to_string())?;
+    Ok(log.events())
+}
+
+/// Saves a redacted diagnostics bundle (zip) to `path` for attaching to bug reports.
+#[tauri::command]
+pub fn export_diagnostics(app: tauri::AppHandle, path: String) -> Result<(), String> {
+    log::debug!("Command: export_diagnostics called");
+    let events = global().lock().map_err(|e| e.to_string())?.events();
+    let app_log = app.path().app_log_dir().ok().map(|dir| dir.join("app.log"));
+    let system = SystemInfo::current(&app.package_info().version.to_string());
+    write_bundle(Path::new(&path), &events, app_log.as_deref(), &system)?;
+    log::info!("Diagnostics: exported bundle to {}", path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use tempfile::tempdir;
+
+    fn event(message: &str) -> DiagnosticEvent {
+        DiagnosticEvent::new(Level::Info, "test", message, &[])
+    }
+
+    // ── Redaction ──
+
+    #[test]
+    fn redacts_query_parameters() {
+        assert_eq!(
+            redact("/?state=abc&code=4/0Ax&scope=drive"),
+            "/?state=[redacted]&code=[redacted]&scope=drive"
+        );
+    }
+
+    #[test]
+    fn redacts_json_members_and_bearer_tokens() {
+        assert_eq!(
+            redact(r#"{"access_token": "ya29.x", "expires_in": 3599}"#),
+            r#"{"access_token": "[redacted]", "expires_in": 3599}"#
+        );
+        assert_eq!(
+            redact("Authorization: Bearer ya29.abc done"),
+            "Authorization: Bearer [redacted] done"
+        );
+    }
+
+    #[test]
+    fn keys_only_match_whole_words() {
+        assert_eq!(redact("barcode=123 unicode"), "barcode=123 unicode");
+        assert_eq!(redact("status code 404"), "status code 404");
+    }
+
+    #[test]
+    fn sensitive_fields_are_masked() {
+        let event = DiagnosticEvent::new(
+            Level::Warn,
+            "auth",
+            "callback",
+            &[("code", "abc"), ("path", "/?code=abc")],
+        );
+        assert_eq!(event.fields["code"], "[redacted]");
+        assert_eq!(event.fields["path"], "/?code=[redacted]");
+    }
+
+    // ── Ring buffer ──
+
+    #[test]
+    fn buffer_keeps_only_newest_events() {
+        let mut log = DiagnosticsLog::new(3);
+        for i in 0..5 {
+            log.push(event(&i.to_string())).unwrap();
+        }
+        let messages: Vec<String> = log.events().into_iter().map(|e| e.message).collect();
+        assert_eq!(messages, ["2", "3", "4"]);
+    }
+
+    #[test]
+    fn file_stays_bounded_and_survives_restart() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(DIAGNOSTICS_FILE_NAME);
+
+        let mut log = DiagnosticsLog::new(4);
+        log.push(event("before attach")).unwrap();
+        log.attach(path.clone()).unwrap();
+        for i in 0..20 {
+            log.push(event(&i.to_string())).unwrap();
+        }
+        let lines = fs::read_to_string(&path).unwrap().lines().count();
+        assert!(lines <= 8, "file has {} lines", lines);
+
+        let mut reopened = DiagnosticsLog::new(4);
+        reopened.attach(path).unwrap();
+        let messages: Vec<String> = reopened.events().into_iter().map(|e| e.message).collect();
+        assert_eq!(messages, ["16", "17", "18", "19"]);
+    }
+
+    // ── Bundle ──
+
+    #[test]
+    fn bundle_contains_redacted_files() {
+        let dir = tempdir().unwrap();
+        let app_log = dir.path().join("app.log");
+        fs::write(&app_log, "token exchange body: refresh_token=1//0abc&x=1\n").unwrap();
+        let dest = dir.path().join("bundle.zip");
+
+        write_bundle(
+            &dest,
+            &[event("opened browser")],
+            Some(&app_log),
+            &SystemInfo::current("1.2.3"),
+        )
+        .unwrap();
+
+        let mut archive = zip::ZipArchive::new(fs::File::open(&dest).unwrap()).unwrap();
+        let mut read = |name: &str| {
+            let mut content = String::new();
+            archive
+                .by_name(name)
+                .unwrap()
+                .read_to_string(&mut content)
+                .unwrap();
+            content
+        };
+        assert!(read("system.json").contains("1.2.3"));
+        assert!(read(DIAGNOSTICS_FILE_NAME).contains("opened browser"));
+        let log = read("app.log");
+        assert!(log.contains("refresh_token=[redacted]"));
+        assert!(!log.contains("1//0abc"));
+    }
+}
@@ -0,0 +1,625 @@
+use crate::notes::{app_data_file, Note};
+use crate::sync::merge_notes;
+use crate::NotesState;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{Manager, State};
+
+const NOTES_DIR: &str = "notes";
+const BRANCH: &str = "main";
+const REMOTE: &str = "origin";
+const DEFAULT_DEBOUNCE_MS: u64 = 3000;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HistorySettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// URL of the remote used by push/pull (any URL `git` understands).
+    #[serde(default)]
+    pub remote: Option<String>,
+    #[serde(default = "default_debounce_ms")]
+    pub debounce_ms: u64,
+}
+
+fn default_debounce_ms() -> u64 {
+    DEFAULT_DEBOUNCE_MS
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            remote: None,
+            debounce_ms: DEFAULT_DEBOUNCE_MS,
+        }
+    }
+}
+
+impl HistorySettings {
+    fn get_settings_path(app: &tauri::AppHandle) -> PathBuf {
+        app_data_file(app, "history.json")
+    }
+
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        fs::read_to_string(Self::get_settings_path(app))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, app: &tauri::AppHandle) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(Self::get_settings_path(app), content).map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub commit: String,
+    pub author: String,
+    pub date: String,
+    pub message: String,
+}
+
+/// Renders a note as Markdown with JSON-encoded front matter.
+///
+/// `window_state` is deliberately left out: it is per-device and would turn
+/// every window move into a commit.
+pub fn note_to_markdown(note: &Note) -> String {
+    let field = |value: &str| serde_json::to_string(value).unwrap_or_default();
+    format!(
+        "---\nid: {}\ntitle: {}\ncolor: {}\ncreated_at: {}\nupdated_at: {}\ndeleted: {}\n---\n{}",
+        field(&note.id),
+        field(&note.title),
+        field(&note.color),
+        field(&note.created_at),
+        field(&note.updated_at),
+        note.deleted,
+        note.content
+    )
+}
+
+pub fn note_from_markdown(text: &str) -> Option<Note> {
+    let body = text.strip_prefix("---\n")?;
+    let (header, content) = match body.split_once("\n---\n") {
+        Some(parts) => parts,
+        None => (body.strip_suffix("\n---")?, ""),
+    };
+
+    let mut fields = serde_json::Map::new();
+    for line in header.lines() {
+        let (key, value) = line.split_once(": ")?;
+        fields.insert(key.to_string(), serde_json::from_str(value).ok()?);
+    }
+    fields.insert("content".to_string(), content.into());
+    serde_json::from_value(serde_json::Value::Object(fields)).ok()
+}
+
+/// Describes a staged change set as a commit message.
+fn commit_message(changes: &[(String, &str)], notes: &[Note]) -> Option<String> {
+    let describe = |(status, id): &(String, &str)| {
+        let note = notes.iter().find(|n| n.id == *id);
+        let title = note.map_or(*id, |n| n.title.as_str());
+        let verb = match status.as_str() {
+            "A" => "Create",
+            "D" => "Delete",
+            _ if note.is_some_and(|n| n.deleted) => "Delete",
+            _ => "Update",
+        };
+        format!("{} \"{}\"", verb, title)
+    };
+
+    match changes {
+        [] => None,
+        [single] => Some(describe(single)),
+        many => {
+            let lines: Vec<String> = many.iter().map(|c| format!("- {}", describe(c))).collect();
+            Some(format!(
+                "Update {} notes\n\n{}",
+                many.len(),
+                lines.join("\n")
+            ))
+        }
+    }
+}
+
+/// A git working tree holding one Markdown file per note.
+///
+/// Talks to the `git` executable so the user's own credentials, SSH agent
+/// and config apply to push/pull.
+pub struct GitRepo {
+    dir: PathBuf,
+}
+
+impl GitRepo {
+    pub fn open_or_init(dir: &Path) -> Result<Self, String> {
+        fs::create_dir_all(dir.join(NOTES_DIR)).map_err(|e| e.to_string())?;
+        let repo = Self {
+            dir: dir.to_path_buf(),
+        };
+        if !dir.join(".git").exists() {
+            log::info!("History: initializing repository in {:?}", dir);
+            repo.git(&["init", "-q"])?;
+            repo.git(&["symbolic-ref", "HEAD", &format!("refs/heads/{}", BRANCH)])?;
+            repo.git(&["config", "core.autocrlf", "false"])?;
+            if repo.git(&["config", "user.email"]).is_err() {
+                repo.git(&["config", "user.name", "Markdown Notes"])?;
+                repo.git(&["config", "user.email", "markdown-notes@localhost"])?;
+            }
+            repo.git(&[
+                "commit",
+                "-q",
+                "--allow-empty",
+                "-m",
+                "Initialize note history",
+            ])?;
+        }
+        Ok(repo)
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String, String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            .output()
+            .map_err(|e| format!("Failed to run git: {}", e))?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        } else {
+            Err(format!(
+                "git {} failed: {}",
+                args.first().unwrap_or(&""),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        }
+    }
+
+    fn note_path(id: &str) -> String {
+        format!("{}/{}.md", NOTES_DIR, id)
+    }
+
+    /// Note ids become file names, so reject anything that could escape `notes/`.
+    fn is_safe_id(id: &str) -> bool {
+        !id.is_empty()
+            && !id.starts_with('.')
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    }
+
+    /// Mirrors `notes` into the working tree.
+    fn write_notes(&self, notes: &[Note]) -> Result<(), String> {
+        let notes_dir = self.dir.join(NOTES_DIR);
+        for entry in fs::read_dir(&notes_dir).map_err(|e| e.to_string())? {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            let id = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default();
+            if !notes.iter().any(|n| n.id == id) {
+                fs::remove_file(&path).map_err(|e| e.to_string())?;
+            }
+        }
+        for note in notes.iter().filter(|n| Self::is_safe_id(&n.id)) {
+            let path = self.dir.join(Self::note_path(&note.id));
+            let text = note_to_markdown(note);
+            if fs::read_to_string(&path).ok().as_deref() != Some(text.as_str()) {
+                fs::write(&path, text).map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes `notes` and commits whatever changed. Returns the new commit, if any.
+    pub fn snapshot(&self, notes: &[Note]) -> Result<Option<String>, String> {
+        self.write_notes(notes)?;
+        self.git(&["add", "-A", "--", NOTES_DIR])?;
+        let staged = self.git(&["diff", "--cached", "--name-status", "--", NOTES_DIR])?;
+        let changes: Vec<(String, &str)> = staged
+            .lines()
+            .filter_map(|line| {
+                let (status, path) = line.split_once('\t')?;
+                let id = path.strip_prefix("notes/")?.strip_suffix(".md")?;
+                Some((status.to_string(), id))
+            })
+            .collect();
+
+        let Some(message) = commit_message(&changes, notes) else {
+            return Ok(None);
+        };
+        self.git(&["commit", "-q", "-m", &message])?;
+        let commit = self.git(&["rev-parse", "HEAD"])?.trim().to_string();
+        log::info!("History: committed {}", commit);
+        Ok(Some(commit))
+    }
+
+    pub fn note_log(&self, note_id: &str) -> Result<Vec<HistoryEntry>, String> {
+        if !Self::is_safe_id(note_id) {
+            return Err("Invalid note id".to_string());
+        }
+        let output = self.git(&[
+            "log",
+            "--format=%H%x1f%an%x1f%aI%x1f%s",
+            "--",
+            &Self::note_path(note_id),
+        ])?;
+        Ok(output
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split('\x1f');
+                Some(HistoryEntry {
+                    commit: parts.next()?.to_string(),
+                    author: parts.next()?.to_string(),
+                    date: parts.next()?.to_string(),
+                    message: parts.next()?.to_string(),
+                })
+            })
+            .collect())
+    }
+
+    pub fn note_at(&self, note_id: &str, commit: &str) -> Result<Note, String> {
+        if !Self::is_safe_id(note_id) || !commit.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err("Invalid commit id".to_string());
+        }
+        let spec = format!("{}:{}", commit, Self::note_path(note_id));
+        let text = self.git(&["show", &spec])?;
+        note_from_markdown(&text).ok_or_else(|| "Note file at that revision is invalid".to_string())
+    }
+
+    pub fn set_remote(&self, url: &str) -> Result<(), String> {
+        if self.git(&["remote", "get-url", REMOTE]).is_ok() {
+            self.git(&["remote", "set-url", REMOTE, url])?;
+        } else {
+            self.git(&["remote", "add", REMOTE, url])?;
+        }
+        Ok(())
+    }
+
+    pub fn push(&self) -> Result<(), String> {
+        self.git(&["push", "-q", REMOTE, &format!("HEAD:refs/heads/{}", BRANCH)])
+            .map(|_| ())
+    }
+
+    /// Fetches the remote branch and returns its notes, or `None` if there is nothing new.
+    pub fn fetch(&self) -> Result<Option<Vec<Note>>, String> {
+        if self
+            .git(&["ls-remote", "--heads", REMOTE, BRANCH])?
+            .trim()
+            .is_empty()
+        {
+            return Ok(None);
+        }
+        self.git(&["fetch", "-q", REMOTE, BRANCH])?;
+        if self
+            .git(&["merge-base", "--is-ancestor", "FETCH_HEAD", "HEAD"])
+            .is_ok()
+        {
+            return Ok(None);
+        }
+
+        let listing = self.git(&[
+            "ls-tree",
+            "--name-only",
+            "FETCH_HEAD",
+            &format!("{}/", NOTES_DIR),
+        ])?;
+        let mut notes = Vec::new();
+        for path in listing.lines() {
+            let text = self.git(&["show", &format!("FETCH_HEAD:{}", path)])?;
+            match note_from_markdown(&text) {
+                Some(note) => notes.push(note),
+                None => log::warn!("History: skipping unreadable remote file {}", path),
+            }
+        }
+        Ok(Some(notes))
+    }
+
+    /// Records the fetched branch as merged, with `merged` as the resulting tree.
+    pub fn complete_merge(&self, merged: &[Note]) -> Result<(), String> {
+        self.git(&[
+            "merge",
+            "-q",
+            "-s",
+            "ours",
+            "--no-commit",
+            "--allow-unrelated-histories",
+            "FETCH_HEAD",
+        ])?;
+        self.write_notes(merged)?;
+        self.git(&["add", "-A", "--", NOTES_DIR])?;
+        self.git(&["commit", "-q", "-m", "Merge notes from remote"])
+            .map(|_| ())
+    }
+}
+
+/// Background committer fed by `NotesStore::save`.
+#[derive(Default)]
+pub struct HistoryState {
+    repo: Mutex<Option<Arc<Mutex<GitRepo>>>>,
+    sender: Mutex<Option<Sender<Vec<Note>>>>,
+}
+
+impl HistoryState {
+    /// Queues a snapshot; saves arriving within the debounce window share one commit.
+    pub fn record(&self, notes: &[Note]) {
+        if let Ok(sender) = self.sender.lock() {
+            if let Some(sender) = sender.as_ref() {
+                sender.send(notes.to_vec()).ok();
+            }
+        }
+    }
+
+    fn repo(&self) -> Result<Arc<Mutex<GitRepo>>, String> {
+        self.repo
+            .lock()
+            .map_err(|e| e.to_string())?
+            .clone()
+            .ok_or_else(|| "Git history is not enabled".to_string())
+    }
+
+    pub fn start(&self, app: &tauri::AppHandle, settings: &HistorySettings) -> Result<(), String> {
+        self.stop();
+        if !settings.enabled {
+            return Ok(());
+        }
+
+        let dir = app_data_file(app, "history");
+        let repo = GitRepo::open_or_init(&dir)?;
+        if let Some(remote) = &settings.remote {
+            repo.set_remote(remote)?;
+        }
+        let repo = Arc::new(Mutex::new(repo));
+
+        let (sender, receiver) = mpsc::channel::<Vec<Note>>();
+        let worker_repo = Arc::clone(&repo);
+        let debounce = Duration::from_millis(settings.debounce_ms);
+        std::thread::spawn(move || {
+            while let Ok(mut latest) = receiver.recv() {
+                loop {
+                    match receiver.recv_timeout(debounce) {
+                        Ok(newer) => latest = newer,
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                if let Ok(repo) = worker_repo.lock() {
+                    if let Err(e) = repo.snapshot(&latest) {
+                        log::error!("History: commit failed: {}", e);
+                    }
+                }
+            }
+            log::debug!("History: worker stopped");
+        });
+
+        *self.repo.lock().map_err(|e| e.to_string())? = Some(repo);
+        *self.sender.lock().map_err(|e| e.to_string())? = Some(sender);
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        if let Ok(mut sender) = self.sender.lock() {
+            sender.take();
+        }
+        if let Ok(mut repo) = self.repo.lock() {
+            repo.take();
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_history_settings(app: tauri::AppHandle) -> HistorySettings {
+    log::debug!("Command: get_history_settings called");
+    HistorySettings::load(&app)
+}
+
+/// Runs git work on a blocking thread: commands would otherwise freeze the UI while
+/// a push or fetch holds the repository lock.
+async fn run_blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    tauri::async_runtime::spawn_blocking(work)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn configure_history(
+    app: tauri::AppHandle,
+    enabled: bool,
+    remote: Option<String>,
+) -> Result<(), String> {
+    log::debug!("Command: configure_history called (enabled: {})", enabled);
+    run_blocking(move || {
+        let history = app.state::<HistoryState>();
+        let mut settings = HistorySettings::load(&app);
+        settings.enabled = enabled;
+        settings.remote = remote.filter(|r| !r.trim().is_empty());
+        history.start(&app, &settings)?;
+        settings.save(&app)?;
+
+        if enabled {
+            let notes = app
+                .state::<NotesState>()
+                .0
+                .lock()
+                .map_err(|e| e.to_string())?
+                .notes
+                .clone();
+            history.record(&notes);
+        }
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn history_push(
+    history: State<'_, HistoryState>,
+    notes_state: State<'_, NotesState>,
+) -> Result<(), String> {
+    log::debug!("Command: history_push called");
+    let repo = history.repo()?;
+    let notes = notes_state
+        .0
+        .lock()
+        .map_err(|e| e.to_string())?
+        .notes
+        .clone();
+    run_blocking(move || {
+        let repo = repo.lock().map_err(|e| e.to_string())?;
+        repo.snapshot(&notes)?;
+        repo.push()
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn history_pull(
+    app: tauri::AppHandle,
+    history: State<'_, HistoryState>,
+) -> Result<Vec<Note>, String> {
+    log::debug!("Command: history_pull called");
+    let repo = history.repo()?;
+    run_blocking(move || {
+        let repo = repo.lock().map_err(|e| e.to_string())?;
+        let notes_state = app.state::<NotesState>();
+
+        let local = notes_state
+            .0
+            .lock()
+            .map_err(|e| e.to_string())?
+            .notes
+            .clone();
+        repo.snapshot(&local)?;
+        let Some(remote) = repo.fetch()? else {
+            log::info!("History: already up to date");
+            return Ok(local);
+        };
+
+        let mut store = notes_state.0.lock().map_err(|e| e.to_string())?;
+        let mut merged = merge_notes(&store.notes, &remote);
+        // Window placement and prefs are per-device and not stored in the repository.
+        for note in merged.iter_mut() {
+            if let Some(local) = store.get_note(&note.id) {
+                note.window_state = local.window_state.clone();
+                note.window_prefs = local.window_prefs.clone();
+            }
+        }
+        repo.complete_merge(&merged)?;
+        store.notes = merged;
+        store.save(&app)?;
+        Ok(store.notes.clone())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_note_history(
+    history: State<'_, HistoryState>,
+    note_id: String,
+) -> Result<Vec<HistoryEntry>, String> {
+    log::debug!("Command: get_note_history called for id: {}", note_id);
+    let repo = history.repo()?;
+    run_blocking(move || repo.lock().map_err(|e| e.to_string())?.note_log(&note_id)).await
+}
+
+#[tauri::command]
+pub async fn get_note_revision(
+    history: State<'_, HistoryState>,
+    note_id: String,
+    commit: String,
+) -> Result<Note, String> {
+    log::debug!("Command: get_note_revision called for id: {}", note_id);
+    let repo = history.repo()?;
+    run_blocking(move || {
+        repo.lock()
+            .map_err(|e| e.to_string())?
+            .note_at(&note_id, &commit)
+    })
+    .await
+}
+
+/// Starts the committer during setup if the user enabled it.
+pub fn init(app: &tauri::AppHandle) {
+    let state = HistoryState::default();
+    if let Err(e) = state.start(app, &HistorySettings::load(app)) {
+        log::error!("History: failed to start: {}", e);
+    }
+    app.manage(state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::create_test_note as note;
+
+    #[test]
+    fn markdown_roundtrip_preserves_fields() {
+        let mut original = note("abc", "Title with \"quotes\": and colons");
+        original.content = "# Heading\n\n---\n\nBody\r\nwith CRLF".to_string();
+        original.color = "#dbeafe".to_string();
+        original.deleted = true;
+
+        let parsed = note_from_markdown(&note_to_markdown(&original)).unwrap();
+        assert_eq!(parsed.id, original.id);
+        assert_eq!(parsed.title, original.title);
+        assert_eq!(parsed.content, original.content);
+        assert_eq!(parsed.color, original.color);
+        assert_eq!(parsed.updated_at, original.updated_at);
+        assert!(parsed.deleted);
+    }
+
+    #[test]
+    fn markdown_roundtrip_with_empty_content() {
+        let original = note("abc", "Empty");
+        let parsed = note_from_markdown(&note_to_markdown(&original)).unwrap();
+        assert_eq!(parsed.content, "");
+    }
+
+    #[test]
+    fn markdown_without_front_matter_is_rejected() {
+        assert!(note_from_markdown("# Just markdown").is_none());
+    }
+
+    #[test]
+    fn commit_message_for_single_change() {
+        let notes = vec![note("1", "Groceries")];
+        let changes = vec![("A".to_string(), "1")];
+        assert_eq!(
+            commit_message(&changes, &notes).unwrap(),
+            "Create \"Groceries\""
+        );
+    }
+
+    #[test]
+    fn commit_message_treats_tombstones_as_deletes() {
+        let mut tombstone = note("1", "Old");
+        tombstone.deleted = true;
+        let changes = vec![("M".to_string(), "1")];
+        assert_eq!(
+            commit_message(&changes, &[tombstone]).unwrap(),
+            "Delete \"Old\""
+        );
+    }
+
+    #[test]
+    fn commit_message_lists_multiple_changes() {
+        let notes = vec![note("1", "One"), note("2", "Two")];
+        let changes = vec![("M".to_string(), "1"), ("A".to_string(), "2")];
+        let message = commit_message(&changes, &notes).unwrap();
+        assert!(message.starts_with("Update 2 notes\n\n"));
+        assert!(message.contains("- Update \"One\""));
+        assert!(message.contains("- Create \"Two\""));
+    }
+
+    #[test]
+    fn commit_message_empty_without_changes() {
+        assert!(commit_message(&[], &[]).is_none());
+    }
+}
@@ -0,0 +1,278 @@
+use crate::notes::app_data_file;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+/// Accelerators like `CommandOrControl+Alt+N`; `null` or empty disables one.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct HotkeySettings {
+    pub quick_capture: Option<String>,
+    /// Opens the small window that appends to the inbox note.
+    pub capture_to_inbox: Option<String>,
+    pub toggle_notes: Option<String>,
+    pub search: Option<String>,
+}
+
+impl Default for HotkeySettings {
+    fn default() -> Self {
+        Self {
+            quick_capture: Some("CommandOrControl+Alt+N".to_string()),
+            capture_to_inbox: Some("CommandOrControl+Alt+I".to_string()),
+            toggle_notes: Some("CommandOrControl+Alt+H".to_string()),
+            search: Some("CommandOrControl+Alt+F".to_string()),
+        }
+    }
+}
+
+impl HotkeySettings {
+    fn get_settings_path(app: &tauri::AppHandle) -> PathBuf {
+        app_data_file(app, "hotkeys.json")
+    }
+
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        fs::read_to_string(Self::get_settings_path(app))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, app: &tauri::AppHandle) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(Self::get_settings_path(app), content).map_err(|e| e.to_string())
+    }
+
+    /// Parses the configured accelerators, rejecting invalid and duplicate ones.
+    pub fn bindings(&self) -> Result<Vec<(Shortcut, HotkeyAction)>, String> {
+        let configured = [
+            (&self.quick_capture, HotkeyAction::QuickCapture),
+            (&self.capture_to_inbox, HotkeyAction::CaptureToInbox),
+            (&self.toggle_notes, HotkeyAction::ToggleNotes),
+            (&self.search, HotkeyAction::Search),
+        ];
+        let mut bindings: Vec<(Shortcut, HotkeyAction)> = Vec::new();
+        for (accelerator, action) in configured {
+            let Some(accelerator) = accelerator.as_deref().map(str::trim) else {
+                continue;
+            };
+            if accelerator.is_empty() {
+                continue;
+            }
+            let shortcut = Shortcut::from_str(accelerator)
+                .map_err(|e| format!("Invalid shortcut '{}': {}", accelerator, e))?;
+            if bindings.iter().any(|(existing, _)| *existing == shortcut) {
+                return Err(format!("Shortcut '{}' is used twice", accelerator));
+            }
+            bindings.push((shortcut, action));
+        }
+        Ok(bindings)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HotkeyAction {
+    QuickCapture,
+    CaptureToInbox,
+    ToggleNotes,
+    Search,
+}
+
+/// Shortcuts currently registered with the OS.
+#[derive(Default)]
+pub struct HotkeyState(Mutex<Vec<(Shortcut, HotkeyAction)>>);
+
+/// Installs the global-shortcut plugin and registers the saved shortcuts.
+pub fn init(app: &tauri::AppHandle) -> tauri::Result<()> {
+    app.plugin(
+        tauri_plugin_global_shortcut::Builder::new()
+            .with_handler(|app, shortcut, event| {
+                if event.state() != ShortcutState::Pressed {
+                    return;
+                }
+                let action = app
+                    .state::<HotkeyState>()
+                    .0
+                    .lock()
+                    .ok()
+                    .and_then(|bindings| {
+                        bindings
+                            .iter()
+                            .find(|(bound, _)| bound == shortcut)
+                            .map(|(_, action)| *action)
+                    });
+                if let Some(action) = action {
+                    handle_action(app, action);
+                }
+            })
+            .build(),
+    )?;
+    app.manage(HotkeyState::default());
+
+    if let Err(e) = apply(app, &HotkeySettings::load(app)) {
+        log::warn!("Hotkeys: {}", e);
+    }
+    Ok(())
+}
+
+/// Replaces all registered shortcuts with `settings`.
+///
+/// Shortcuts that cannot be registered are skipped and reported as an error.
+fn apply(app: &tauri::AppHandle, settings: &HotkeySettings) -> Result<(), String> {
+    let bindings = settings.bindings()?;
+    let state = app.state::<HotkeyState>();
+    let mut registered = state.0.lock().map_err(|e| e.to_string())?;
+
+    let shortcuts = app.global_shortcut();
+    shortcuts.unregister_all().map_err(|e| e.to_string())?;
+    registered.clear();
+    let mut failed = Vec::new();
+    for (shortcut, action) in bindings {
+        // One taken shortcut should not disable the others
+        match shortcuts.register(shortcut) {
+            Ok(()) => registered.push((shortcut, action)),
+            Err(e) => failed.push(format!("{:?} ({})", action, e)),
+        }
+    }
+    log::info!("Hotkeys: {} shortcuts registered", registered.len());
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Cannot register shortcuts: {}", failed.join(", ")))
+    }
+}
+
+fn handle_action(app: &tauri::AppHandle, action: HotkeyAction) {
+    log::debug!("Hotkeys: {:?}", action);
+    match action {
+        HotkeyAction::QuickCapture => {
+            crate::open_window_later(app, |app| {
+                if let Err(e) = open_capture_note(app) {
+                    log::error!("Hotkeys: quick capture failed: {}", e);
+                }
+            });
+        }
+        HotkeyAction::CaptureToInbox => {
+            crate::open_window_later(app, |app| {
+                if let Err(e) = crate::capture::open_capture_window(app) {
+                    log::error!("Hotkeys: cannot open capture window: {}", e);
+                }
+            });
+        }
+        HotkeyAction::ToggleNotes => {
+            let notes: Vec<tauri::WebviewWindow> = app
+                .webview_windows()
+                .into_iter()
+                .filter(|(label, _)| label != "main")
+                .map(|(_, window)| window)
+                .collect();
+            let any_visible = notes.iter().any(|w| w.is_visible().unwrap_or(false));
+            for window in notes {
+                if any_visible {
+                    window.hide().ok();
+                } else {
+                    window.show().ok();
+                }
+            }
+        }
+        HotkeyAction::Search => {
+            if let Some(main) = app.get_webview_window("main") {
+                main.unminimize().ok();
+                main.show().ok();
+                main.set_focus().ok();
+                main.emit_to("main", "open-search", ()).ok();
+            }
+        }
+    }
+}
+
+/// Creates a note and opens it focused, like a new note from the main window.
+fn open_capture_note(app: &tauri::AppHandle) -> Result<(), String> {
+    let note = crate::create_and_open_note(app)?;
+    if let Some(window) = app.get_webview_window(&note.id) {
+        window.set_focus().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_hotkey_settings(app: tauri::AppHandle) -> HotkeySettings {
+    log::debug!("Command: get_hotkey_settings called");
+    HotkeySettings::load(&app)
+}
+
+#[tauri::command]
+pub fn configure_hotkeys(app: tauri::AppHandle, settings: HotkeySettings) -> Result<(), String> {
+    log::debug!("Command: configure_hotkeys called");
+    if let Err(e) = apply(&app, &settings) {
+        // Keep the saved shortcuts working instead of the rejected set
+        if let Err(restore) = apply(&app, &HotkeySettings::load(&app)) {
+            log::warn!("Hotkeys: {}", restore);
+        }
+        return Err(e);
+    }
+    settings.save(&app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_bindings_parse() {
+        let bindings = HotkeySettings::default().bindings().unwrap();
+        let actions: Vec<HotkeyAction> = bindings.iter().map(|(_, action)| *action).collect();
+        assert_eq!(
+            actions,
+            [
+                HotkeyAction::QuickCapture,
+                HotkeyAction::CaptureToInbox,
+                HotkeyAction::ToggleNotes,
+                HotkeyAction::Search
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_or_missing_shortcuts_are_disabled() {
+        let settings = HotkeySettings {
+            quick_capture: Some("  ".to_string()),
+            capture_to_inbox: None,
+            toggle_notes: None,
+            search: Some("Alt+Space".to_string()),
+        };
+        let bindings = settings.bindings().unwrap();
+        assert_eq!(bindings.len(), 1);
+        assert_eq!(bindings[0].1, HotkeyAction::Search);
+    }
+
+    #[test]
+    fn invalid_and_duplicate_shortcuts_are_rejected() {
+        let invalid = HotkeySettings {
+            quick_capture: Some("Ctrl+Nope".to_string()),
+            ..Default::default()
+        };
+        assert!(invalid.bindings().unwrap_err().contains("Ctrl+Nope"));
+
+        let duplicate = HotkeySettings {
+            quick_capture: Some("Ctrl+Alt+N".to_string()),
+            toggle_notes: Some("Control+Alt+N".to_string()),
+            ..Default::default()
+        };
+        assert!(duplicate.bindings().unwrap_err().contains("twice"));
+    }
+
+    #[test]
+    fn missing_fields_use_defaults_and_null_disables() {
+        let settings: HotkeySettings = serde_json::from_str(r#"{"search":null}"#).unwrap();
+        assert_eq!(
+            settings.quick_capture,
+            HotkeySettings::default().quick_capture
+        );
+        assert_eq!(settings.search, None);
+        assert_eq!(settings.bindings().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,248 @@
+use crate::linked::{write_back_saved, WriteBack};
+use crate::notes::Note;
+use crate::NotesState;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{DragDropEvent, Emitter, Manager, WindowEvent};
+
+const TEXT_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "svg", "bmp"];
+/// Folders nested deeper than this are not descended into.
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FileKind {
+    Text,
+    Image,
+}
+
+pub fn file_kind(path: &Path) -> Option<FileKind> {
+    let extension = path.extension()?.to_string_lossy().to_ascii_lowercase();
+    if TEXT_EXTENSIONS.contains(&extension.as_str()) {
+        Some(FileKind::Text)
+    } else if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+        Some(FileKind::Image)
+    } else {
+        None
+    }
+}
+
+/// Importable files among `paths`, descending into folders in name order.
+///
+/// Hidden entries and symlinked folders are skipped.
+pub fn collect_files(paths: &[PathBuf]) -> Vec<(PathBuf, FileKind)> {
+    let mut files = Vec::new();
+    for path in paths {
+        collect_into(path, 0, &mut files);
+    }
+    files
+}
+
+fn collect_into(path: &Path, depth: usize, files: &mut Vec<(PathBuf, FileKind)>) {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return;
+    };
+    if metadata.is_dir() {
+        if depth >= MAX_DEPTH {
+            return;
+        }
+        let Ok(entries) = fs::read_dir(path) else {
+            return;
+        };
+        let mut children: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+            .map(|entry| entry.path())
+            .collect();
+        children.sort();
+        for child in children {
+            collect_into(&child, depth + 1, files);
+        }
+    } else if let Some(kind) = file_kind(path) {
+        // Follows file symlinks, but not folder ones
+        if fs::metadata(path).is_ok_and(|m| m.is_file()) {
+            files.push((path.to_path_buf(), kind));
+        }
+    }
+}
+
+/// Title from the first Markdown heading, else the file name.
+pub fn title_for(content: &str, path: &Path) -> String {
+    content
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("# "))
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+        .or_else(|| path.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .unwrap_or_default()
+}
+
+/// Reads a Markdown or text file into a new note.
+pub fn note_from_file(path: &Path) -> Result<Note, String> {
+    let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let (content, _, _) = crate::linked::decode(&bytes)?;
+    Ok(Note {
+        title: title_for(&content, path),
+        content,
+        ..Note::new()
+    })
+}
+
+fn alt_text(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Imports dropped files: text becomes notes, images go into `target` or a new note.
+fn import_dropped(
+    app: &tauri::AppHandle,
+    paths: &[PathBuf],
+    target: Option<&str>,
+) -> Result<(), String> {
+    let files = collect_files(paths);
+    if files.is_empty() {
+        return Ok(());
+    }
+    let dir = crate::attachments::attachments_dir(app);
+    let mut notes = Vec::new();
+    let mut links = Vec::new();
+    for (path, kind) in &files {
+        let imported = match kind {
+            FileKind::Text => note_from_file(path).map(|note| notes.push(note)),
+            FileKind::Image => crate::attachments::store_file(&dir, path)
+                .map(|name| links.push(crate::attachments::markdown_link(&name, &alt_text(path)))),
+        };
+        if let Err(e) = imported {
+            log::warn!("Import: skipping {}: {}", path.display(), e);
+        }
+    }
+
+    let state = app.state::<NotesState>();
+    let mut store = state.0.lock().map_err(|e| e.to_string())?;
+    let mut updated = None;
+    if !links.is_empty() {
+        let images = links.join("\n\n");
+        let existing =
+            target.and_then(|id| store.notes.iter_mut().find(|n| n.id == id && !n.deleted));
+        match existing {
+            Some(note) => {
+                note.content = append_block(&note.content, &images);
+                note.updated_at = chrono::Utc::now().to_rfc3339();
+                // A linked file edited elsewhere wins; add the images to its text instead
+                if let WriteBack::Conflict(_) = write_back_saved(app, note)? {
+                    note.content = append_block(&note.content, &images);
+                    write_back_saved(app, note)?;
+                }
+                updated = Some(note.clone());
+            }
+            None => notes.push(Note {
+                title: alt_text(&files[0].0),
+                content: format!("{}\n", images),
+                ..Note::new()
+            }),
+        }
+    }
+    let created = notes.len();
+    for note in notes {
+        store.add_note(note);
+    }
+    store.save(app)?;
+    drop(store);
+
+    log::info!(
+        "Import: {} notes created, {} images stored",
+        created,
+        links.len()
+    );
+    if let Some(note) = &updated {
+        crate::linked::notify_updated(app, note);
+    }
+    app.emit_to("main", "notes-imported", created).ok();
+    Ok(())
+}
+
+/// Appends `block` as its own paragraph.
+fn append_block(content: &str, block: &str) -> String {
+    let body = content.trim_end();
+    if body.is_empty() {
+        format!("{}\n", block)
+    } else {
+        format!("{}\n\n{}\n", body, block)
+    }
+}
+
+/// Imports files dropped on the dashboard or a note window.
+pub fn on_window_event(window: &tauri::Window, event: &WindowEvent) {
+    let WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) = event else {
+        return;
+    };
+    let app = window.app_handle().clone();
+    let label = window.label().to_string();
+    let paths = paths.clone();
+    // Reading the files can take a while, so keep it off the event loop
+    tauri::async_runtime::spawn_blocking(move || {
+        let target = (label != "main").then_some(label.as_str());
+        if let Err(e) = import_dropped(&app, &paths, target) {
+            log::error!("Import: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_kinds_follow_the_extension() {
+        assert_eq!(file_kind(Path::new("a.MD")), Some(FileKind::Text));
+        assert_eq!(file_kind(Path::new("a.txt")), Some(FileKind::Text));
+        assert_eq!(file_kind(Path::new("a.jpeg")), Some(FileKind::Image));
+        assert_eq!(file_kind(Path::new("a.pdf")), None);
+        assert_eq!(file_kind(Path::new("README")), None);
+    }
+
+    #[test]
+    fn folders_are_collected_recursively_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("sub/deeper")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("b.md"), "b").unwrap();
+        fs::write(root.join("a.txt"), "a").unwrap();
+        fs::write(root.join("skip.pdf"), "x").unwrap();
+        fs::write(root.join(".hidden.md"), "x").unwrap();
+        fs::write(root.join(".git/HEAD.md"), "x").unwrap();
+        fs::write(root.join("sub/deeper/pic.png"), "png").unwrap();
+
+        let files = collect_files(&[root.to_path_buf()]);
+        assert_eq!(
+            files,
+            [
+                (root.join("a.txt"), FileKind::Text),
+                (root.join("b.md"), FileKind::Text),
+                (root.join("sub/deeper/pic.png"), FileKind::Image),
+            ]
+        );
+    }
+
+    #[test]
+    fn text_files_become_notes_titled_by_heading() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plan.md");
+        fs::write(&path, "\u{feff}intro\r\n# Q3 plan\r\n").unwrap();
+        let note = note_from_file(&path).unwrap();
+        assert_eq!(note.title, "Q3 plan");
+        assert_eq!(note.content, "intro\n# Q3 plan\n");
+
+        let path = dir.path().join("todo.txt");
+        fs::write(&path, "milk").unwrap();
+        assert_eq!(note_from_file(&path).unwrap().title, "todo");
+    }
+
+    #[test]
+    fn images_are_appended_as_a_paragraph() {
+        assert_eq!(append_block("# Note\n\n", "![a](x)"), "# Note\n\n![a](x)\n");
+        assert_eq!(append_block("", "![a](x)"), "![a](x)\n");
+    }
+}
@@ -0,0 +1,252 @@
+use crate::NotesState;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+#[derive(Debug, PartialEq)]
+pub enum LaunchTarget {
+    Note(String),
+    File(PathBuf),
+}
+
+/// What a launch asks for, parsed from its command line.
+#[derive(Debug, Default, PartialEq)]
+pub struct LaunchRequest {
+    pub targets: Vec<LaunchTarget>,
+    /// `--new-note`: create a note and open it.
+    pub new_note: bool,
+    /// `--capture`: open the inbox capture window.
+    pub capture: bool,
+    /// Unrecognised flags, reported but otherwise ignored.
+    pub unknown: Vec<String>,
+}
+
+/// Parses `argv` (including the program name); file paths are resolved against `cwd`.
+///
+/// Arguments after `--` are never treated as flags.
+pub fn parse(argv: &[String], cwd: &Path, is_note: impl Fn(&str) -> bool) -> LaunchRequest {
+    let mut request = LaunchRequest::default();
+    let mut flags_done = false;
+    for arg in argv.iter().skip(1) {
+        // Deep links are routed by `deeplink`, which sees the same arguments
+        if arg.is_empty() || crate::deeplink::is_deep_link(arg) {
+            continue;
+        }
+        if !flags_done && arg.starts_with('-') {
+            match arg.as_str() {
+                "--" => flags_done = true,
+                "--new-note" => request.new_note = true,
+                "--capture" => request.capture = true,
+                _ => request.unknown.push(arg.clone()),
+            }
+            continue;
+        }
+        let target = if is_note(arg) {
+            LaunchTarget::Note(arg.clone())
+        } else {
+            LaunchTarget::File(cwd.join(arg))
+        };
+        if !request.targets.contains(&target) {
+            request.targets.push(target);
+        }
+    }
+    request
+}
+
+/// Events for the main window (files to open, searches), held until it says it is listening.
+#[derive(Default)]
+pub struct LaunchQueue(Mutex<PendingEvents>);
+
+#[derive(Default)]
+struct PendingEvents {
+    ready: bool,
+    events: Vec<(&'static str, String)>,
+}
+
+impl LaunchQueue {
+    /// Queues the event, or returns it when the frontend is already listening.
+    fn push(&self, event: &'static str, payload: String) -> Option<(&'static str, String)> {
+        let mut pending = self.0.lock().ok()?;
+        if pending.ready {
+            Some((event, payload))
+        } else {
+            pending.events.push((event, payload));
+            None
+        }
+    }
+
+    /// Marks the frontend as listening and returns the queued events.
+    fn mark_ready(&self) -> Vec<(&'static str, String)> {
+        match self.0.lock() {
+            Ok(mut pending) => {
+                pending.ready = true;
+                std::mem::take(&mut pending.events)
+            }
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+/// Emits `event` to the main window, or queues it until `launch_ready` on a cold start.
+pub fn deliver(app: &tauri::AppHandle, event: &'static str, payload: String) {
+    if let Some((event, payload)) = app.state::<LaunchQueue>().push(event, payload) {
+        app.emit_to("main", event, payload).ok();
+    }
+}
+
+/// Handles a launch's arguments, from this process or a forwarded second one.
+pub fn handle(app: &tauri::AppHandle, argv: &[String], cwd: &Path) {
+    let request = {
+        let state = app.state::<NotesState>();
+        let Ok(store) = state.0.lock() else {
+            return;
+        };
+        parse(argv, cwd, |arg| {
+            store.get_note(arg).is_some_and(|note| !note.deleted)
+        })
+    };
+    for flag in &request.unknown {
+        log::warn!("Launch: ignoring unknown flag {}", flag);
+    }
+
+    for target in request.targets {
+        match target {
+            LaunchTarget::Note(note_id) => {
+                crate::open_window_later(app, move |app| {
+                    if let Err(e) = crate::show_note_window(app, &note_id) {
+                        log::error!("Launch: cannot open note {}: {}", note_id, e);
+                    }
+                });
+            }
+            LaunchTarget::File(path) => {
+                deliver(app, "open-file", path.to_string_lossy().to_string());
+            }
+        }
+    }
+    if request.new_note {
+        crate::open_window_later(app, |app| {
+            if let Err(e) = crate::create_and_open_note(app) {
+                log::error!("Launch: cannot create note: {}", e);
+            }
+        });
+    }
+    if request.capture {
+        crate::open_window_later(app, |app| {
+            if let Err(e) = crate::capture::open_capture_window(app) {
+                log::error!("Launch: cannot open capture window: {}", e);
+            }
+        });
+    }
+}
+
+/// Handles the arguments of a second launch in the running instance.
+pub fn forward(app: &tauri::AppHandle, argv: Vec<String>, cwd: String) {
+    log::info!(
+        "Launch: forwarded {} arguments",
+        argv.len().saturating_sub(1)
+    );
+    if let Some(main) = app.get_webview_window("main") {
+        main.unminimize().ok();
+        main.show().ok();
+        main.set_focus().ok();
+    }
+    handle(app, &argv, Path::new(&cwd));
+}
+
+/// Called by the main window once it listens for `open-file` and `open-search`;
+/// delivers queued events.
+#[tauri::command]
+pub fn launch_ready(app: tauri::AppHandle, state: tauri::State<'_, LaunchQueue>) {
+    log::debug!("Command: launch_ready called");
+    for (event, payload) in state.mark_ready() {
+        app.emit_to("main", event, payload).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn program_name_and_empty_args_are_skipped() {
+        let request = parse(
+            &args(&["app", "", "markdown-notes://note/abc"]),
+            Path::new("/tmp"),
+            |_| false,
+        );
+        assert_eq!(request, LaunchRequest::default());
+    }
+
+    #[test]
+    fn multiple_files_resolve_against_cwd() {
+        let cwd = Path::new("/home/user");
+        let request = parse(&args(&["app", "a.md", "docs/b.md", "a.md"]), cwd, |_| false);
+        assert_eq!(
+            request.targets,
+            [
+                LaunchTarget::File(cwd.join("a.md")),
+                LaunchTarget::File(cwd.join("docs/b.md"))
+            ]
+        );
+    }
+
+    #[test]
+    fn note_ids_and_files_are_told_apart() {
+        let cwd = Path::new("/home/user");
+        let request = parse(&args(&["app", "abc-123", "todo.md"]), cwd, |arg| {
+            arg == "abc-123"
+        });
+        assert_eq!(
+            request.targets,
+            [
+                LaunchTarget::Note("abc-123".to_string()),
+                LaunchTarget::File(cwd.join("todo.md"))
+            ]
+        );
+    }
+
+    #[test]
+    fn absolute_paths_are_kept() {
+        let absolute = std::env::temp_dir().join("note.md");
+        let argv = vec!["app".to_string(), absolute.to_string_lossy().to_string()];
+        let request = parse(&argv, Path::new("/elsewhere"), |_| false);
+        assert_eq!(request.targets, [LaunchTarget::File(absolute)]);
+    }
+
+    #[test]
+    fn flags_are_parsed_and_double_dash_ends_them() {
+        let cwd = Path::new("/w");
+        let request = parse(
+            &args(&["app", "--new-note", "--verbose", "--", "--capture"]),
+            cwd,
+            |_| false,
+        );
+        assert!(request.new_note);
+        assert!(!request.capture);
+        assert_eq!(request.unknown, ["--verbose"]);
+        assert_eq!(request.targets, [LaunchTarget::File(cwd.join("--capture"))]);
+    }
+
+    #[test]
+    fn events_are_queued_until_ready() {
+        let queue = LaunchQueue::default();
+        assert_eq!(queue.push("open-file", "a.md".to_string()), None);
+        assert_eq!(queue.push("open-search", "todo".to_string()), None);
+        assert_eq!(
+            queue.mark_ready(),
+            [
+                ("open-file", "a.md".to_string()),
+                ("open-search", "todo".to_string())
+            ]
+        );
+        assert_eq!(
+            queue.push("open-file", "c.md".to_string()),
+            Some(("open-file", "c.md".to_string()))
+        );
+        assert!(queue.mark_ready().is_empty());
+    }
+}
@@ -0,0 +1,422 @@
+use crate::diagnostics::{self, Level};
+use crate::notes::app_data_file;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use url::Url;
+
+const ALLOWED_SCHEMES: &[&str] = &["http", "https", "mailto"];
+/// Argument of a custom launcher that is replaced by the URL.
+pub const URL_PLACEHOLDER: &str = "{url}";
+/// PowerShell reads the URL from this variable so it is never parsed as code.
+const POWERSHELL_URL_VAR: &str = "MARKDOWN_NOTES_OPEN_URL";
+
+/// User-configured program that opens URLs, e.g. `firefox --new-tab {url}`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CustomLauncher {
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct LauncherSettings {
+    #[serde(default)]
+    pub custom: Option<CustomLauncher>,
+}
+
+impl LauncherSettings {
+    fn get_settings_path(app: &tauri::AppHandle) -> PathBuf {
+        app_data_file(app, "launcher.json")
+    }
+
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        fs::read_to_string(Self::get_settings_path(app))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, app: &tauri::AppHandle) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(Self::get_settings_path(app), content).map_err(|e| e.to_string())
+    }
+}
+
+/// Parses `url` and rejects anything but http, https and mailto.
+pub fn validate_url(url: &str) -> Result<Url, String> {
+    let parsed = Url::parse(url.trim()).map_err(|e| format!("Invalid URL: {}", e))?;
+    if !ALLOWED_SCHEMES.contains(&parsed.scheme()) {
+        return Err(format!(
+            "Refusing to open a {}: URL; only http, https and mailto are allowed",
+            parsed.scheme()
+        ));
+    }
+    Ok(parsed)
+}
+
+/// A process to spawn; the URL is always a separate argument or variable.
+#[derive(Debug, PartialEq)]
+pub struct LaunchCommand {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Strategy {
+    Custom(CustomLauncher),
+    /// `wslview` from wslu, opens the Windows default browser from WSL.
+    WslView,
+    /// `powershell.exe Start-Process`, for WSL without wslu.
+    PowerShell,
+    XdgOpen,
+    /// The platform default via the `opener` crate.
+    System,
+}
+
+impl Strategy {
+    pub fn name(&self) -> &str {
+        match self {
+            Strategy::Custom(custom) => &custom.program,
+            Strategy::WslView => "wslview",
+            Strategy::PowerShell => "powershell.exe",
+            Strategy::XdgOpen => "xdg-open",
+            Strategy::System => "system",
+        }
+    }
+
+    /// Returns `None` for [`Strategy::System`], which does not spawn a command itself.
+    pub fn command(&self, url: &Url) -> Option<LaunchCommand> {
+        let simple = |program: &str| LaunchCommand {
+            program: program.to_string(),
+            args: vec![url.to_string()],
+            env: Vec::new(),
+        };
+        match self {
+            Strategy::Custom(custom) => {
+                let mut args: Vec<String> = custom
+                    .args
+                    .iter()
+                    .map(|arg| {
+                        if arg == URL_PLACEHOLDER {
+                            url.to_string()
+                        } else {
+                            arg.clone()
+                        }
+                    })
+                    .collect();
+                if !custom.args.iter().any(|arg| arg == URL_PLACEHOLDER) {
+                    args.push(url.to_string());
+                }
+                Some(LaunchCommand {
+                    program: custom.program.clone(),
+                    args,
+                    env: Vec::new(),
+                })
+            }
+            Strategy::WslView => Some(simple("wslview")),
+            Strategy::XdgOpen => Some(simple("xdg-open")),
+            Strategy::PowerShell => {
+                // WSLENV forwards the variable to the Windows process.
+                let wslenv = match std::env::var("WSLENV") {
+                    Ok(existing) if !existing.is_empty() => {
+                        format!("{}:{}/u", existing, POWERSHELL_URL_VAR)
+                    }
+                    _ => format!("{}/u", POWERSHELL_URL_VAR),
+                };
+                Some(LaunchCommand {
+                    program: "powershell.exe".to_string(),
+                    args: vec![
+                        "-NoProfile".to_string(),
+                        "-NonInteractive".to_string(),
+                        "-Command".to_string(),
+                        format!("Start-Process -FilePath $env:{}", POWERSHELL_URL_VAR),
+                    ],
+                    env: vec![
+                        (POWERSHELL_URL_VAR.to_string(), url.to_string()),
+                        ("WSLENV".to_string(), wslenv),
+                    ],
+                })
+            }
+            Strategy::System => None,
+        }
+    }
+
+    fn launch(&self, url: &Url) -> Result<(), String> {
+        let Some(launch) = self.command(url) else {
+            return opener::open_browser(url.as_str()).map_err(|e| e.to_string());
+        };
+        let mut command = Command::new(&launch.program);
+        command
+            .args(&launch.args)
+            .envs(launch.env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+
+        // A custom browser may stay in the foreground, the helpers return right away.
+        if let Strategy::Custom(_) = self {
+            command.spawn().map(|_| ()).map_err(|e| e.to_string())
+        } else {
+            let status = command.status().map_err(|e| e.to_string())?;
+            if status.success() {
+                Ok(())
+            } else {
+                Err(format!("exited with {}", status))
+            }
+        }
+    }
+}
+
+/// Orders the strategies to try: the user's command, then the helpers that
+/// fit this platform, then the system default.
+pub fn detect_strategies(
+    custom: Option<&CustomLauncher>,
+    os: &str,
+    wsl: bool,
+    has_program: impl Fn(&str) -> bool,
+) -> Vec<Strategy> {
+    let mut strategies: Vec<Strategy> = custom.cloned().map(Strategy::Custom).into_iter().collect();
+    if os != "windows" && os != "macos" {
+        if wsl {
+            if has_program("wslview") {
+                strategies.push(Strategy::WslView);
+            }
+            if has_program("powershell.exe") {
+                strategies.push(Strategy::PowerShell);
+            }
+        }
+        if has_program("xdg-open") {
+            strategies.push(Strategy::XdgOpen);
+        }
+    }
+    strategies.push(Strategy::System);
+    strategies
+}
+
+fn is_wsl() -> bool {
+    std::env::var_os("WSL_DISTRO_NAME").is_some()
+        || fs::read_to_string("/proc/sys/kernel/osrelease")
+            .is_ok_and(|release| release.to_lowercase().contains("microsoft"))
+}
+
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+/// Opens `url` with the first strategy that works.
+pub fn open_url(url: &str, custom: Option<&CustomLauncher>) -> Result<(), String> {
+    let url = validate_url(url)?;
+    let strategies = detect_strategies(custom, std::env::consts::OS, is_wsl(), on_path);
+
+    let mut errors = Vec::new();
+    for strategy in &strategies {
+        match strategy.launch(&url) {
+            Ok(()) => {
+                diagnostics::record(
+                    Level::Info,
+                    "browser",
+                    "Opened URL",
+                    &[("url", url.as_str()), ("strategy", strategy.name())],
+                );
+                return Ok(());
+            }
+            Err(e) => {
+                log::warn!("Launcher: {} failed: {}", strategy.name(), e);
+                diagnostics::record(
+                    Level::Warn,
+                    "browser",
+                    "Launcher failed",
+                    &[("strategy", strategy.name()), ("error", &e)],
+                );
+                errors.push(format!("{}: {}", strategy.name(), e));
+            }
+        }
+    }
+    Err(format!("Failed to open browser ({})", errors.join("; ")))
+}
+
+/// The helpers are waited on to fall back when one fails, so this runs off the main thread.
+#[tauri::command]
+pub async fn open_external_url(app: tauri::AppHandle, url: String) -> Result<(), String> {
+    log::debug!("Command: open_external_url called");
+    let custom = LauncherSettings::load(&app).custom;
+    tauri::async_runtime::spawn_blocking(move || open_url(&url, custom.as_ref()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn get_launcher_settings(app: tauri::AppHandle) -> LauncherSettings {
+    log::debug!("Command: get_launcher_settings called");
+    LauncherSettings::load(&app)
+}
+
+/// Asks in a native dialog, which no webview script can answer, before a program is saved.
+async fn approve_launcher(
+    app: &tauri::AppHandle,
+    launcher: &CustomLauncher,
+) -> Result<bool, String> {
+    let message = format!(
+        "URLを開くプログラムとして次のコマンドを登録しますか？\n\n{} {}",
+        launcher.program,
+        launcher.args.join(" ")
+    );
+    let dialog = app
+        .dialog()
+        .message(message)
+        .title("URLランチャーの変更")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "登録".to_string(),
+            "キャンセル".to_string(),
+        ));
+    tauri::async_runtime::spawn_blocking(move || dialog.blocking_show())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Sets the custom URL launcher; an empty `program` restores detection.
+///
+/// A new program runs on every link click, so the user must approve it first.
+#[tauri::command]
+pub async fn set_url_launcher(
+    app: tauri::AppHandle,
+    program: Option<String>,
+    args: Option<Vec<String>>,
+) -> Result<(), String> {
+    log::debug!("Command: set_url_launcher called");
+    let mut settings = LauncherSettings::load(&app);
+    let custom = program
+        .map(|program| program.trim().to_string())
+        .filter(|program| !program.is_empty())
+        .map(|program| CustomLauncher {
+            program,
+            args: args.unwrap_or_default(),
+        });
+    if let Some(launcher) = custom
+        .as_ref()
+        .filter(|c| settings.custom.as_ref() != Some(*c))
+    {
+        if !approve_launcher(&app, launcher).await? {
+            return Err("The URL launcher change was not approved".to_string());
+        }
+    }
+    settings.custom = custom;
+    settings.save(&app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        validate_url(s).unwrap()
+    }
+
+    // ── URL validation ──
+
+    #[test]
+    fn allows_web_and_mail_urls() {
+        for ok in [
+            "https://example.com/a?b=c",
+            "http://localhost:1234/",
+            "mailto:someone@example.com",
+        ] {
+            assert!(validate_url(ok).is_ok(), "{}", ok);
+        }
+    }
+
+    #[test]
+    fn rejects_other_schemes_and_garbage() {
+        for bad in [
+            "file:///etc/passwd",
+            "javascript:alert(1)",
+            "ms-settings:",
+            "calc.exe",
+            "/relative/path",
+            "",
+        ] {
+            assert!(validate_url(bad).is_err(), "{}", bad);
+        }
+    }
+
+    // ── Strategy detection ──
+
+    #[test]
+    fn wsl_prefers_wslview_then_powershell() {
+        let strategies = detect_strategies(None, "linux", true, |_| true);
+        assert_eq!(
+            strategies,
+            [
+                Strategy::WslView,
+                Strategy::PowerShell,
+                Strategy::XdgOpen,
+                Strategy::System
+            ]
+        );
+    }
+
+    #[test]
+    fn custom_launcher_comes_first_and_missing_helpers_are_skipped() {
+        let custom = CustomLauncher {
+            program: "firefox".to_string(),
+            args: Vec::new(),
+        };
+        let strategies = detect_strategies(Some(&custom), "linux", false, |p| p == "wslview");
+        assert_eq!(strategies, [Strategy::Custom(custom), Strategy::System]);
+        assert_eq!(
+            detect_strategies(None, "windows", false, |_| true),
+            [Strategy::System]
+        );
+    }
+
+    // ── Commands ──
+
+    #[test]
+    fn url_is_a_single_argument() {
+        let target = url("https://example.com/?q=a'b\"c;rm -rf");
+        let launch = Strategy::XdgOpen.command(&target).unwrap();
+        assert_eq!(launch.args, [target.to_string()]);
+    }
+
+    #[test]
+    fn custom_placeholder_is_replaced_or_url_appended() {
+        let target = url("https://example.com/");
+        let with_placeholder = Strategy::Custom(CustomLauncher {
+            program: "browser".to_string(),
+            args: vec!["--new-tab".to_string(), URL_PLACEHOLDER.to_string()],
+        });
+        assert_eq!(
+            with_placeholder.command(&target).unwrap().args,
+            ["--new-tab", "https://example.com/"]
+        );
+
+        let without = Strategy::Custom(CustomLauncher {
+            program: "browser".to_string(),
+            args: vec!["--private".to_string()],
+        });
+        assert_eq!(
+            without.command(&target).unwrap().args,
+            ["--private", "https://example.com/"]
+        );
+    }
+
+    #[test]
+    fn powershell_gets_url_through_environment() {
+        let target = url("https://example.com/?a='x'");
+        let launch = Strategy::PowerShell.command(&target).unwrap();
+        assert!(launch.args.iter().all(|arg| !arg.contains("example.com")));
+        assert!(launch
+            .env
+            .contains(&(POWERSHELL_URL_VAR.to_string(), target.to_string())));
+        assert!(launch
+            .env
+            .iter()
+            .any(|(key, value)| key == "WSLENV" && value.contains(POWERSHELL_URL_VAR)));
+    }
+}
@@ -1,10 +1,29 @@
+pub mod attachments;
 pub mod auth;
+pub mod capture;
+pub mod cli;
+pub mod deeplink;
+pub mod diagnostics;
+pub mod history;
+pub mod hotkeys;
+pub mod import;
+pub mod launch;
+pub mod launcher;
+pub mod linked;
 pub mod notes;
+pub mod placement;
+pub mod session;
+pub mod sync;
+pub mod tokens;
+pub mod tray;
 
-use notes::{Note, NotesStore};
+use notes::{Note, NotesStore, WindowPrefs};
+use placement::{Layout, MonitorArea, Placement};
 use std::sync::Mutex;
 use tauri::image::Image;
-use tauri::{Emitter, Manager, State, WebviewUrl, WebviewWindowBuilder};
+use tauri::{
+    Emitter, Manager, PhysicalPosition, PhysicalSize, State, WebviewUrl, WebviewWindowBuilder,
+};
 
 pub struct NotesState(pub Mutex<NotesStore>);
 
@@ -39,9 +58,47 @@ fn save_note(
     note: Note,
 ) -> Result<(), String> {
     log::debug!("Command: save_note called for id: {}", note.id);
+    let mut note = note;
     let mut store = state.0.lock().map_err(|e| e.to_string())?;
-    store.update_note(note);
-    store.save(&app)
+    let outcome = linked::write_back_saved(&app, &mut note)?;
+    store.update_note_content(note.clone());
+    // Both the file and the note changed: the note follows the file, the edit
+    // goes to a new note so neither is lost
+    let conflict = match &outcome {
+        linked::WriteBack::Conflict(edit) => {
+            let copy = Note {
+                title: format!("{} (競合コピー)", note.title),
+                content: edit.clone(),
+                color: note.color.clone(),
+                ..Note::new()
+            };
+            store.add_note(copy.clone());
+            Some(copy)
+        }
+        _ => None,
+    };
+    store.save(&app)?;
+    drop(store);
+    if matches!(
+        outcome,
+        linked::WriteBack::Reloaded | linked::WriteBack::Conflict(_)
+    ) {
+        linked::notify_updated(&app, &note);
+    }
+    if let Some(copy) = conflict {
+        log::warn!(
+            "Linked file of {} changed on disk; kept the edit as {}",
+            note.id,
+            copy.id
+        );
+        app.emit_to("main", "notes-imported", 1).ok();
+        open_window_later(&app, move |app| {
+            if let Err(e) = show_note_window(app, &copy.id) {
+                log::error!("Cannot open conflict copy {}: {}", copy.id, e);
+            }
+        });
+    }
+    Ok(())
 }
 
 #[tauri::command]
@@ -89,12 +146,19 @@ fn update_window_state(
     height: u32,
 ) -> Result<(), String> {
     log::debug!("Command: update_window_state called for id: {}", note_id);
+    let monitor = app
+        .get_webview_window(&note_id)
+        .and_then(|window| window.current_monitor().ok().flatten());
     let mut store = state.0.lock().map_err(|e| e.to_string())?;
     if let Some(note) = store.notes.iter_mut().find(|n| n.id == note_id) {
         note.window_state.x = x;
         note.window_state.y = y;
         note.window_state.width = width;
         note.window_state.height = height;
+        if let Some(monitor) = &monitor {
+            note.window_state.monitor = monitor.name().cloned();
+            note.window_state.scale_factor = Some(monitor.scale_factor());
+        }
     }
     store.save(&app)
 }
@@ -114,45 +178,327 @@ async fn open_note_window(
     }
 
     // Retrieve note data and drop store immediately to avoid deadlock during window creation
-    let (title, width, height, x, y) = {
+    let note = {
         let store = state.0.lock().map_err(|e| e.to_string())?;
         if let Some(note) = store.get_note(&note_id) {
-            (
-                note.title.clone(),
-                note.window_state.width,
-                note.window_state.height,
-                note.window_state.x,
-                note.window_state.y,
-            )
+            note.clone()
         } else {
             log::warn!("Note not found: {}", note_id);
             return Ok(());
         }
     };
 
-    let url = WebviewUrl::App(format!("note.html?id={}", note_id).into());
-    log::debug!("Building window for note: {}", title);
+    build_note_window(&app, &note)
+}
+
+/// Adds an empty note and opens its window.
+pub(crate) fn create_and_open_note(app: &tauri::AppHandle) -> Result<Note, String> {
+    let note = Note::new();
+    {
+        let state = app.state::<NotesState>();
+        let mut store = state.0.lock().map_err(|e| e.to_string())?;
+        store.add_note(note.clone());
+        store.save(app)?;
+    }
+    build_note_window(app, &note)?;
+    Ok(note)
+}
+
+/// Runs `open` on the async runtime instead of the event loop.
+///
+/// Event-loop callbacks (tray, menu, shortcut and deep-link handlers) must not build windows
+/// themselves: on Windows that deadlocks the loop the new window waits on.
+pub(crate) fn open_window_later(
+    app: &tauri::AppHandle,
+    open: impl FnOnce(&tauri::AppHandle) + Send + 'static,
+) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move { open(&app) });
+}
+
+/// Brings a note's window to the front, opening it if needed.
+pub(crate) fn show_note_window(app: &tauri::AppHandle, note_id: &str) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(note_id) {
+        window.unminimize().map_err(|e| e.to_string())?;
+        window.show().map_err(|e| e.to_string())?;
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+    let note = {
+        let state = app.state::<NotesState>();
+        let store = state.0.lock().map_err(|e| e.to_string())?;
+        store.get_note(note_id).cloned().ok_or("Note not found")?
+    };
+    build_note_window(app, &note)
+}
+
+fn build_note_window(app: &tauri::AppHandle, note: &Note) -> Result<(), String> {
+    let url = WebviewUrl::App(format!("note.html?id={}", note.id).into());
+    log::debug!("Building window for note: {}", note.title);
+
+    let monitors: Vec<MonitorArea> = app
+        .available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .map(MonitorArea::from_monitor)
+        .collect();
+    let primary = app
+        .primary_monitor()
+        .ok()
+        .flatten()
+        .map(|primary| MonitorArea::from_monitor(&primary))
+        .and_then(|primary| monitors.iter().position(|m| *m == primary));
+    let placement = placement::place(&note.window_state, &monitors, primary);
 
-    WebviewWindowBuilder::new(&app, &note_id, url)
-        .title(&title)
-        .inner_size(width as f64, height as f64)
-        .position(x as f64, y as f64)
-        .decorations(true)
+    let prefs = &note.window_prefs;
+    // Created hidden and moved in physical pixels, which are unambiguous across monitors
+    let builder = WebviewWindowBuilder::new(app, &note.id, url)
+        .title(&note.title)
+        .decorations(!prefs.compact)
+        .always_on_top(prefs.always_on_top)
+        .always_on_bottom(prefs.pinned_to_desktop)
+        .skip_taskbar(prefs.pinned_to_desktop)
         .resizable(true)
-        .build()
+        .visible(false);
+    // Lets the page fade the note for opacity below 1
+    #[cfg(not(target_os = "macos"))]
+    let builder = builder.transparent(true);
+    let window = builder.build().map_err(|e| e.to_string())?;
+    window
+        .set_ignore_cursor_events(prefs.click_through)
         .map_err(|e| e.to_string())?;
+    apply_placement(&window, &placement)?;
+    window.show().map_err(|e| e.to_string())?;
 
     // Set dev icon if in debug mode
     #[cfg(debug_assertions)]
     {
-        if let Some(window) = app.get_webview_window(&note_id) {
+        if let Some(window) = app.get_webview_window(&note.id) {
             let _ = set_window_icon(&window);
         }
     }
 
+    if let Some(session) = app.try_state::<session::SessionState>() {
+        session.update(|session| session.opened(&note.id))?;
+    }
+    Ok(())
+}
+
+fn apply_placement(window: &tauri::WebviewWindow, placement: &Placement) -> Result<(), String> {
+    window
+        .set_size(PhysicalSize::new(placement.width, placement.height))
+        .map_err(|e| e.to_string())?;
+    window
+        .set_position(PhysicalPosition::new(placement.x, placement.y))
+        .map_err(|e| e.to_string())
+}
+
+fn apply_window_prefs(window: &tauri::WebviewWindow, prefs: &WindowPrefs) -> Result<(), String> {
+    window
+        .set_decorations(!prefs.compact)
+        .map_err(|e| e.to_string())?;
+    window
+        .set_always_on_top(prefs.always_on_top)
+        .map_err(|e| e.to_string())?;
+    window
+        .set_always_on_bottom(prefs.pinned_to_desktop)
+        .map_err(|e| e.to_string())?;
+    window
+        .set_skip_taskbar(prefs.pinned_to_desktop)
+        .map_err(|e| e.to_string())?;
+    window
+        .set_ignore_cursor_events(prefs.click_through)
+        .map_err(|e| e.to_string())?;
+    // Opacity and compact styling are applied by the page
+    window
+        .emit_to(window.label(), "window-prefs-changed", prefs)
+        .map_err(|e| e.to_string())
+}
+
+/// Stores a note's window preferences and applies them to its open window.
+pub(crate) fn update_window_prefs(
+    app: &tauri::AppHandle,
+    note_id: &str,
+    prefs: WindowPrefs,
+) -> Result<WindowPrefs, String> {
+    let prefs = prefs.normalized();
+    {
+        let state = app.state::<NotesState>();
+        let mut store = state.0.lock().map_err(|e| e.to_string())?;
+        let note = store
+            .notes
+            .iter_mut()
+            .find(|n| n.id == note_id)
+            .ok_or("Note not found")?;
+        note.window_prefs = prefs.clone();
+        store.save(app)?;
+    }
+    if let Some(window) = app.get_webview_window(note_id) {
+        apply_window_prefs(&window, &prefs)?;
+    }
+    Ok(prefs)
+}
+
+/// Makes every click-through note clickable again; the way back without a mouse.
+pub(crate) fn release_click_through(app: &tauri::AppHandle) -> Result<(), String> {
+    let released: Vec<(String, WindowPrefs)> = {
+        let state = app.state::<NotesState>();
+        let store = state.0.lock().map_err(|e| e.to_string())?;
+        store
+            .notes
+            .iter()
+            .filter(|n| n.window_prefs.click_through)
+            .map(|n| {
+                let prefs = WindowPrefs {
+                    click_through: false,
+                    ..n.window_prefs.clone()
+                };
+                (n.id.clone(), prefs)
+            })
+            .collect()
+    };
+    for (note_id, prefs) in released {
+        update_window_prefs(app, &note_id, prefs)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn set_note_window_prefs(
+    app: tauri::AppHandle,
+    note_id: String,
+    prefs: WindowPrefs,
+) -> Result<WindowPrefs, String> {
+    log::debug!("Command: set_note_window_prefs called for id: {}", note_id);
+    update_window_prefs(&app, &note_id, prefs)
+}
+
+fn current_area(window: &tauri::WebviewWindow) -> Result<MonitorArea, String> {
+    let monitor = window
+        .current_monitor()
+        .map_err(|e| e.to_string())?
+        .ok_or("No monitor found for this window")?;
+    Ok(MonitorArea::from_monitor(&monitor))
+}
+
+/// Records `placement` as the note's window state and moves its window if open.
+fn place_note(
+    app: &tauri::AppHandle,
+    note: &mut Note,
+    placement: &Placement,
+    area: &MonitorArea,
+) -> Result<(), String> {
+    note.window_state = notes::WindowState {
+        x: placement.x,
+        y: placement.y,
+        width: placement.width,
+        height: placement.height,
+        monitor: area.name.clone(),
+        scale_factor: Some(area.scale_factor),
+    };
+    if let Some(note_window) = app.get_webview_window(&note.id) {
+        apply_placement(&note_window, placement)?;
+    }
     Ok(())
 }
 
+/// Moves every note onto the monitor of the calling window, open or not.
+#[tauri::command]
+fn gather_note_windows(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    state: State<'_, NotesState>,
+) -> Result<(), String> {
+    log::debug!(
+        "Command: gather_note_windows called from {}",
+        window.label()
+    );
+    let area = current_area(&window)?;
+
+    let mut store = state.0.lock().map_err(|e| e.to_string())?;
+    let notes: Vec<&mut Note> = store.notes.iter_mut().filter(|n| !n.deleted).collect();
+    let states: Vec<notes::WindowState> = notes.iter().map(|n| n.window_state.clone()).collect();
+    for (note, placement) in notes.into_iter().zip(placement::gather(&states, &area)) {
+        place_note(&app, note, &placement, &area)?;
+    }
+    store.save(&app)
+}
+
+/// Lays out the open note windows on `monitor`, or on the calling window's monitor.
+#[tauri::command]
+fn arrange_note_windows(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    state: State<'_, NotesState>,
+    layout: Layout,
+    monitor: Option<String>,
+) -> Result<(), String> {
+    log::debug!("Command: arrange_note_windows called ({:?})", layout);
+    let area = match monitor {
+        Some(name) => app
+            .available_monitors()
+            .map_err(|e| e.to_string())?
+            .iter()
+            .find(|m| m.name() == Some(&name))
+            .map(MonitorArea::from_monitor)
+            .ok_or_else(|| format!("Monitor not found: {}", name))?,
+        None => current_area(&window)?,
+    };
+
+    let mut store = state.0.lock().map_err(|e| e.to_string())?;
+    let open: Vec<&mut Note> = store
+        .notes
+        .iter_mut()
+        .filter(|n| !n.deleted && app.get_webview_window(&n.id).is_some())
+        .collect();
+    let placements = {
+        let notes: Vec<&Note> = open.iter().map(|n| &**n).collect();
+        placement::arrange(layout, &notes, &area)
+    };
+    for (note, placement) in open.into_iter().zip(placements) {
+        place_note(&app, note, &placement, &area)?;
+    }
+    store.save(&app)
+}
+
+/// Reopens the note windows of the previous session, back to front.
+fn restore_session(app: &tauri::AppHandle) {
+    let Some(session) = app.try_state::<session::SessionState>() else {
+        return;
+    };
+    let previous = session.snapshot();
+    let notes: Vec<Note> = {
+        let state = app.state::<NotesState>();
+        let Ok(store) = state.0.lock() else {
+            return;
+        };
+        previous
+            .windows_to_restore(|id| store.get_note(id).is_some_and(|note| !note.deleted))
+            .iter()
+            .filter_map(|id| store.get_note(id).cloned())
+            .collect()
+    };
+
+    log::info!("Session: restoring {} note windows", notes.len());
+    for note in &notes {
+        if let Err(e) = build_note_window(app, note) {
+            log::warn!("Session: cannot reopen {}: {}", note.id, e);
+        }
+    }
+    // Drop windows that could not be restored from the saved session
+    let restored: Vec<String> = notes.iter().map(|note| note.id.clone()).collect();
+    session
+        .update(|session| session.windows.retain(|id| restored.contains(id)))
+        .ok();
+
+    if let Some(window) = previous
+        .focused
+        .filter(|_| previous.restore)
+        .and_then(|label| app.get_webview_window(&label))
+    {
+        window.set_focus().ok();
+    }
+}
+
 fn load_dev_icon() -> Result<Image<'static>, String> {
     log::debug!("Loading dev icon bytes");
     let icon_bytes = include_bytes!("../icons/dev-icon.png");
@@ -165,22 +511,31 @@ fn set_window_icon(window: &tauri::WebviewWindow) -> Result<(), String> {
     window.set_icon(icon).map_err(|e| e.to_string())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_dev_icon_loads() {
-        let result = load_dev_icon();
-        assert!(result.is_ok(), "Dev icon should load successfully");
-    }
-}
-
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        // Must be registered first so a second launch exits before doing any work
+        .plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+            launch::forward(app, argv, cwd);
+        }))
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        .manage(launch::LaunchQueue::default())
+        .register_asynchronous_uri_scheme_protocol(
+            attachments::PROTOCOL,
+            |ctx, request, responder| {
+                // Image reads must not stall the webview's request thread
+                let app = ctx.app_handle().clone();
+                tauri::async_runtime::spawn_blocking(move || {
+                    responder.respond(attachments::protocol(&app, &request))
+                });
+            },
+        )
+        .on_window_event(|window, event| {
+            session::on_window_event(window, event);
+            tray::on_window_event(window, event);
+            import::on_window_event(window, event);
+        })
         .invoke_handler(tauri::generate_handler![
             create_note,
             get_all_notes,
@@ -189,10 +544,55 @@ pub fn run() {
             delete_note,
             update_window_state,
             open_note_window,
+            gather_note_windows,
+            arrange_note_windows,
+            set_note_window_prefs,
             save_all_notes,
             auth::start_google_auth_server,
-            auth::open_external_url,
+            auth::cancel_google_auth,
+            launcher::open_external_url,
+            launcher::get_launcher_settings,
+            launcher::set_url_launcher,
             auth::frontend_log,
+            diagnostics::get_diagnostics,
+            diagnostics::export_diagnostics,
+            sync::get_sync_settings,
+            sync::set_sync_provider,
+            sync::sync_now,
+            sync::drive::connect_google_drive,
+            tokens::get_access_token,
+            tokens::sign_out,
+            sync::webdav::configure_webdav_sync,
+            sync::folder::configure_folder_sync,
+            sync::lan::lan_set_enabled,
+            sync::lan::lan_begin_pairing,
+            sync::lan::lan_discover,
+            sync::lan::lan_pair,
+            sync::lan::lan_list_peers,
+            sync::lan::lan_unpair,
+            sync::lan::lan_sync_now,
+            history::get_history_settings,
+            history::configure_history,
+            history::history_push,
+            history::history_pull,
+            history::get_note_history,
+            history::get_note_revision,
+            session::get_session_restore,
+            session::set_session_restore,
+            hotkeys::get_hotkey_settings,
+            hotkeys::configure_hotkeys,
+            capture::append_to_note,
+            capture::get_capture_settings,
+            capture::set_inbox_note,
+            capture::open_capture,
+            linked::open_linked_file,
+            linked::unlink_file,
+            attachments::add_attachment,
+            attachments::read_attachment,
+            attachments::delete_attachment,
+            attachments::list_attachments,
+            attachments::collect_attachments,
+            launch::launch_ready,
         ])
         .setup(|app| {
             app.handle().plugin(
@@ -207,6 +607,7 @@ pub fn run() {
                     .level(log::LevelFilter::Info)
                     .build(),
             )?;
+            diagnostics::init(app.handle());
 
             if cfg!(debug_assertions) {
                 log::debug!("Setup: Setting dev icon for existing windows");
@@ -220,19 +621,22 @@ pub fn run() {
             // Manage NotesStore state
             let store = NotesStore::load(app.handle());
             app.manage(NotesState(Mutex::new(store)));
+            app.manage(auth::AuthServerState::default());
+            app.manage(session::SessionState::open(app.handle()));
+            history::init(app.handle());
+            sync::lan::init(app.handle());
+            tray::init(app.handle())?;
+            hotkeys::init(app.handle())?;
+            restore_session(app.handle());
+            deeplink::init(app.handle())?;
+            linked::init(app.handle());
+            attachments::init(app.handle());
 
-            // Get command line arguments and send file path to frontend
+            // Files are queued until the main window calls `launch_ready`
             let args: Vec<String> = std::env::args().collect();
-            if args.len() > 1 {
-                let file_path = args[1].clone();
-                let app_handle = app.handle().clone();
-
-                // Emit event after window is ready using std::thread
-                std::thread::spawn(move || {
-                    // Small delay to ensure window is ready
-                    std::thread::sleep(std::time::Duration::from_millis(500));
-                    let _ = app_handle.emit("open-file", file_path);
-                });
+            match std::env::current_dir() {
+                Ok(cwd) => launch::handle(app.handle(), &args, &cwd),
+                Err(e) => log::error!("Launch: cannot read working directory: {}", e),
             }
 
             Ok(())
@@ -240,3 +644,14 @@ pub fn run() {
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dev_icon_loads() {
+        let result = load_dev_icon();
+        assert!(result.is_ok(), "Dev icon should load successfully");
+    }
+}
@@ -0,0 +1,411 @@
+use crate::notes::{app_data_file, Note};
+use crate::NotesState;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, UNIX_EPOCH};
+use tauri::{Emitter, Manager, State};
+
+/// Links are device-local, so they live outside notes.json and never sync.
+const LINKS_FILE_NAME: &str = "linked_files.json";
+/// How often linked files are checked for external edits.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FileEncoding {
+    #[default]
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LineEnding {
+    /// Written as-is; also used for files with mixed line endings.
+    #[default]
+    Lf,
+    Crlf,
+}
+
+/// Markdown file on disk that a note mirrors.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LinkedFile {
+    pub path: String,
+    #[serde(default)]
+    pub encoding: FileEncoding,
+    #[serde(default)]
+    pub line_ending: LineEnding,
+    /// SHA-256 of the bytes last read or written, to spot external edits.
+    #[serde(default)]
+    pub hash: String,
+    /// Modification time in ms at that point, to skip unchanged files cheaply.
+    #[serde(default)]
+    pub modified_ms: Option<u64>,
+}
+
+/// Files opened on this device, by note id.
+pub type Links = BTreeMap<String, LinkedFile>;
+
+pub struct LinkedState {
+    links: Mutex<Links>,
+    path: PathBuf,
+}
+
+impl LinkedState {
+    pub fn load(path: PathBuf) -> Self {
+        let links = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            links: Mutex::new(links),
+            path,
+        }
+    }
+
+    pub fn open(app: &tauri::AppHandle) -> Self {
+        Self::load(app_data_file(app, LINKS_FILE_NAME))
+    }
+
+    pub fn get(&self, note_id: &str) -> Option<LinkedFile> {
+        self.links.lock().ok()?.get(note_id).cloned()
+    }
+
+    /// Applies `change` and saves the links if it altered them.
+    pub fn update<T>(&self, change: impl FnOnce(&mut Links) -> T) -> Result<T, String> {
+        let mut links = self.links.lock().map_err(|e| e.to_string())?;
+        let before = links.clone();
+        let result = change(&mut links);
+        if *links != before {
+            let content = serde_json::to_string_pretty(&*links).map_err(|e| e.to_string())?;
+            fs::write(&self.path, content).map_err(|e| e.to_string())?;
+        }
+        Ok(result)
+    }
+}
+
+/// Decodes file bytes into editor text with `\n` line endings.
+///
+/// Files mixing `\r\n` and `\n` are kept as-is so that writing them back is lossless.
+pub fn decode(bytes: &[u8]) -> Result<(String, FileEncoding, LineEnding), String> {
+    let (text, encoding) = if let Some(rest) = bytes.strip_prefix(UTF8_BOM) {
+        (utf8(rest)?, FileEncoding::Utf8Bom)
+    } else if let Some(rest) = bytes.strip_prefix(UTF16_LE_BOM) {
+        (utf16(rest, u16::from_le_bytes)?, FileEncoding::Utf16Le)
+    } else if let Some(rest) = bytes.strip_prefix(UTF16_BE_BOM) {
+        (utf16(rest, u16::from_be_bytes)?, FileEncoding::Utf16Be)
+    } else {
+        (utf8(bytes)?, FileEncoding::Utf8)
+    };
+
+    let crlf = text.matches("\r\n").count();
+    let lf = text.matches('\n').count();
+    if crlf > 0 && crlf == lf {
+        Ok((text.replace("\r\n", "\n"), encoding, LineEnding::Crlf))
+    } else {
+        Ok((text, encoding, LineEnding::Lf))
+    }
+}
+
+fn utf8(bytes: &[u8]) -> Result<String, String> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| "File is not UTF-8 or UTF-16 text".to_string())
+}
+
+fn utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Result<String, String> {
+    if bytes.len() % 2 != 0 {
+        return Err("File is not valid UTF-16 text".to_string());
+    }
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16(&units).map_err(|_| "File is not valid UTF-16 text".to_string())
+}
+
+/// Inverse of [`decode`].
+pub fn encode(content: &str, encoding: FileEncoding, line_ending: LineEnding) -> Vec<u8> {
+    let text = match line_ending {
+        LineEnding::Lf => content.to_string(),
+        LineEnding::Crlf => content.replace("\r\n", "\n").replace('\n', "\r\n"),
+    };
+    match encoding {
+        FileEncoding::Utf8 => text.into_bytes(),
+        FileEncoding::Utf8Bom => [UTF8_BOM, text.as_bytes()].concat(),
+        FileEncoding::Utf16Le => UTF16_LE_BOM
+            .iter()
+            .copied()
+            .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect(),
+        FileEncoding::Utf16Be => UTF16_BE_BOM
+            .iter()
+            .copied()
+            .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+            .collect(),
+    }
+}
+
+fn hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn modified_ms(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    let ms = modified.duration_since(UNIX_EPOCH).ok()?.as_millis();
+    u64::try_from(ms).ok()
+}
+
+/// Reads `path` into a new note and the link to it.
+pub fn read_linked(path: &Path) -> Result<(Note, LinkedFile), String> {
+    let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let (content, encoding, line_ending) = decode(&bytes)?;
+    let title = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let note = Note {
+        title,
+        content,
+        ..Note::new()
+    };
+    let link = LinkedFile {
+        path: path.to_string_lossy().to_string(),
+        encoding,
+        line_ending,
+        hash: hash(&bytes),
+        modified_ms: modified_ms(path),
+    };
+    Ok((note, link))
+}
+
+/// Reloads the note from its file if the file changed; returns whether it did.
+///
+/// A missing file leaves the note untouched.
+pub fn reload(note: &mut Note, link: &mut LinkedFile) -> Result<bool, String> {
+    let path = Path::new(&link.path);
+    let modified = modified_ms(path);
+    if modified.is_none() || modified == link.modified_ms {
+        return Ok(false);
+    }
+    let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    link.modified_ms = modified;
+    let disk_hash = hash(&bytes);
+    if disk_hash == link.hash {
+        return Ok(false);
+    }
+    let (content, encoding, line_ending) = decode(&bytes)?;
+    link.encoding = encoding;
+    link.line_ending = line_ending;
+    link.hash = disk_hash;
+    note.content = content;
+    note.updated_at = Utc::now().to_rfc3339();
+    Ok(true)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WriteBack {
+    /// Not linked, the file is gone, or it already holds this content.
+    Unchanged,
+    Written,
+    /// The file was edited outside the app since it was last synced; its
+    /// content replaced the note's instead of being overwritten.
+    Reloaded,
+    /// Like `Reloaded`, but the note had been edited too; holds the note's content
+    /// so the caller can keep it elsewhere.
+    Conflict(String),
+}
+
+/// Writes the note's content to its linked file in the file's own format.
+pub fn write_back(note: &mut Note, link: &mut LinkedFile) -> Result<WriteBack, String> {
+    let edit = note.content.clone();
+    let edited = hash(&encode(&edit, link.encoding, link.line_ending)) != link.hash;
+    if reload(note, link)? {
+        if edited && note.content != edit {
+            return Ok(WriteBack::Conflict(edit));
+        }
+        return Ok(WriteBack::Reloaded);
+    }
+    let path = Path::new(&link.path);
+    if !path.exists() {
+        log::warn!("Linked file {} no longer exists", link.path);
+        return Ok(WriteBack::Unchanged);
+    }
+    let bytes = encode(&note.content, link.encoding, link.line_ending);
+    let new_hash = hash(&bytes);
+    if new_hash == link.hash {
+        return Ok(WriteBack::Unchanged);
+    }
+    fs::write(path, &bytes).map_err(|e| format!("{}: {}", path.display(), e))?;
+    link.hash = new_hash;
+    link.modified_ms = modified_ms(path);
+    Ok(WriteBack::Written)
+}
+
+/// [`write_back`] for a note being saved, if it is linked on this device.
+pub fn write_back_saved(app: &tauri::AppHandle, note: &mut Note) -> Result<WriteBack, String> {
+    let Some(state) = app.try_state::<LinkedState>() else {
+        return Ok(WriteBack::Unchanged);
+    };
+    state.update(|links| match links.get_mut(&note.id) {
+        Some(link) => write_back(note, link),
+        None => Ok(WriteBack::Unchanged),
+    })?
+}
+
+/// Tells an open editor that the backend replaced its note.
+pub fn notify_updated(app: &tauri::AppHandle, note: &Note) {
+    if app.get_webview_window(&note.id).is_some() {
+        app.emit_to(note.id.as_str(), "note-updated", note).ok();
+    }
+}
+
+/// Starts watching linked files for edits made by other programs.
+pub fn init(app: &tauri::AppHandle) {
+    app.manage(LinkedState::open(app));
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(POLL_INTERVAL);
+        if let Err(e) = poll(&app) {
+            log::warn!("Linked files: {}", e);
+        }
+    });
+}
+
+fn poll(app: &tauri::AppHandle) -> Result<(), String> {
+    let state = app.state::<NotesState>();
+    let mut store = state.0.lock().map_err(|e| e.to_string())?;
+    let reloaded = app.state::<LinkedState>().update(|links| {
+        let mut reloaded = Vec::new();
+        for note in store.notes.iter_mut().filter(|n| !n.deleted) {
+            let Some(link) = links.get_mut(&note.id) else {
+                continue;
+            };
+            match reload(note, link) {
+                Ok(true) => reloaded.push(note.clone()),
+                Ok(false) => {}
+                Err(e) => log::debug!("Linked files: {}", e),
+            }
+        }
+        reloaded
+    })?;
+    if reloaded.is_empty() {
+        return Ok(());
+    }
+    store.save(app)?;
+    drop(store);
+    for note in &reloaded {
+        log::info!("Linked files: reloaded {}", note.title);
+        notify_updated(app, note);
+    }
+    Ok(())
+}
+
+/// Opens `path` as a linked note, reusing the note already linked to it.
+#[tauri::command]
+pub fn open_linked_file(
+    app: tauri::AppHandle,
+    state: State<'_, NotesState>,
+    linked: State<'_, LinkedState>,
+    path: String,
+) -> Result<Note, String> {
+    log::debug!("Command: open_linked_file called for {}", path);
+    let path = fs::canonicalize(&path).map_err(|e| format!("{}: {}", path, e))?;
+    let key = path.to_string_lossy().to_string();
+
+    let mut store = state.0.lock().map_err(|e| e.to_string())?;
+    let note = linked.update(|links| {
+        let existing = store
+            .notes
+            .iter_mut()
+            .find(|n| !n.deleted && links.get(&n.id).is_some_and(|link| link.path == key));
+        match existing {
+            Some(note) => {
+                if let Some(link) = links.get_mut(&note.id) {
+                    reload(note, link)?;
+                }
+                Ok(note.clone())
+            }
+            None => {
+                let (note, link) = read_linked(&path)?;
+                links.insert(note.id.clone(), link);
+                store.add_note(note.clone());
+                Ok::<_, String>(note)
+            }
+        }
+    })??;
+    store.save(&app)?;
+    Ok(note)
+}
+
+/// Stops mirroring a note to its file; both keep their current content.
+#[tauri::command]
+pub fn unlink_file(linked: State<'_, LinkedState>, note_id: String) -> Result<(), String> {
+    log::debug!("Command: unlink_file called for id: {}", note_id);
+    linked
+        .update(|links| links.remove(&note_id))?
+        .map(|_| ())
+        .ok_or_else(|| "Note is not linked to a file".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(bytes: &[u8]) {
+        let (content, encoding, line_ending) = decode(bytes).unwrap();
+        assert_eq!(encode(&content, encoding, line_ending), bytes);
+    }
+
+    #[test]
+    fn encodings_and_line_endings_roundtrip() {
+        roundtrip(b"# Title\nbody\n");
+        roundtrip(b"# Title\r\nbody\r\n");
+        roundtrip(b"no trailing newline");
+        roundtrip(b"mixed\r\nline\nendings\r\n");
+        roundtrip("\u{feff}# メモ\r\n".as_bytes());
+        roundtrip(&encode(
+            "# メモ\n本文\n",
+            FileEncoding::Utf16Le,
+            LineEnding::Crlf,
+        ));
+        roundtrip(&encode("# メモ\n", FileEncoding::Utf16Be, LineEnding::Lf));
+    }
+
+    #[test]
+    fn crlf_files_are_edited_with_lf() {
+        let (content, encoding, line_ending) = decode(b"a\r\nb\r\n").unwrap();
+        assert_eq!(content, "a\nb\n");
+        assert_eq!(encoding, FileEncoding::Utf8);
+        assert_eq!(line_ending, LineEnding::Crlf);
+        assert_eq!(
+            encode("a\nb\nc\n", encoding, line_ending),
+            b"a\r\nb\r\nc\r\n"
+        );
+    }
+
+    #[test]
+    fn bom_is_detected() {
+        let (content, encoding, _) = decode("\u{feff}text".as_bytes()).unwrap();
+        assert_eq!(content, "text");
+        assert_eq!(encoding, FileEncoding::Utf8Bom);
+    }
+
+    #[test]
+    fn binary_files_are_rejected() {
+        assert!(decode(&[0x82, 0xa0, 0xff]).is_err());
+        assert!(decode(&[0xff, 0xfe, 0x41]).is_err());
+    }
+}
@@ -1,16 +1,26 @@
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use tauri::Manager;
 use uuid::Uuid;
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct WindowState {
     pub x: i32,
     pub y: i32,
     pub width: u32,
     pub height: u32,
+    /// Monitor the window was last on.
+    #[serde(default)]
+    pub monitor: Option<String>,
+    /// Scale factor the rectangle was recorded at, in physical pixels.
+    /// States without it predate multi-monitor support.
+    #[serde(default)]
+    pub scale_factor: Option<f64>,
 }
 
 impl Default for WindowState {
@@ -20,11 +30,69 @@ impl Default for WindowState {
             y: 100,
             width: 300,
             height: 400,
+            monitor: None,
+            scale_factor: None,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// How long saving waits for another process to release `notes.lock`.
+pub const STORE_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const STORE_LOCK_RETRY: Duration = Duration::from_millis(50);
+
+/// Lowest opacity a note can be set to, so it never becomes invisible.
+pub const MIN_OPACITY: f64 = 0.2;
+
+/// Per-note sticky-note behaviour of its window.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WindowPrefs {
+    #[serde(default)]
+    pub always_on_top: bool,
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+    /// Undecorated window; the note header is the drag handle.
+    #[serde(default)]
+    pub compact: bool,
+    /// Kept below other windows and out of the taskbar.
+    #[serde(default)]
+    pub pinned_to_desktop: bool,
+    /// Mouse events pass through to whatever is behind the note.
+    #[serde(default)]
+    pub click_through: bool,
+}
+
+fn default_opacity() -> f64 {
+    1.0
+}
+
+impl Default for WindowPrefs {
+    fn default() -> Self {
+        Self {
+            always_on_top: false,
+            opacity: 1.0,
+            compact: false,
+            pinned_to_desktop: false,
+            click_through: false,
+        }
+    }
+}
+
+impl WindowPrefs {
+    /// Clamps the opacity and resolves conflicting layers; pinning wins.
+    pub fn normalized(mut self) -> Self {
+        self.opacity = if self.opacity.is_finite() {
+            self.opacity.clamp(MIN_OPACITY, 1.0)
+        } else {
+            1.0
+        };
+        if self.pinned_to_desktop {
+            self.always_on_top = false;
+        }
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Note {
     pub id: String,
     pub title: String,
@@ -33,6 +101,8 @@ pub struct Note {
     pub updated_at: String,
     #[serde(default)]
     pub window_state: WindowState,
+    #[serde(default)]
+    pub window_prefs: WindowPrefs,
     #[serde(default = "default_color")]
     pub color: String,
     #[serde(default)]
@@ -59,25 +129,71 @@ impl Note {
             created_at: now.clone(),
             updated_at: now,
             window_state: WindowState::default(),
+            window_prefs: WindowPrefs::default(),
             color: String::from("#fef3c7"), // Warm yellow like sticky note
             deleted: false,
         }
     }
 }
 
+/// Resolves a file inside the app data dir, creating the directory if needed.
+pub fn app_data_file(app: &tauri::AppHandle, file_name: &str) -> PathBuf {
+    let app_dir = app
+        .path()
+        .app_data_dir()
+        .expect("Failed to get app data dir");
+    fs::create_dir_all(&app_dir).ok();
+    app_dir.join(file_name)
+}
+
+/// Runs `f` while holding the exclusive lock next to the store at `path`.
+///
+/// The app and the command-line tool both write `notes.json`; this keeps them apart.
+/// Gives up after [`STORE_LOCK_TIMEOUT`] so a stuck process cannot freeze the app.
+pub fn with_store_lock<T>(path: &Path, f: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+    with_store_lock_timeout(path, STORE_LOCK_TIMEOUT, f)
+}
+
+fn with_store_lock_timeout<T>(
+    path: &Path,
+    timeout: Duration,
+    f: impl FnOnce() -> Result<T, String>,
+) -> Result<T, String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path.with_extension("lock"))
+        .map_err(|e| e.to_string())?;
+    let mut lock = fd_lock::RwLock::new(file);
+    let deadline = Instant::now() + timeout;
+    loop {
+        match lock.try_write() {
+            Ok(_guard) => return f(),
+            Err(e) if e.kind() != ErrorKind::WouldBlock => return Err(e.to_string()),
+            Err(_) if Instant::now() >= deadline => {
+                return Err("The note store is locked by another process".to_string())
+            }
+            Err(_) => std::thread::sleep(STORE_LOCK_RETRY),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Default)]
 pub struct NotesStore {
     pub notes: Vec<Note>,
+    /// Ids present when the file was last read or written, to tell our
+    /// deletions apart from notes added by another process.
+    #[serde(skip)]
+    known_ids: HashSet<String>,
 }
 
 impl NotesStore {
     fn get_store_path(app: &tauri::AppHandle) -> PathBuf {
-        let app_dir = app
-            .path()
-            .app_data_dir()
-            .expect("Failed to get app data dir");
-        fs::create_dir_all(&app_dir).ok();
-        app_dir.join("notes.json")
+        app_data_file(app, "notes.json")
     }
 
     pub fn load(app: &tauri::AppHandle) -> Self {
@@ -87,7 +203,7 @@ impl NotesStore {
 
     pub fn load_from_path(path: &PathBuf) -> Self {
         log::debug!("NotesStore: Loading from {:?}", path);
-        if path.exists() {
+        let mut store: Self = if path.exists() {
             let content = fs::read_to_string(path).unwrap_or_default();
             let store = serde_json::from_str(&content).unwrap_or_default();
             log::debug!("NotesStore: Loaded store successfully");
@@ -95,18 +211,44 @@ impl NotesStore {
         } else {
             log::debug!("NotesStore: Store file does not exist, using default");
             Self::default()
-        }
+        };
+        store.known_ids = store.notes.iter().map(|n| n.id.clone()).collect();
+        store
     }
 
-    pub fn save(&self, app: &tauri::AppHandle) -> Result<(), String> {
+    /// Saves under the store lock, first merging changes other processes made.
+    pub fn save(&mut self, app: &tauri::AppHandle) -> Result<(), String> {
         let path = Self::get_store_path(app);
-        self.save_to_path(&path)
+        with_store_lock(&path, || {
+            if path.exists() {
+                self.reconcile(Self::load_from_path(&path).notes);
+            }
+            self.save_to_path(&path)
+        })?;
+        if let Some(history) = app.try_state::<crate::history::HistoryState>() {
+            history.record(&self.notes);
+        }
+        crate::tray::refresh(app, &self.notes);
+        Ok(())
     }
 
+    /// Merges notes read from disk: newer edits win, notes we removed stay removed.
+    pub fn reconcile(&mut self, on_disk: Vec<Note>) {
+        let external: Vec<Note> = on_disk
+            .into_iter()
+            .filter(|n| !self.known_ids.contains(&n.id) || self.get_note(&n.id).is_some())
+            .collect();
+        self.notes = crate::sync::merge_notes(&self.notes, &external);
+        self.known_ids = self.notes.iter().map(|n| n.id.clone()).collect();
+    }
+
+    /// Writes through a temporary file so readers never see a partial store.
     pub fn save_to_path(&self, path: &PathBuf) -> Result<(), String> {
         log::debug!("NotesStore: Saving to {:?}", path);
         let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
-        fs::write(path, content).map_err(|e| e.to_string())?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, content).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, path).map_err(|e| e.to_string())?;
         log::debug!("NotesStore: Saved successfully");
         Ok(())
     }
@@ -125,11 +267,44 @@ impl NotesStore {
         }
     }
 
+    /// Applies an edit from a note window, keeping the window geometry and prefs,
+    /// which only the backend changes.
+    pub fn update_note_content(&mut self, note: Note) {
+        if let Some(existing) = self.notes.iter_mut().find(|n| n.id == note.id) {
+            *existing = Note {
+                window_state: existing.window_state.clone(),
+                window_prefs: existing.window_prefs.clone(),
+                ..note
+            };
+        }
+    }
+
     pub fn delete_note(&mut self, id: &str) {
         self.notes.retain(|n| n.id != id);
     }
 }
 
+/// Test note with fixed timestamps; shared by the unit tests across the crate.
+#[cfg(test)]
+pub(crate) fn create_test_note(id: &str, title: &str) -> Note {
+    create_test_note_at(id, title, "2026-01-01T00:00:00+00:00")
+}
+
+#[cfg(test)]
+pub(crate) fn create_test_note_at(id: &str, title: &str, updated_at: &str) -> Note {
+    Note {
+        id: id.to_string(),
+        title: title.to_string(),
+        content: String::new(),
+        created_at: "2026-01-01T00:00:00+00:00".to_string(),
+        updated_at: updated_at.to_string(),
+        window_state: WindowState::default(),
+        window_prefs: WindowPrefs::default(),
+        color: "#fef3c7".to_string(),
+        deleted: false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -205,19 +380,6 @@ mod tests {
 
     // ── NotesStore tests ───────────────────────────────────
 
-    fn create_test_note(id: &str, title: &str) -> Note {
-        Note {
-            id: id.to_string(),
-            title: title.to_string(),
-            content: String::new(),
-            created_at: "2026-01-01T00:00:00+00:00".to_string(),
-            updated_at: "2026-01-01T00:00:00+00:00".to_string(),
-            window_state: WindowState::default(),
-            color: "#fef3c7".to_string(),
-            deleted: false,
-        }
-    }
-
     #[test]
     fn store_default_is_empty() {
         let store = NotesStore::default();
@@ -272,6 +434,27 @@ mod tests {
         assert_eq!(note.content, "New content");
     }
 
+    #[test]
+    fn store_update_note_content_keeps_window_fields() {
+        let mut store = NotesStore::default();
+        let mut stored = create_test_note("1", "Original");
+        stored.window_state.x = 700;
+        stored.window_state.monitor = Some("DISPLAY2".to_string());
+        stored.window_prefs.always_on_top = true;
+        store.add_note(stored);
+
+        store.update_note_content(Note {
+            content: "Edited".to_string(),
+            ..create_test_note("1", "Edited")
+        });
+
+        let note = store.get_note("1").unwrap();
+        assert_eq!(note.content, "Edited");
+        assert_eq!(note.window_state.x, 700);
+        assert_eq!(note.window_state.monitor.as_deref(), Some("DISPLAY2"));
+        assert!(note.window_prefs.always_on_top);
+    }
+
     #[test]
     fn store_update_nonexistent_note_is_noop() {
         let mut store = NotesStore::default();
@@ -301,6 +484,59 @@ mod tests {
         assert_eq!(store.notes.len(), 1);
     }
 
+    #[test]
+    fn reconcile_keeps_external_additions_and_newer_edits() {
+        let mut store = NotesStore::default();
+        store.reconcile(vec![
+            create_test_note("1", "One"),
+            create_test_note("2", "Two"),
+        ]);
+
+        let mut edited = create_test_note("1", "Edited elsewhere");
+        edited.updated_at = "2026-02-01T00:00:00+00:00".to_string();
+        store.reconcile(vec![
+            edited,
+            create_test_note("2", "Two"),
+            create_test_note("3", "Added elsewhere"),
+        ]);
+
+        assert_eq!(store.get_note("1").unwrap().title, "Edited elsewhere");
+        assert!(store.get_note("3").is_some());
+    }
+
+    #[test]
+    fn reconcile_does_not_resurrect_local_deletions() {
+        let mut store = NotesStore::default();
+        store.reconcile(vec![
+            create_test_note("1", "One"),
+            create_test_note("2", "Two"),
+        ]);
+        store.delete_note("1");
+        store.add_note(create_test_note("4", "Local"));
+
+        store.reconcile(vec![
+            create_test_note("1", "One"),
+            create_test_note("2", "Two"),
+        ]);
+
+        assert!(store.get_note("1").is_none());
+        assert!(store.get_note("4").is_some());
+        assert_eq!(store.notes.len(), 2);
+    }
+
+    #[test]
+    fn store_lock_gives_up_while_another_holder_keeps_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.json");
+        with_store_lock(&path, || {
+            let result = with_store_lock_timeout(&path, Duration::from_millis(100), || Ok(()));
+            assert!(result.unwrap_err().contains("locked"));
+            Ok(())
+        })
+        .unwrap();
+        assert!(with_store_lock(&path, || Ok(())).is_ok());
+    }
+
     #[test]
     fn store_serialization_roundtrip() {
         let mut store = NotesStore::default();
@@ -333,6 +569,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_note_deserialization_with_missing_fields() {
         let json = r#"{
             "id": "test-uuid",
@@ -357,6 +594,7 @@ mod tests {
             y: 20,
             width: 100,
             height: 200,
+            ..Default::default()
         };
         let json = serde_json::to_string(&ws).unwrap();
 
@@ -366,4 +604,31 @@ mod tests {
         assert!(json.contains("\"width\":100"));
         assert!(json.contains("\"height\":200"));
     }
+
+    #[test]
+    fn test_window_prefs_default_when_missing() {
+        let json = r#"{"id":"a","title":"t","content":"","created_at":"","updated_at":""}"#;
+        let note: Note = serde_json::from_str(json).unwrap();
+        assert_eq!(note.window_prefs, WindowPrefs::default());
+        assert_eq!(note.window_prefs.opacity, 1.0);
+    }
+
+    #[test]
+    fn test_window_prefs_normalized() {
+        let prefs = WindowPrefs {
+            always_on_top: true,
+            opacity: 0.0,
+            pinned_to_desktop: true,
+            ..Default::default()
+        }
+        .normalized();
+        assert_eq!(prefs.opacity, MIN_OPACITY);
+        assert!(!prefs.always_on_top);
+
+        let prefs = WindowPrefs {
+            opacity: f64::NAN,
+            ..Default::default()
+        };
+        assert_eq!(prefs.normalized().opacity, 1.0);
+    }
 }
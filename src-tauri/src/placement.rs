@@ -0,0 +1,409 @@
+use crate::notes::{Note, WindowState};
+use crate::sync::compare_timestamps;
+use serde::Deserialize;
+
+/// Windows with less than this share of their area on screen are moved.
+const MIN_VISIBLE_RATIO: f64 = 0.5;
+/// Distance from the monitor edge, and between tiles, for arranged windows.
+const LAYOUT_MARGIN: i32 = 40;
+/// Offset between consecutive cascaded windows.
+const CASCADE_STEP: i32 = 32;
+/// Cascades start over to the right after this many windows.
+const CASCADE_LENGTH: usize = 10;
+/// Vertical offset in a colour stack, enough to show each note's header.
+const STACK_STEP: i32 = 48;
+
+/// Usable area of a monitor in physical pixels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonitorArea {
+    pub name: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+}
+
+impl MonitorArea {
+    pub fn from_monitor(monitor: &tauri::Monitor) -> Self {
+        let area = monitor.work_area();
+        Self {
+            name: monitor.name().cloned(),
+            x: area.position.x,
+            y: area.position.y,
+            width: area.size.width,
+            height: area.size.height,
+            scale_factor: monitor.scale_factor(),
+        }
+    }
+
+    fn intersection(&self, rect: &Placement) -> i64 {
+        let overlap = |start: i32, len: u32, other_start: i32, other_len: u32| {
+            let end = (start as i64 + len as i64).min(other_start as i64 + other_len as i64);
+            (end - (start as i64).max(other_start as i64)).max(0)
+        };
+        overlap(self.x, self.width, rect.x, rect.width)
+            * overlap(self.y, self.height, rect.y, rect.height)
+    }
+
+    /// Fits `rect` inside this area, shrinking it if it is larger.
+    fn clamp(&self, rect: Placement) -> Placement {
+        let width = rect.width.min(self.width);
+        let height = rect.height.min(self.height);
+        let max_x = self.x + (self.width - width) as i32;
+        let max_y = self.y + (self.height - height) as i32;
+        Placement {
+            x: rect.x.clamp(self.x, max_x),
+            y: rect.y.clamp(self.y, max_y),
+            width,
+            height,
+        }
+    }
+}
+
+/// Window rectangle in physical pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Placement {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Placement {
+    fn area(&self) -> i64 {
+        self.width as i64 * self.height as i64
+    }
+}
+
+/// Converts a saved size to physical pixels on a monitor with `target_scale`.
+fn scaled(state: &WindowState, target_scale: f64) -> (u32, u32) {
+    let ratio = target_scale / state.scale_factor.unwrap_or(1.0);
+    (
+        (state.width as f64 * ratio).round() as u32,
+        (state.height as f64 * ratio).round() as u32,
+    )
+}
+
+/// Chooses where a saved window opens.
+///
+/// Windows that are still mostly visible keep their position. Others are
+/// moved onto the monitor they were saved on if it is still connected, else
+/// the monitor showing most of them, else the primary one.
+pub fn place(state: &WindowState, monitors: &[MonitorArea], primary: Option<usize>) -> Placement {
+    let saved = Placement {
+        x: state.x,
+        y: state.y,
+        width: state.width,
+        height: state.height,
+    };
+    if monitors.is_empty() {
+        return saved;
+    }
+
+    let visible: i64 = monitors.iter().map(|m| m.intersection(&saved)).sum();
+    let same_monitor = state
+        .monitor
+        .as_ref()
+        .and_then(|name| monitors.iter().find(|m| m.name.as_ref() == Some(name)));
+    if visible as f64 >= saved.area() as f64 * MIN_VISIBLE_RATIO {
+        let scale = same_monitor.map_or(state.scale_factor.unwrap_or(1.0), |m| m.scale_factor);
+        let (width, height) = scaled(state, scale);
+        return Placement {
+            width,
+            height,
+            ..saved
+        };
+    }
+
+    let target = same_monitor
+        .or_else(|| {
+            monitors
+                .iter()
+                .filter(|m| m.intersection(&saved) > 0)
+                .max_by_key(|m| m.intersection(&saved))
+        })
+        .or_else(|| primary.and_then(|index| monitors.get(index)))
+        .unwrap_or(&monitors[0]);
+    let (width, height) = scaled(state, target.scale_factor);
+    target.clamp(Placement {
+        width,
+        height,
+        ..saved
+    })
+}
+
+/// Cascades windows from the top-left corner of `area`.
+pub fn gather(states: &[WindowState], area: &MonitorArea) -> Vec<Placement> {
+    states
+        .iter()
+        .enumerate()
+        .map(|(index, state)| {
+            let step = (index % CASCADE_LENGTH) as i32 * CASCADE_STEP;
+            let column = (index / CASCADE_LENGTH) as i32 * CASCADE_STEP * 2;
+            let (width, height) = scaled(state, area.scale_factor);
+            area.clamp(Placement {
+                x: area.x + LAYOUT_MARGIN + step + column,
+                y: area.y + LAYOUT_MARGIN + step,
+                width,
+                height,
+            })
+        })
+        .collect()
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Layout {
+    Cascade,
+    /// Equal cells in a near-square grid.
+    Tile,
+    /// One column per colour, notes stacked so their headers show.
+    ByColor,
+    /// Grid ordered from the most recently updated note.
+    ByUpdated,
+}
+
+/// Splits `length` into `count` cells separated by the layout margin.
+fn cell_size(length: u32, count: usize) -> u32 {
+    let gaps = LAYOUT_MARGIN as i64 * (count as i64 + 1);
+    ((length as i64 - gaps) / count as i64).max(1) as u32
+}
+
+fn tile(count: usize, area: &MonitorArea) -> Vec<Placement> {
+    let columns = (count as f64).sqrt().ceil().max(1.0) as usize;
+    let rows = count.div_ceil(columns).max(1);
+    let width = cell_size(area.width, columns);
+    let height = cell_size(area.height, rows);
+    (0..count)
+        .map(|index| {
+            let (column, row) = ((index % columns) as i32, (index / columns) as i32);
+            area.clamp(Placement {
+                x: area.x + LAYOUT_MARGIN + column * (width as i32 + LAYOUT_MARGIN),
+                y: area.y + LAYOUT_MARGIN + row * (height as i32 + LAYOUT_MARGIN),
+                width,
+                height,
+            })
+        })
+        .collect()
+}
+
+/// Computes a layout for `notes` on `area`; placements follow the input order.
+pub fn arrange(layout: Layout, notes: &[&Note], area: &MonitorArea) -> Vec<Placement> {
+    if notes.is_empty() {
+        return Vec::new();
+    }
+    match layout {
+        Layout::Cascade => {
+            let states: Vec<WindowState> = notes.iter().map(|n| n.window_state.clone()).collect();
+            gather(&states, area)
+        }
+        Layout::Tile => tile(notes.len(), area),
+        Layout::ByUpdated => {
+            let mut order: Vec<usize> = (0..notes.len()).collect();
+            order.sort_by(|&a, &b| compare_timestamps(&notes[b].updated_at, &notes[a].updated_at));
+            let cells = tile(notes.len(), area);
+            let mut placements = cells.clone();
+            for (cell, &index) in cells.into_iter().zip(&order) {
+                placements[index] = cell;
+            }
+            placements
+        }
+        Layout::ByColor => {
+            let mut colors: Vec<&str> = notes.iter().map(|n| n.color.as_str()).collect();
+            colors.sort_unstable();
+            colors.dedup();
+            let column_width = cell_size(area.width, colors.len());
+            let mut next_in_column = vec![0i32; colors.len()];
+
+            let mut order: Vec<usize> = (0..notes.len()).collect();
+            order.sort_by(|&a, &b| compare_timestamps(&notes[b].updated_at, &notes[a].updated_at));
+            let mut placements = vec![None; notes.len()];
+            for index in order {
+                let note = notes[index];
+                let column = colors.binary_search(&note.color.as_str()).unwrap_or(0);
+                let (width, height) = scaled(&note.window_state, area.scale_factor);
+                let row = next_in_column[column];
+                next_in_column[column] += 1;
+                placements[index] = Some(area.clamp(Placement {
+                    x: area.x
+                        + LAYOUT_MARGIN
+                        + column as i32 * (column_width as i32 + LAYOUT_MARGIN),
+                    y: area.y + LAYOUT_MARGIN + row * STACK_STEP,
+                    width: width.min(column_width),
+                    height,
+                }));
+            }
+            placements.into_iter().flatten().collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::create_test_note_at;
+
+    fn monitor(name: &str, x: i32, width: u32, scale_factor: f64) -> MonitorArea {
+        MonitorArea {
+            name: Some(name.to_string()),
+            x,
+            y: 0,
+            width,
+            height: 1080,
+            scale_factor,
+        }
+    }
+
+    fn state(x: i32, y: i32, monitor: Option<&str>, scale_factor: Option<f64>) -> WindowState {
+        WindowState {
+            x,
+            y,
+            width: 300,
+            height: 400,
+            monitor: monitor.map(str::to_string),
+            scale_factor,
+        }
+    }
+
+    #[test]
+    fn visible_window_stays_put() {
+        let monitors = [monitor("A", 0, 1920, 1.0)];
+        let placed = place(&state(100, 200, Some("A"), Some(1.0)), &monitors, Some(0));
+        assert_eq!(
+            placed,
+            Placement {
+                x: 100,
+                y: 200,
+                width: 300,
+                height: 400
+            }
+        );
+    }
+
+    #[test]
+    fn window_on_disconnected_monitor_moves_to_primary() {
+        let monitors = [monitor("A", 0, 1920, 1.0)];
+        let placed = place(&state(2500, 100, Some("B"), Some(1.0)), &monitors, Some(0));
+        assert_eq!(placed.x, 1920 - 300);
+        assert_eq!(placed.y, 100);
+    }
+
+    #[test]
+    fn window_returns_to_its_monitor_after_rearrangement() {
+        // "B" used to be right of "A" and now sits left of it
+        let monitors = [monitor("A", 0, 1920, 1.0), monitor("B", -2560, 2560, 1.0)];
+        let placed = place(&state(2200, 50, Some("B"), Some(1.0)), &monitors, Some(0));
+        assert!(placed.x >= -2560 && placed.x + 300 <= 0, "{:?}", placed);
+    }
+
+    #[test]
+    fn size_follows_scale_factor_and_fits_monitor() {
+        let monitors = [monitor("Laptop", 0, 500, 2.0)];
+        let placed = place(&state(5000, 0, Some("4K"), Some(1.0)), &monitors, None);
+        assert_eq!(placed.width, 500);
+        assert_eq!(placed.height, 800);
+        assert_eq!(placed.x, 0);
+    }
+
+    #[test]
+    fn legacy_state_without_monitor_is_clamped() {
+        let monitors = [monitor("A", 0, 1920, 1.0)];
+        let placed = place(&state(-1000, -1000, None, None), &monitors, None);
+        assert_eq!((placed.x, placed.y), (0, 0));
+    }
+
+    #[test]
+    fn no_monitors_leaves_state_unchanged() {
+        let placed = place(&state(-1000, 5, None, None), &[], None);
+        assert_eq!((placed.x, placed.y), (-1000, 5));
+    }
+
+    #[test]
+    fn gather_cascades_inside_area() {
+        let area = monitor("A", 1920, 1920, 1.0);
+        let states: Vec<WindowState> = (0..12).map(|_| state(0, 0, None, None)).collect();
+        let placed = gather(&states, &area);
+        assert_eq!((placed[0].x, placed[0].y), (1920 + 40, 40));
+        assert_eq!((placed[1].x, placed[1].y), (1920 + 72, 72));
+        assert_eq!((placed[10].x, placed[10].y), (1920 + 104, 40));
+        assert!(placed
+            .iter()
+            .all(|p| area.intersection(p) == p.width as i64 * p.height as i64));
+    }
+
+    fn note(color: &str, updated_at: &str) -> Note {
+        Note {
+            color: color.to_string(),
+            ..create_test_note_at("", "", updated_at)
+        }
+    }
+
+    #[test]
+    fn tile_fills_a_grid_inside_area() {
+        let area = monitor("A", 0, 1920, 1.0);
+        let notes = [note("a", "1"), note("a", "2"), note("a", "3")];
+        let refs: Vec<&Note> = notes.iter().collect();
+        let placed = arrange(Layout::Tile, &refs, &area);
+        // 3 notes -> 2 columns x 2 rows
+        assert_eq!(placed[0].width, (1920 - 120) / 2);
+        assert_eq!(placed[0].height, (1080 - 120) / 2);
+        assert_eq!(placed[1].x, 40 + placed[0].width as i32 + 40);
+        assert_eq!(placed[2].y, 40 + placed[0].height as i32 + 40);
+        assert!(placed.iter().all(|p| area.intersection(p) == p.area()));
+    }
+
+    #[test]
+    fn by_updated_puts_newest_first() {
+        let area = monitor("A", 0, 1920, 1.0);
+        let notes = [note("a", "2026-01-01"), note("a", "2026-03-01")];
+        let refs: Vec<&Note> = notes.iter().collect();
+        let placed = arrange(Layout::ByUpdated, &refs, &area);
+        assert!(placed[1].x < placed[0].x);
+    }
+
+    #[test]
+    fn by_updated_compares_instants_across_offsets() {
+        let area = monitor("A", 0, 1920, 1.0);
+        // 10:00+09:00 is 01:00 UTC, earlier than 02:00Z
+        let notes = [
+            note("a", "2026-03-01T10:00:00+09:00"),
+            note("a", "2026-03-01T02:00:00Z"),
+        ];
+        let refs: Vec<&Note> = notes.iter().collect();
+        let placed = arrange(Layout::ByUpdated, &refs, &area);
+        assert!(placed[1].x < placed[0].x);
+    }
+
+    #[test]
+    fn by_color_stacks_each_color_in_a_column() {
+        let area = monitor("A", 0, 1920, 1.0);
+        let notes = [
+            note("#fef3c7", "1"),
+            note("#dbeafe", "2"),
+            note("#fef3c7", "3"),
+        ];
+        let refs: Vec<&Note> = notes.iter().collect();
+        let placed = arrange(Layout::ByColor, &refs, &area);
+        // "#dbeafe" sorts first and gets the left column
+        assert_eq!((placed[1].x, placed[1].y), (40, 40));
+        assert_eq!(placed[0].x, placed[2].x);
+        assert!(placed[0].x > placed[1].x);
+        // Newest note of a colour is on top of its stack
+        assert_eq!(placed[2].y, 40);
+        assert_eq!(placed[0].y, 40 + STACK_STEP);
+    }
+
+    #[test]
+    fn empty_layout_is_empty() {
+        let area = monitor("A", 0, 1920, 1.0);
+        for layout in [
+            Layout::Cascade,
+            Layout::Tile,
+            Layout::ByColor,
+            Layout::ByUpdated,
+        ] {
+            assert!(arrange(layout, &[], &area).is_empty());
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use crate::notes::app_data_file;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{Manager, State};
+
+const SESSION_FILE_NAME: &str = "session.json";
+
+/// Note windows that were open, so they can be reopened on the next start.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Session {
+    #[serde(default = "default_restore")]
+    pub restore: bool,
+    /// Note ids of open windows, back to front.
+    #[serde(default)]
+    pub windows: Vec<String>,
+    /// Label of the window that had focus last; may be `main`.
+    #[serde(default)]
+    pub focused: Option<String>,
+}
+
+fn default_restore() -> bool {
+    true
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            restore: true,
+            windows: Vec::new(),
+            focused: None,
+        }
+    }
+}
+
+impl Session {
+    pub fn opened(&mut self, note_id: &str) {
+        self.windows.retain(|id| id != note_id);
+        self.windows.push(note_id.to_string());
+    }
+
+    /// Raises a note window to the top of the stack and remembers the focus.
+    pub fn focused(&mut self, label: &str) {
+        if let Some(index) = self.windows.iter().position(|id| id == label) {
+            let id = self.windows.remove(index);
+            self.windows.push(id);
+        }
+        self.focused = Some(label.to_string());
+    }
+
+    pub fn closed(&mut self, note_id: &str) {
+        self.windows.retain(|id| id != note_id);
+        if self.focused.as_deref() == Some(note_id) {
+            self.focused = None;
+        }
+    }
+
+    /// Windows to reopen, back to front, skipping notes that no longer exist.
+    pub fn windows_to_restore(&self, exists: impl Fn(&str) -> bool) -> Vec<String> {
+        if !self.restore {
+            return Vec::new();
+        }
+        self.windows
+            .iter()
+            .filter(|id| exists(id))
+            .cloned()
+            .collect()
+    }
+}
+
+/// The current session, written through to `session.json` on every change.
+pub struct SessionState {
+    session: Mutex<Session>,
+    path: PathBuf,
+}
+
+impl SessionState {
+    pub fn load(path: PathBuf) -> Self {
+        let session = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            session: Mutex::new(session),
+            path,
+        }
+    }
+
+    pub fn open(app: &tauri::AppHandle) -> Self {
+        Self::load(app_data_file(app, SESSION_FILE_NAME))
+    }
+
+    pub fn snapshot(&self) -> Session {
+        self.session
+            .lock()
+            .map(|session| session.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn update(&self, change: impl FnOnce(&mut Session)) -> Result<(), String> {
+        let mut session = self.session.lock().map_err(|e| e.to_string())?;
+        let before = session.clone();
+        change(&mut session);
+        if *session == before {
+            return Ok(());
+        }
+        let content = serde_json::to_string_pretty(&*session).map_err(|e| e.to_string())?;
+        fs::write(&self.path, content).map_err(|e| e.to_string())
+    }
+}
+
+/// Keeps the session in step with focus changes and closed note windows.
+pub fn on_window_event(window: &tauri::Window, event: &tauri::WindowEvent) {
+    let app = window.app_handle();
+    let Some(state) = app.try_state::<SessionState>() else {
+        return;
+    };
+    let label = window.label();
+    let result = match event {
+        tauri::WindowEvent::Focused(true) => state.update(|session| session.focused(label)),
+        // Closing the last window quits the app; keep it so it comes back.
+        tauri::WindowEvent::CloseRequested { .. } if app.webview_windows().len() > 1 => {
+            state.update(|session| session.closed(label))
+        }
+        _ => Ok(()),
+    };
+    if let Err(e) = result {
+        log::warn!("Session: cannot save: {}", e);
+    }
+}
+
+#[tauri::command]
+pub fn get_session_restore(session: State<'_, SessionState>) -> bool {
+    log::debug!("Command: get_session_restore called");
+    session.snapshot().restore
+}
+
+#[tauri::command]
+pub fn set_session_restore(session: State<'_, SessionState>, enabled: bool) -> Result<(), String> {
+    log::debug!("Command: set_session_restore called (enabled: {})", enabled);
+    session.update(|session| session.restore = enabled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn session(windows: &[&str]) -> Session {
+        let mut session = Session::default();
+        for id in windows {
+            session.opened(id);
+        }
+        session
+    }
+
+    #[test]
+    fn focus_moves_window_to_front() {
+        let mut session = session(&["a", "b", "c"]);
+        session.focused("a");
+        assert_eq!(session.windows, ["b", "c", "a"]);
+        assert_eq!(session.focused.as_deref(), Some("a"));
+
+        session.focused("main");
+        assert_eq!(session.windows, ["b", "c", "a"]);
+        assert_eq!(session.focused.as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn closing_forgets_window_and_focus() {
+        let mut session = session(&["a", "b"]);
+        session.focused("b");
+        session.closed("b");
+        assert_eq!(session.windows, ["a"]);
+        assert_eq!(session.focused, None);
+    }
+
+    #[test]
+    fn restore_skips_missing_notes_and_honours_setting() {
+        let mut session = session(&["a", "gone", "b"]);
+        assert_eq!(session.windows_to_restore(|id| id != "gone"), ["a", "b"]);
+        session.restore = false;
+        assert!(session.windows_to_restore(|_| true).is_empty());
+    }
+
+    #[test]
+    fn state_persists_changes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(SESSION_FILE_NAME);
+        let state = SessionState::load(path.clone());
+        state.update(|session| session.opened("a")).unwrap();
+        state.update(|session| session.restore = false).unwrap();
+
+        let reloaded = SessionState::load(path).snapshot();
+        assert_eq!(reloaded.windows, ["a"]);
+        assert!(!reloaded.restore);
+    }
+
+    #[test]
+    fn missing_file_defaults_to_restoring() {
+        let dir = tempdir().unwrap();
+        let session = SessionState::load(dir.path().join(SESSION_FILE_NAME)).snapshot();
+        assert!(session.restore);
+        assert!(session.windows.is_empty());
+    }
+}
@@ -0,0 +1,426 @@
+use super::{RemoteDocument, RemoteSnapshot, StoreError, SyncCursor, SyncProvider, SyncSettings};
+use crate::notes::Note;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::time::Duration;
+
+const MULTIPART_BOUNDARY: &str = "markdown_notes_boundary";
+
+/// Base URLs of the Google APIs, overridable so tests can point at a mock server.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DriveEndpoints {
+    pub token_url: String,
+    pub revoke_url: String,
+    pub api_base: String,
+    pub upload_base: String,
+}
+
+impl Default for DriveEndpoints {
+    fn default() -> Self {
+        Self {
+            token_url: "https://oauth2.googleapis.com/token".to_string(),
+            revoke_url: "https://oauth2.googleapis.com/revoke".to_string(),
+            api_base: "https://www.googleapis.com/drive/v3".to_string(),
+            upload_base: "https://www.googleapis.com/upload/drive/v3".to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct DriveTokens {
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// Unix timestamp (seconds) after which `access_token` is no longer valid.
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+}
+
+/// OAuth client credentials of the desktop app.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct DriveConfig {
+    pub client_id: String,
+    #[serde(default)]
+    pub client_secret: String,
+}
+
+/// Metadata of a Drive file; `version` increases on every change.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct DriveFile {
+    pub id: String,
+    pub version: String,
+}
+
+#[derive(Deserialize)]
+struct FileList {
+    #[serde(default)]
+    files: Vec<DriveFile>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    expires_in: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct OAuthError {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(response: ureq::Response) -> Result<T, String> {
+    let body = response.into_string().map_err(|e| e.to_string())?;
+    serde_json::from_str(&body).map_err(|e| format!("Unexpected Google response: {}", e))
+}
+
+fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(30))
+        .build()
+}
+
+fn token_request(
+    endpoints: &DriveEndpoints,
+    action: &str,
+    form: &[(&str, &str)],
+) -> Result<TokenResponse, String> {
+    match agent().post(&endpoints.token_url).send_form(form) {
+        Ok(response) => read_json(response),
+        Err(ureq::Error::Status(_, response)) => {
+            let message = match read_json::<OAuthError>(response) {
+                Ok(e) => e.error_description.unwrap_or(e.error),
+                Err(_) => "unknown error".to_string(),
+            };
+            Err(format!("{} failed: {}", action, message))
+        }
+        Err(e) => Err(format!("{} failed: {}", action, e)),
+    }
+}
+
+fn expires_at(token: &TokenResponse) -> Option<i64> {
+    token
+        .expires_in
+        .map(|secs| chrono::Utc::now().timestamp() + secs)
+}
+
+/// Exchanges an authorization code (PKCE flow) for tokens.
+pub fn exchange_code(
+    endpoints: &DriveEndpoints,
+    config: &DriveConfig,
+    code: &str,
+    code_verifier: &str,
+    redirect_uri: &str,
+) -> Result<DriveTokens, String> {
+    let token = token_request(
+        endpoints,
+        "Token exchange",
+        &[
+            ("code", code),
+            ("client_id", &config.client_id),
+            ("client_secret", &config.client_secret),
+            ("redirect_uri", redirect_uri),
+            ("grant_type", "authorization_code"),
+            ("code_verifier", code_verifier),
+        ],
+    )?;
+    Ok(DriveTokens {
+        expires_at: expires_at(&token),
+        access_token: token.access_token,
+        refresh_token: token.refresh_token,
+    })
+}
+
+/// Obtains a fresh access token; Google usually keeps the refresh token unchanged.
+pub fn refresh_tokens(
+    endpoints: &DriveEndpoints,
+    config: &DriveConfig,
+    refresh_token: &str,
+) -> Result<DriveTokens, String> {
+    let token = token_request(
+        endpoints,
+        "Token refresh",
+        &[
+            ("client_id", &config.client_id),
+            ("client_secret", &config.client_secret),
+            ("refresh_token", refresh_token),
+            ("grant_type", "refresh_token"),
+        ],
+    )?;
+    Ok(DriveTokens {
+        expires_at: expires_at(&token),
+        access_token: token.access_token,
+        refresh_token: token
+            .refresh_token
+            .or_else(|| Some(refresh_token.to_string())),
+    })
+}
+
+/// Revokes a token (and with a refresh token, the whole grant).
+pub fn revoke_token(endpoints: &DriveEndpoints, token: &str) -> Result<(), String> {
+    match agent()
+        .post(&endpoints.revoke_url)
+        .send_form(&[("token", token)])
+    {
+        Ok(_) => Ok(()),
+        // Already revoked or expired: nothing left to do.
+        Err(ureq::Error::Status(400, _)) => Ok(()),
+        Err(e) => Err(format!("Token revocation failed: {}", e)),
+    }
+}
+
+pub struct DriveClient {
+    agent: ureq::Agent,
+    endpoints: DriveEndpoints,
+    access_token: String,
+}
+
+impl DriveClient {
+    pub fn new(endpoints: DriveEndpoints, access_token: &str) -> Self {
+        Self {
+            agent: agent(),
+            endpoints,
+            access_token: access_token.to_string(),
+        }
+    }
+
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        self.agent
+            .request(method, url)
+            .set("Authorization", &format!("Bearer {}", self.access_token))
+    }
+
+    /// Finds a non-trashed file by exact name.
+    pub fn find_file(&self, name: &str) -> Result<Option<DriveFile>, String> {
+        let query = format!("name = '{}' and trashed = false", name.replace('\'', "\\'"));
+        let list: FileList = self
+            .request("GET", &format!("{}/files", self.endpoints.api_base))
+            .query("q", &query)
+            .query("spaces", "drive")
+            .query("fields", "files(id,version)")
+            .call()
+            .map_err(|e| describe_error("list files", e))
+            .and_then(read_json)?;
+        Ok(list.files.into_iter().next())
+    }
+
+    pub fn metadata(&self, file_id: &str) -> Result<Option<DriveFile>, String> {
+        let url = format!("{}/files/{}", self.endpoints.api_base, file_id);
+        match self
+            .request("GET", &url)
+            .query("fields", "id,version")
+            .call()
+        {
+            Ok(response) => read_json(response).map(Some),
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(e) => Err(describe_error("read file metadata", e)),
+        }
+    }
+
+    pub fn download(&self, file_id: &str) -> Result<String, String> {
+        let url = format!("{}/files/{}", self.endpoints.api_base, file_id);
+        self.request("GET", &url)
+            .query("alt", "media")
+            .call()
+            .map_err(|e| describe_error("download", e))?
+            .into_string()
+            .map_err(|e| e.to_string())
+    }
+
+    /// Creates a JSON file with metadata and content in one multipart upload.
+    pub fn create(&self, name: &str, content: &str) -> Result<DriveFile, String> {
+        let metadata = serde_json::json!({ "name": name, "mimeType": "application/json" });
+        let body = format!(
+            "--{b}\r\nContent-Type: application/json; charset=UTF-8\r\n\r\n{metadata}\r\n\
+             --{b}\r\nContent-Type: application/json\r\n\r\n{content}\r\n--{b}--\r\n",
+            b = MULTIPART_BOUNDARY,
+        );
+        self.request("POST", &format!("{}/files", self.endpoints.upload_base))
+            .query("uploadType", "multipart")
+            .query("fields", "id,version")
+            .set(
+                "Content-Type",
+                &format!("multipart/related; boundary={}", MULTIPART_BOUNDARY),
+            )
+            .send_string(&body)
+            .map_err(|e| describe_error("upload", e))
+            .and_then(read_json)
+    }
+
+    /// Replaces the content if the file is still at `expected_version`.
+    ///
+    /// Drive has no conditional writes, so the version is checked right before
+    /// uploading; the window is small and the next sync repairs any loss.
+    pub fn update(
+        &self,
+        file_id: &str,
+        content: &str,
+        expected_version: Option<&str>,
+    ) -> Result<DriveFile, StoreError> {
+        if let Some(expected) = expected_version {
+            let current = self.metadata(file_id).map_err(StoreError::Other)?;
+            if current.map(|f| f.version).as_deref() != Some(expected) {
+                return Err(StoreError::Conflict);
+            }
+        }
+        let url = format!("{}/files/{}", self.endpoints.upload_base, file_id);
+        self.request("PATCH", &url)
+            .query("uploadType", "media")
+            .query("fields", "id,version")
+            .set("Content-Type", "application/json")
+            .send_string(content)
+            .map_err(|e| describe_error("upload", e))
+            .and_then(read_json)
+            .map_err(StoreError::Other)
+    }
+}
+
+fn describe_error(action: &str, error: ureq::Error) -> String {
+    match error {
+        ureq::Error::Status(401, _) => {
+            "Google Drive session expired. Please sign in again.".to_string()
+        }
+        ureq::Error::Status(code, response) => {
+            format!(
+                "Google Drive {} failed: {} {}",
+                action,
+                code,
+                response.status_text()
+            )
+        }
+        ureq::Error::Transport(transport) => {
+            format!("Google Drive {} failed: {}", action, transport)
+        }
+    }
+}
+
+/// The shared sync file on Drive, located lazily by name.
+pub struct DriveDocument {
+    client: DriveClient,
+    file_id: RefCell<Option<String>>,
+}
+
+impl DriveDocument {
+    pub fn new(client: DriveClient) -> Self {
+        Self {
+            client,
+            file_id: RefCell::new(None),
+        }
+    }
+
+    fn current(&self) -> Result<Option<DriveFile>, String> {
+        let cached = self.file_id.borrow().clone();
+        if let Some(id) = cached {
+            if let Some(file) = self.client.metadata(&id)? {
+                return Ok(Some(file));
+            }
+        }
+        let file = self.client.find_file(super::SYNC_FILE_NAME)?;
+        *self.file_id.borrow_mut() = file.as_ref().map(|f| f.id.clone());
+        Ok(file)
+    }
+}
+
+impl RemoteDocument for DriveDocument {
+    fn version(&self) -> Result<Option<String>, String> {
+        Ok(self.current()?.map(|f| f.version))
+    }
+
+    fn fetch(&self) -> Result<Option<RemoteSnapshot>, String> {
+        // Read the version first: if the file changes mid-download the stale
+        // version makes the following store fail and the sync retry.
+        let Some(file) = self.current()? else {
+            return Ok(None);
+        };
+        let body = self.client.download(&file.id)?;
+        let notes = serde_json::from_str(&body)
+            .map_err(|e| format!("Remote sync file is not valid: {}", e))?;
+        Ok(Some(RemoteSnapshot {
+            notes,
+            version: file.version,
+        }))
+    }
+
+    fn store(&self, notes: &[Note], expected_version: Option<&str>) -> Result<String, StoreError> {
+        let body = serde_json::to_string(notes).map_err(|e| StoreError::Other(e.to_string()))?;
+        let existing = self.current().map_err(StoreError::Other)?;
+        let file = match (existing, expected_version) {
+            (Some(file), Some(_)) => self.client.update(&file.id, &body, expected_version)?,
+            (Some(_), None) => return Err(StoreError::Conflict),
+            (None, Some(_)) => return Err(StoreError::Conflict),
+            (None, None) => self
+                .client
+                .create(super::SYNC_FILE_NAME, &body)
+                .map_err(StoreError::Other)?,
+        };
+        *self.file_id.borrow_mut() = Some(file.id);
+        Ok(file.version)
+    }
+}
+
+/// Exchanges the code from the loopback redirect and switches sync to the backend Drive client.
+#[tauri::command]
+pub async fn connect_google_drive(
+    app: tauri::AppHandle,
+    code: String,
+    code_verifier: String,
+    redirect_uri: String,
+    client_id: String,
+    client_secret: String,
+) -> Result<String, String> {
+    log::debug!("Command: connect_google_drive called");
+    let config = DriveConfig {
+        client_id,
+        client_secret,
+    };
+    let session = crate::tokens::GoogleSession::open(&app);
+    let tokens = exchange_code(
+        session.endpoints(),
+        &config,
+        &code,
+        &code_verifier,
+        &redirect_uri,
+    )?;
+    let access_token = tokens.access_token.clone();
+    session.save(&crate::tokens::GoogleCredentials { config, tokens })?;
+
+    SyncSettings::update(&app, |settings| {
+        settings.google_drive_connected = true;
+        settings.provider = SyncProvider::GoogleDrive;
+        settings.cursor = SyncCursor::default();
+        Ok(())
+    })?;
+    log::info!("Google Drive: connected");
+    Ok(access_token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_endpoints_point_at_google() {
+        let endpoints = DriveEndpoints::default();
+        assert!(endpoints
+            .token_url
+            .starts_with("https://oauth2.googleapis.com/"));
+        assert!(endpoints.upload_base.contains("/upload/"));
+    }
+
+    #[test]
+    fn drive_file_parses_string_version() {
+        let file: DriveFile = serde_json::from_str(r#"{"id":"abc","version":"42"}"#).unwrap();
+        assert_eq!(file.version, "42");
+    }
+
+    #[test]
+    fn tokens_default_missing_fields() {
+        let tokens: DriveTokens = serde_json::from_str(r#"{"access_token":"t"}"#).unwrap();
+        assert_eq!(tokens.refresh_token, None);
+        assert_eq!(tokens.expires_at, None);
+    }
+}
@@ -0,0 +1,319 @@
+use super::{merge_notes, SyncCursor, SyncProvider, SyncSettings};
+use crate::notes::Note;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Sub-directory created inside the user's shared folder.
+const SYNC_DIR_NAME: &str = "markdown-notes-sync";
+const LOG_EXTENSION: &str = "jsonl";
+/// Own log is rewritten once it holds this many more entries than live notes.
+const COMPACT_SLACK: usize = 64;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct FolderConfig {
+    /// Directory kept in sync by Syncthing, Dropbox, a network share, ...
+    pub path: PathBuf,
+}
+
+/// Syncs through per-device append-only change logs in a shared directory.
+///
+/// Each device only ever writes `<device_id>.jsonl`, so file sync tools never
+/// see two writers on the same file. Every line is a full `Note` snapshot.
+pub struct FolderSync {
+    dir: PathBuf,
+    device_id: String,
+}
+
+impl FolderSync {
+    pub fn new(root: &Path, device_id: &str) -> Result<Self, String> {
+        if !root.is_dir() {
+            return Err(format!("Sync folder does not exist: {}", root.display()));
+        }
+        let dir = root.join(SYNC_DIR_NAME);
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        Ok(Self {
+            dir,
+            device_id: device_id.to_string(),
+        })
+    }
+
+    fn own_log_path(&self) -> PathBuf {
+        self.dir
+            .join(format!("{}.{}", self.device_id, LOG_EXTENSION))
+    }
+
+    /// Logs written by any device, including conflict copies made by sync tools.
+    fn log_paths(&self) -> Result<Vec<PathBuf>, String> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(&self.dir)
+            .map_err(|e| e.to_string())?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                let hidden = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .map_or(true, |n| n.starts_with('.') || n.starts_with('~'));
+                !hidden
+                    && path.is_file()
+                    && path.extension().and_then(|e| e.to_str()) == Some(LOG_EXTENSION)
+            })
+            .collect();
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// Reads a change log, skipping lines that are truncated or otherwise unreadable.
+    pub fn read_log(path: &Path) -> Vec<Note> {
+        let content = match fs::read(path) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(e) => {
+                log::warn!("FolderSync: cannot read {:?}: {}", path, e);
+                return Vec::new();
+            }
+        };
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str::<Note>(line) {
+                Ok(note) => Some(note),
+                Err(e) => {
+                    log::debug!("FolderSync: skipping bad line in {:?}: {}", path, e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn append(&self, entries: &[Note]) -> Result<(), String> {
+        let path = self.own_log_path();
+        // A crash mid-write can leave an unterminated line; never glue onto it.
+        let needs_newline = fs::read(&path)
+            .map(|bytes| bytes.last().is_some_and(|b| *b != b'\n'))
+            .unwrap_or(false);
+
+        let mut buffer = String::new();
+        if needs_newline {
+            buffer.push('\n');
+        }
+        for note in entries {
+            buffer.push_str(&serde_json::to_string(note).map_err(|e| e.to_string())?);
+            buffer.push('\n');
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| e.to_string())?;
+        file.write_all(buffer.as_bytes())
+            .and_then(|_| file.sync_all())
+            .map_err(|e| e.to_string())
+    }
+
+    fn compact(&self, live: &[Note]) -> Result<(), String> {
+        let path = self.own_log_path();
+        let tmp_path = path.with_extension("tmp");
+        let mut buffer = String::new();
+        for note in live {
+            buffer.push_str(&serde_json::to_string(note).map_err(|e| e.to_string())?);
+            buffer.push('\n');
+        }
+        fs::write(&tmp_path, buffer).map_err(|e| e.to_string())?;
+        fs::rename(&tmp_path, &path).map_err(|e| e.to_string())
+    }
+
+    /// Merges every device's log into `local` and records local changes in our own log.
+    pub fn sync(&self, local: &[Note]) -> Result<Vec<Note>, String> {
+        let own_path = self.own_log_path();
+        let own_entries = Self::read_log(&own_path);
+        let own_state = merge_notes(&[], &own_entries);
+
+        let mut known = own_state.clone();
+        for path in self.log_paths()? {
+            if path != own_path {
+                known = merge_notes(&known, &Self::read_log(&path));
+            }
+        }
+
+        let merged = merge_notes(local, &known);
+        let changed: Vec<Note> = merged
+            .iter()
+            .filter(|note| !known.contains(note))
+            .cloned()
+            .collect();
+
+        if !changed.is_empty() {
+            log::info!("FolderSync: recording {} changed notes", changed.len());
+            self.append(&changed)?;
+        }
+
+        let own_live = merge_notes(&own_state, &changed);
+        if own_entries.len() + changed.len() > own_live.len() + COMPACT_SLACK {
+            log::info!("FolderSync: compacting {:?}", own_path);
+            self.compact(&own_live)?;
+        }
+
+        Ok(merged)
+    }
+}
+
+#[tauri::command]
+pub fn configure_folder_sync(app: tauri::AppHandle, path: String) -> Result<(), String> {
+    log::debug!("Command: configure_folder_sync called for {}", path);
+    let config = FolderConfig {
+        path: PathBuf::from(path),
+    };
+    SyncSettings::update(&app, |settings| {
+        FolderSync::new(&config.path, settings.device_id())?;
+        settings.folder = Some(config);
+        settings.provider = SyncProvider::Folder;
+        settings.cursor = SyncCursor::default();
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::create_test_note_at as note;
+    use tempfile::tempdir;
+
+    #[test]
+    fn two_devices_converge() {
+        let dir = tempdir().unwrap();
+        let device_a = FolderSync::new(dir.path(), "device-a").unwrap();
+        let device_b = FolderSync::new(dir.path(), "device-b").unwrap();
+
+        let a = device_a
+            .sync(&[note("1", "from a", "2026-01-01T00:00:00Z")])
+            .unwrap();
+        let b = device_b
+            .sync(&[note("2", "from b", "2026-01-01T00:00:00Z")])
+            .unwrap();
+        assert_eq!(b.len(), 2);
+
+        let a = device_a.sync(&a).unwrap();
+        assert_eq!(a.len(), 2);
+        assert!(a.iter().any(|n| n.title == "from b"));
+    }
+
+    #[test]
+    fn newer_edit_wins_across_devices() {
+        let dir = tempdir().unwrap();
+        let device_a = FolderSync::new(dir.path(), "device-a").unwrap();
+        let device_b = FolderSync::new(dir.path(), "device-b").unwrap();
+
+        device_a
+            .sync(&[note("1", "old", "2026-01-01T00:00:00Z")])
+            .unwrap();
+        device_b
+            .sync(&[note("1", "new", "2026-01-02T00:00:00Z")])
+            .unwrap();
+
+        let a = device_a
+            .sync(&[note("1", "old", "2026-01-01T00:00:00Z")])
+            .unwrap();
+        assert_eq!(a[0].title, "new");
+    }
+
+    #[test]
+    fn each_device_writes_only_its_own_log() {
+        let dir = tempdir().unwrap();
+        let device_a = FolderSync::new(dir.path(), "device-a").unwrap();
+        device_a
+            .sync(&[note("1", "a", "2026-01-01T00:00:00Z")])
+            .unwrap();
+
+        let sync_dir = dir.path().join(SYNC_DIR_NAME);
+        let files: Vec<_> = fs::read_dir(&sync_dir).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        assert!(sync_dir.join("device-a.jsonl").exists());
+    }
+
+    #[test]
+    fn unchanged_notes_are_not_logged_again() {
+        let dir = tempdir().unwrap();
+        let device_a = FolderSync::new(dir.path(), "device-a").unwrap();
+        let notes = vec![note("1", "a", "2026-01-01T00:00:00Z")];
+
+        device_a.sync(&notes).unwrap();
+        device_a.sync(&notes).unwrap();
+
+        let entries = FolderSync::read_log(&device_a.own_log_path());
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[test]
+    fn partial_lines_are_tolerated() {
+        let dir = tempdir().unwrap();
+        let device_a = FolderSync::new(dir.path(), "device-a").unwrap();
+        let device_b = FolderSync::new(dir.path(), "device-b").unwrap();
+        device_b
+            .sync(&[note("2", "b", "2026-01-01T00:00:00Z")])
+            .unwrap();
+
+        // Simulate a sync tool that has only delivered half of the next line
+        let b_log = device_b.own_log_path();
+        let mut file = OpenOptions::new().append(true).open(&b_log).unwrap();
+        file.write_all(br#"{"id":"3","title":"trunc"#).unwrap();
+
+        let merged = device_a.sync(&[]).unwrap();
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].id, "2");
+
+        // Our own log keeps working after a torn write
+        let a_log = device_a.own_log_path();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&a_log)
+            .unwrap();
+        file.write_all(b"{\"id\":").unwrap();
+        device_a
+            .sync(&[note("4", "d", "2026-01-01T00:00:00Z")])
+            .unwrap();
+        assert!(FolderSync::read_log(&a_log).iter().any(|n| n.id == "4"));
+    }
+
+    #[test]
+    fn conflict_copies_are_merged_and_temp_files_ignored() {
+        let dir = tempdir().unwrap();
+        let device_a = FolderSync::new(dir.path(), "device-a").unwrap();
+        let sync_dir = dir.path().join(SYNC_DIR_NAME);
+
+        let copy = serde_json::to_string(&note("5", "copy", "2026-01-01T00:00:00Z")).unwrap();
+        fs::write(
+            sync_dir.join("device-b (conflicted copy).jsonl"),
+            copy + "\n",
+        )
+        .unwrap();
+        let temp = serde_json::to_string(&note("6", "temp", "2026-01-01T00:00:00Z")).unwrap();
+        fs::write(sync_dir.join(".syncthing.device-c.jsonl"), temp + "\n").unwrap();
+
+        let merged = device_a.sync(&[]).unwrap();
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].id, "5");
+    }
+
+    #[test]
+    fn own_log_is_compacted() {
+        let dir = tempdir().unwrap();
+        let device_a = FolderSync::new(dir.path(), "device-a").unwrap();
+
+        for i in 0..(COMPACT_SLACK + 5) {
+            let updated_at = format!("2026-01-01T00:{:02}:{:02}Z", i / 60, i % 60);
+            device_a.sync(&[note("1", "edit", &updated_at)]).unwrap();
+        }
+
+        let entries = FolderSync::read_log(&device_a.own_log_path());
+        assert!(entries.len() <= COMPACT_SLACK);
+        assert!(!device_a.own_log_path().with_extension("tmp").exists());
+    }
+
+    #[test]
+    fn missing_root_is_an_error() {
+        let dir = tempdir().unwrap();
+        assert!(FolderSync::new(&dir.path().join("nope"), "device-a").is_err());
+    }
+}
@@ -0,0 +1,1003 @@
+use super::{merge_notes, SyncSettings};
+use crate::notes::Note;
+use crate::tokens::PrivateStore;
+use crate::NotesState;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{Manager, State};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+const PROTOCOL_VERSION: u32 = 1;
+const SERVICE_TYPE: &str = "_markdown-notes._tcp.local.";
+const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+/// Frames before the handshake are hellos; anyone on the network can send them.
+const MAX_HELLO_LEN: usize = 4 * 1024;
+/// Connections served at once; further ones are closed until a slot frees up.
+const MAX_CONNECTIONS: usize = 8;
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+const PAIRING_WINDOW: Duration = Duration::from_secs(120);
+const PAIRING_ATTEMPTS: u32 = 3;
+/// Crockford base32: no I, L, O or U to misread.
+const CODE_ALPHABET: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const CODE_LEN: usize = 10;
+
+/// A device this installation has paired with.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct PairedPeer {
+    pub device_id: String,
+    pub name: String,
+    /// Hex-encoded 32-byte secret agreed during pairing.
+    ///
+    /// Kept in a `PrivateStore` per peer; read from older `sync.json` files but never
+    /// written back (see `with_secret`).
+    #[serde(default, skip_serializing)]
+    pub secret: String,
+    #[serde(default)]
+    pub last_address: Option<String>,
+}
+
+impl PairedPeer {
+    fn secret_bytes(&self) -> Option<[u8; 32]> {
+        let bytes = decode_hex(&self.secret)?;
+        bytes.try_into().ok()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DiscoveredPeer {
+    pub device_id: String,
+    pub name: String,
+    pub addresses: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct LanIdentity {
+    pub device_id: String,
+    pub name: String,
+}
+
+/// What the network layer needs from the app: notes in, merged notes out, peer secrets.
+pub trait LanHost: Send + Sync + 'static {
+    fn local_notes(&self) -> Result<Vec<Note>, String>;
+    /// Folds `notes` into the store and returns the resulting collection.
+    fn apply_notes(&self, notes: &[Note]) -> Result<Vec<Note>, String>;
+    fn peer(&self, device_id: &str) -> Option<PairedPeer>;
+    fn save_peer(&self, peer: PairedPeer) -> Result<(), String>;
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Mode {
+    Pair,
+    Sync,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Hello {
+    version: u32,
+    device_id: String,
+    name: String,
+    mode: Mode,
+    public_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    Confirm,
+    Notes { notes: Vec<Note> },
+    Error { message: String },
+}
+
+struct PendingPairing {
+    code: String,
+    expires_at: Instant,
+    attempts_left: u32,
+}
+
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes).expect("OS random number generator unavailable");
+    bytes
+}
+
+/// Generates a one-time pairing code such as `7KQ2M-X9D4A` (50 bits).
+pub fn generate_pairing_code() -> String {
+    let code: String = random_bytes::<CODE_LEN>()
+        .iter()
+        .map(|b| CODE_ALPHABET[(*b as usize) % CODE_ALPHABET.len()] as char)
+        .collect();
+    format!("{}-{}", &code[..CODE_LEN / 2], &code[CODE_LEN / 2..])
+}
+
+/// Canonical form of a typed code: case, separators and look-alikes are ignored.
+pub fn normalize_pairing_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| match c.to_ascii_uppercase() {
+            'O' => '0',
+            'I' | 'L' => '1',
+            other => other,
+        })
+        .collect()
+}
+
+fn default_device_name() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .unwrap_or_else(|_| "Markdown Notes".to_string())
+}
+
+/// Length-prefixed frames, encrypted once the handshake has derived keys.
+struct Channel {
+    stream: TcpStream,
+    send: Option<(ChaCha20Poly1305, u64)>,
+    receive: Option<(ChaCha20Poly1305, u64)>,
+}
+
+impl Channel {
+    fn new(stream: TcpStream) -> Result<Self, String> {
+        stream
+            .set_read_timeout(Some(IO_TIMEOUT))
+            .and_then(|_| stream.set_write_timeout(Some(IO_TIMEOUT)))
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            stream,
+            send: None,
+            receive: None,
+        })
+    }
+
+    fn nonce(counter: u64) -> Nonce {
+        let mut nonce = [0u8; 12];
+        nonce[4..].copy_from_slice(&counter.to_be_bytes());
+        Nonce::from(nonce)
+    }
+
+    fn write_frame(&mut self, payload: &[u8]) -> Result<(), String> {
+        let data = match self.send.as_mut() {
+            Some((cipher, counter)) => {
+                let sealed = cipher
+                    .encrypt(&Self::nonce(*counter), payload)
+                    .map_err(|_| "Encryption failed".to_string())?;
+                *counter += 1;
+                sealed
+            }
+            None => payload.to_vec(),
+        };
+        let len = u32::try_from(data.len()).map_err(|_| "Frame too large".to_string())?;
+        self.stream
+            .write_all(&len.to_be_bytes())
+            .and_then(|_| self.stream.write_all(&data))
+            .map_err(|e| e.to_string())
+    }
+
+    fn read_frame(&mut self) -> Result<Vec<u8>, String> {
+        let mut len = [0u8; 4];
+        self.stream
+            .read_exact(&mut len)
+            .map_err(|e| e.to_string())?;
+        let len = u32::from_be_bytes(len) as usize;
+        let max_len = if self.receive.is_some() {
+            MAX_FRAME_LEN
+        } else {
+            MAX_HELLO_LEN
+        };
+        if len > max_len {
+            return Err("Frame too large".to_string());
+        }
+        // Grows with the bytes that actually arrive rather than the announced length
+        let mut data = Vec::new();
+        (&mut self.stream)
+            .take(len as u64)
+            .read_to_end(&mut data)
+            .map_err(|e| e.to_string())?;
+        if data.len() != len {
+            return Err("Connection closed mid-frame".to_string());
+        }
+
+        match self.receive.as_mut() {
+            Some((cipher, counter)) => {
+                let opened = cipher
+                    .decrypt(&Self::nonce(*counter), data.as_slice())
+                    .map_err(|_| "Peer failed authentication".to_string())?;
+                *counter += 1;
+                Ok(opened)
+            }
+            None => Ok(data),
+        }
+    }
+
+    fn send(&mut self, message: &Message) -> Result<(), String> {
+        let bytes = serde_json::to_vec(message).map_err(|e| e.to_string())?;
+        self.write_frame(&bytes)
+    }
+
+    fn receive(&mut self) -> Result<Message, String> {
+        let bytes = self.read_frame()?;
+        match serde_json::from_slice(&bytes).map_err(|e| e.to_string())? {
+            Message::Error { message } => Err(format!("Peer reported: {}", message)),
+            message => Ok(message),
+        }
+    }
+}
+
+struct SessionKeys {
+    client_to_server: [u8; 32],
+    server_to_client: [u8; 32],
+    pair_secret: [u8; 32],
+}
+
+/// Derives directional keys from the ephemeral DH result and the pre-shared
+/// material (pairing code or stored pair secret), bound to both hellos.
+fn derive_keys(shared: &[u8; 32], psk: &[u8], transcript: &[u8]) -> SessionKeys {
+    let mut ikm = shared.to_vec();
+    ikm.extend_from_slice(psk);
+    let salt = Sha256::digest(transcript);
+    let hkdf = Hkdf::<Sha256>::new(Some(&salt), &ikm);
+    let mut okm = [0u8; 96];
+    hkdf.expand(b"markdown-notes lan v1", &mut okm)
+        .expect("96 bytes is a valid HKDF output length");
+
+    let mut keys = SessionKeys {
+        client_to_server: [0u8; 32],
+        server_to_client: [0u8; 32],
+        pair_secret: [0u8; 32],
+    };
+    keys.client_to_server.copy_from_slice(&okm[..32]);
+    keys.server_to_client.copy_from_slice(&okm[32..64]);
+    keys.pair_secret.copy_from_slice(&okm[64..]);
+    keys
+}
+
+fn hello_for(identity: &LanIdentity, mode: Mode, secret: &StaticSecret) -> Hello {
+    Hello {
+        version: PROTOCOL_VERSION,
+        device_id: identity.device_id.clone(),
+        name: identity.name.clone(),
+        mode,
+        public_key: encode_hex(PublicKey::from(secret).as_bytes()),
+    }
+}
+
+/// Checks the peer's hello and returns the ephemeral DH output.
+fn agree(secret: &StaticSecret, peer: &Hello) -> Result<[u8; 32], String> {
+    if peer.version != PROTOCOL_VERSION {
+        return Err(format!("Unsupported protocol version {}", peer.version));
+    }
+    let peer_key: [u8; 32] = decode_hex(&peer.public_key)
+        .and_then(|b| b.try_into().ok())
+        .ok_or("Peer sent an invalid public key")?;
+    let shared = secret.diffie_hellman(&PublicKey::from(peer_key));
+    if !shared.was_contributory() {
+        return Err("Peer sent a weak public key".to_string());
+    }
+    Ok(*shared.as_bytes())
+}
+
+/// Client side of the hello exchange: returns (peer hello, transcript, DH output).
+fn exchange_hellos(
+    channel: &mut Channel,
+    identity: &LanIdentity,
+    mode: Mode,
+) -> Result<(Hello, Vec<u8>, [u8; 32]), String> {
+    let secret = StaticSecret::from(random_bytes::<32>());
+    let ours =
+        serde_json::to_vec(&hello_for(identity, mode, &secret)).map_err(|e| e.to_string())?;
+    channel.write_frame(&ours)?;
+    let theirs = channel.read_frame()?;
+    let peer: Hello = serde_json::from_slice(&theirs).map_err(|e| e.to_string())?;
+    let shared = agree(&secret, &peer)?;
+    Ok((peer, [ours, theirs].concat(), shared))
+}
+
+fn install_keys(channel: &mut Channel, keys: &SessionKeys, is_client: bool) {
+    let c2s = ChaCha20Poly1305::new(Key::from_slice(&keys.client_to_server));
+    let s2c = ChaCha20Poly1305::new(Key::from_slice(&keys.server_to_client));
+    if is_client {
+        channel.send = Some((c2s, 0));
+        channel.receive = Some((s2c, 0));
+    } else {
+        channel.send = Some((s2c, 0));
+        channel.receive = Some((c2s, 0));
+    }
+}
+
+/// Listens for peers and initiates pairing and sync sessions.
+pub struct LanNode {
+    identity: LanIdentity,
+    host: Arc<dyn LanHost>,
+    pairing: Arc<Mutex<Option<PendingPairing>>>,
+    local_addr: SocketAddr,
+    stopped: Arc<AtomicBool>,
+}
+
+impl LanNode {
+    pub fn start(
+        identity: LanIdentity,
+        host: Arc<dyn LanHost>,
+        bind: SocketAddr,
+    ) -> Result<Self, String> {
+        let listener = TcpListener::bind(bind).map_err(|e| e.to_string())?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        let local_addr = listener.local_addr().map_err(|e| e.to_string())?;
+        log::info!("LAN: listening on {}", local_addr);
+
+        let node = Self {
+            identity,
+            host,
+            pairing: Arc::new(Mutex::new(None)),
+            local_addr,
+            stopped: Arc::new(AtomicBool::new(false)),
+        };
+
+        let identity = node.identity.clone();
+        let host = Arc::clone(&node.host);
+        let pairing = Arc::clone(&node.pairing);
+        let stopped = Arc::clone(&node.stopped);
+        let active = Arc::new(AtomicUsize::new(0));
+        std::thread::spawn(move || {
+            while !stopped.load(Ordering::SeqCst) {
+                match listener.accept() {
+                    Ok((stream, addr)) => {
+                        if active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                            active.fetch_sub(1, Ordering::SeqCst);
+                            log::debug!("LAN: too many connections, closing {}", addr);
+                            continue;
+                        }
+                        let identity = identity.clone();
+                        let host = Arc::clone(&host);
+                        let pairing = Arc::clone(&pairing);
+                        let active = Arc::clone(&active);
+                        std::thread::spawn(move || {
+                            if let Err(e) = serve(stream, &identity, host.as_ref(), &pairing) {
+                                log::warn!("LAN: session with {} failed: {}", addr, e);
+                            }
+                            active.fetch_sub(1, Ordering::SeqCst);
+                        });
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        std::thread::sleep(Duration::from_millis(100));
+                    }
+                    Err(e) => log::warn!("LAN: accept failed: {}", e),
+                }
+            }
+            log::debug!("LAN: listener stopped");
+        });
+
+        Ok(node)
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn identity(&self) -> &LanIdentity {
+        &self.identity
+    }
+
+    /// Opens a pairing window and returns the code to show the user.
+    pub fn begin_pairing(&self) -> String {
+        let code = generate_pairing_code();
+        if let Ok(mut pairing) = self.pairing.lock() {
+            *pairing = Some(PendingPairing {
+                code: normalize_pairing_code(&code),
+                expires_at: Instant::now() + PAIRING_WINDOW,
+                attempts_left: PAIRING_ATTEMPTS,
+            });
+        }
+        code
+    }
+
+    fn connect(address: &str) -> Result<Channel, String> {
+        let addr = address
+            .to_socket_addrs()
+            .map_err(|e| e.to_string())?
+            .next()
+            .ok_or_else(|| format!("Cannot resolve {}", address))?;
+        let stream = TcpStream::connect_timeout(&addr, Duration::from_secs(5))
+            .map_err(|e| format!("Cannot reach {}: {}", address, e))?;
+        Channel::new(stream)
+    }
+
+    /// Pairs with the device at `address` that is currently showing `code`.
+    pub fn pair_with(&self, address: &str, code: &str) -> Result<PairedPeer, String> {
+        let mut channel = Self::connect(address)?;
+        let (peer, transcript, shared) = exchange_hellos(&mut channel, &self.identity, Mode::Pair)?;
+        let keys = derive_keys(
+            &shared,
+            normalize_pairing_code(code).as_bytes(),
+            &transcript,
+        );
+        install_keys(&mut channel, &keys, true);
+
+        channel.send(&Message::Confirm)?;
+        match channel.receive() {
+            Ok(Message::Confirm) => {}
+            Ok(_) => return Err("Unexpected reply while pairing".to_string()),
+            Err(_) => return Err("Pairing failed: wrong or expired code".to_string()),
+        }
+
+        let paired = PairedPeer {
+            device_id: peer.device_id,
+            name: peer.name,
+            secret: encode_hex(&keys.pair_secret),
+            last_address: Some(address.to_string()),
+        };
+        self.host.save_peer(paired.clone())?;
+        log::info!("LAN: paired with {}", paired.name);
+        Ok(paired)
+    }
+
+    /// Exchanges notes with a paired device and returns the merged collection.
+    pub fn sync_with(&self, address: &str, peer: &PairedPeer) -> Result<Vec<Note>, String> {
+        let secret = peer.secret_bytes().ok_or("Stored pair secret is invalid")?;
+        let mut channel = Self::connect(address)?;
+        let (hello, transcript, shared) =
+            exchange_hellos(&mut channel, &self.identity, Mode::Sync)?;
+        if hello.device_id != peer.device_id {
+            return Err(format!("{} is not {}", address, peer.name));
+        }
+        let keys = derive_keys(&shared, &secret, &transcript);
+        install_keys(&mut channel, &keys, true);
+
+        channel.send(&Message::Notes {
+            notes: self.host.local_notes()?,
+        })?;
+        let remote = match channel.receive()? {
+            Message::Notes { notes } => notes,
+            _ => return Err("Unexpected reply while syncing".to_string()),
+        };
+        let merged = self.host.apply_notes(&remote)?;
+
+        self.host.save_peer(PairedPeer {
+            last_address: Some(address.to_string()),
+            ..peer.clone()
+        })?;
+        Ok(merged)
+    }
+
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Drop for LanNode {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Server side of one connection.
+fn serve(
+    stream: TcpStream,
+    identity: &LanIdentity,
+    host: &dyn LanHost,
+    pairing: &Mutex<Option<PendingPairing>>,
+) -> Result<(), String> {
+    stream.set_nonblocking(false).map_err(|e| e.to_string())?;
+    let mut channel = Channel::new(stream)?;
+    let theirs = channel.read_frame()?;
+    let peer_hello: Hello = serde_json::from_slice(&theirs).map_err(|e| e.to_string())?;
+
+    // Look up the pre-shared material before answering so unknown peers learn nothing.
+    let psk = match peer_hello.mode {
+        Mode::Pair => {
+            let pending = pairing.lock().map_err(|e| e.to_string())?;
+            match pending.as_ref() {
+                Some(p) if p.expires_at > Instant::now() => p.code.as_bytes().to_vec(),
+                _ => return Err("Pairing request outside a pairing window".to_string()),
+            }
+        }
+        Mode::Sync => host
+            .peer(&peer_hello.device_id)
+            .and_then(|p| p.secret_bytes())
+            .ok_or_else(|| format!("Unknown device {}", peer_hello.device_id))?
+            .to_vec(),
+    };
+
+    let secret = StaticSecret::from(random_bytes::<32>());
+    let ours = serde_json::to_vec(&hello_for(identity, peer_hello.mode, &secret))
+        .map_err(|e| e.to_string())?;
+    channel.write_frame(&ours)?;
+    let shared = agree(&secret, &peer_hello)?;
+    let keys = derive_keys(&shared, &psk, &[theirs, ours].concat());
+    install_keys(&mut channel, &keys, false);
+
+    let first = channel.receive();
+    if peer_hello.mode == Mode::Pair {
+        let mut pending = pairing.lock().map_err(|e| e.to_string())?;
+        match first {
+            Ok(Message::Confirm) => {
+                *pending = None;
+            }
+            _ => {
+                if let Some(p) = pending.as_mut() {
+                    p.attempts_left = p.attempts_left.saturating_sub(1);
+                    if p.attempts_left == 0 {
+                        log::warn!("LAN: too many failed pairing attempts, closing window");
+                        *pending = None;
+                    }
+                }
+                return Err("Pairing attempt with a wrong code".to_string());
+            }
+        }
+        drop(pending);
+
+        // Persist before confirming so the peer never holds a pairing we lost.
+        host.save_peer(PairedPeer {
+            device_id: peer_hello.device_id,
+            name: peer_hello.name.clone(),
+            secret: encode_hex(&keys.pair_secret),
+            last_address: None,
+        })?;
+        channel.send(&Message::Confirm)?;
+        log::info!("LAN: paired with {}", peer_hello.name);
+        return Ok(());
+    }
+
+    match first? {
+        Message::Notes { notes } => {
+            let merged = host.apply_notes(&notes)?;
+            channel.send(&Message::Notes { notes: merged })
+        }
+        _ => {
+            channel.send(&Message::Error {
+                message: "Expected notes".to_string(),
+            })?;
+            Err("Unexpected message from peer".to_string())
+        }
+    }
+}
+
+/// mDNS advertisement and browsing for other instances on the network.
+pub struct LanDiscovery {
+    daemon: mdns_sd::ServiceDaemon,
+    device_id: String,
+}
+
+impl LanDiscovery {
+    pub fn advertise(identity: &LanIdentity, port: u16) -> Result<Self, String> {
+        let daemon = mdns_sd::ServiceDaemon::new().map_err(|e| e.to_string())?;
+        let properties = HashMap::from([
+            ("id".to_string(), identity.device_id.clone()),
+            ("name".to_string(), identity.name.clone()),
+        ]);
+        let info = mdns_sd::ServiceInfo::new(
+            SERVICE_TYPE,
+            &identity.device_id,
+            &format!("{}.local.", identity.device_id),
+            "",
+            port,
+            properties,
+        )
+        .map_err(|e| e.to_string())?
+        .enable_addr_auto();
+        daemon.register(info).map_err(|e| e.to_string())?;
+        Ok(Self {
+            daemon,
+            device_id: identity.device_id.clone(),
+        })
+    }
+
+    /// Browses for `timeout` and returns every other instance that answered.
+    pub fn discover(&self, timeout: Duration) -> Result<Vec<DiscoveredPeer>, String> {
+        let receiver = self
+            .daemon
+            .browse(SERVICE_TYPE)
+            .map_err(|e| e.to_string())?;
+        let deadline = Instant::now() + timeout;
+        let mut peers: Vec<DiscoveredPeer> = Vec::new();
+
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            let Ok(event) = receiver.recv_timeout(remaining) else {
+                break;
+            };
+            if let mdns_sd::ServiceEvent::ServiceResolved(info) = event {
+                let Some(device_id) = info.get_property_val_str("id") else {
+                    continue;
+                };
+                if device_id == self.device_id || peers.iter().any(|p| p.device_id == device_id) {
+                    continue;
+                }
+                peers.push(DiscoveredPeer {
+                    device_id: device_id.to_string(),
+                    name: info
+                        .get_property_val_str("name")
+                        .unwrap_or(device_id)
+                        .to_string(),
+                    addresses: info
+                        .get_addresses()
+                        .iter()
+                        .map(|ip| SocketAddr::new(*ip, info.get_port()).to_string())
+                        .collect(),
+                });
+            }
+        }
+        self.daemon.stop_browse(SERVICE_TYPE).ok();
+        Ok(peers)
+    }
+}
+
+impl Drop for LanDiscovery {
+    fn drop(&mut self) {
+        self.daemon.shutdown().ok();
+    }
+}
+
+/// Store for a peer's pair secret, `lan_peer_<device_id>.bin`.
+fn secret_store(app: &tauri::AppHandle, device_id: &str) -> Result<PrivateStore, String> {
+    // Device ids come from the network; keep them from naming other files
+    if device_id.is_empty()
+        || !device_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return Err(format!("Invalid device id: {}", device_id));
+    }
+    Ok(PrivateStore::open(
+        app,
+        &format!("lan_peer_{}.bin", device_id),
+    ))
+}
+
+/// Returns `peer` with the secret from its store.
+///
+/// A secret still read from an older `sync.json` is moved to the store first; the
+/// next save of the settings drops it from the file.
+fn with_secret(app: &tauri::AppHandle, peer: &PairedPeer) -> Result<PairedPeer, String> {
+    let store = secret_store(app, &peer.device_id)?;
+    let mut peer = peer.clone();
+    if peer.secret.is_empty() {
+        if let Some(secret) = store.load() {
+            peer.secret = String::from_utf8(secret).map_err(|e| e.to_string())?;
+        }
+    } else {
+        store.save(peer.secret.as_bytes())?;
+    }
+    Ok(peer)
+}
+
+/// Moves pair secrets still kept in `sync.json` to their stores.
+fn migrate_secrets(app: &tauri::AppHandle) -> Result<(), String> {
+    if SyncSettings::load(app)
+        .lan_peers
+        .iter()
+        .all(|p| p.secret.is_empty())
+    {
+        return Ok(());
+    }
+    SyncSettings::update(app, |settings| {
+        for peer in &settings.lan_peers {
+            with_secret(app, peer)?;
+        }
+        Ok(())
+    })
+}
+
+/// `LanHost` backed by the running app's `NotesState` and sync settings.
+struct AppLanHost {
+    app: tauri::AppHandle,
+}
+
+impl LanHost for AppLanHost {
+    fn local_notes(&self) -> Result<Vec<Note>, String> {
+        let state = self.app.state::<NotesState>();
+        let store = state.0.lock().map_err(|e| e.to_string())?;
+        Ok(store.notes.clone())
+    }
+
+    fn apply_notes(&self, notes: &[Note]) -> Result<Vec<Note>, String> {
+        let state = self.app.state::<NotesState>();
+        let mut store = state.0.lock().map_err(|e| e.to_string())?;
+        store.notes = merge_notes(&store.notes, notes);
+        store.save(&self.app)?;
+        Ok(store.notes.clone())
+    }
+
+    fn peer(&self, device_id: &str) -> Option<PairedPeer> {
+        let peer = SyncSettings::load(&self.app)
+            .lan_peers
+            .into_iter()
+            .find(|p| p.device_id == device_id)?;
+        with_secret(&self.app, &peer)
+            .map_err(|e| log::warn!("LAN: {}", e))
+            .ok()
+    }
+
+    fn save_peer(&self, peer: PairedPeer) -> Result<(), String> {
+        secret_store(&self.app, &peer.device_id)?.save(peer.secret.as_bytes())?;
+        SyncSettings::update(&self.app, |settings| {
+            settings.lan_peers.retain(|p| p.device_id != peer.device_id);
+            settings.lan_peers.push(peer);
+            Ok(())
+        })
+    }
+}
+
+struct LanService {
+    node: LanNode,
+    discovery: Option<LanDiscovery>,
+}
+
+/// The running service; commands clone it out so network I/O never holds the lock.
+#[derive(Default)]
+pub struct LanState(Mutex<Option<Arc<LanService>>>);
+
+impl LanState {
+    fn start(&self, app: &tauri::AppHandle) -> Result<(), String> {
+        let mut service = self.0.lock().map_err(|e| e.to_string())?;
+        if service.is_some() {
+            return Ok(());
+        }
+        let identity = LanIdentity {
+            device_id: SyncSettings::update(app, |settings| Ok(settings.device_id().to_string()))?,
+            name: default_device_name(),
+        };
+
+        let host = Arc::new(AppLanHost { app: app.clone() });
+        let node = LanNode::start(identity, host, SocketAddr::from(([0, 0, 0, 0], 0)))?;
+        let discovery = LanDiscovery::advertise(node.identity(), node.local_addr().port())
+            .map_err(|e| log::warn!("LAN: mDNS unavailable: {}", e))
+            .ok();
+        *service = Some(Arc::new(LanService { node, discovery }));
+        Ok(())
+    }
+
+    fn stop(&self) {
+        if let Ok(mut service) = self.0.lock() {
+            // A sync still running keeps its clone; the listener stops now
+            if let Some(service) = service.take() {
+                service.node.stop();
+            }
+        }
+    }
+
+    fn with_service<T>(
+        &self,
+        f: impl FnOnce(&LanService) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let service = self
+            .0
+            .lock()
+            .map_err(|e| e.to_string())?
+            .clone()
+            .ok_or("LAN sync is not enabled")?;
+        f(&service)
+    }
+}
+
+/// Starts the LAN listener during setup if the user enabled it.
+pub fn init(app: &tauri::AppHandle) {
+    if let Err(e) = migrate_secrets(app) {
+        log::warn!("LAN: cannot move pair secrets out of sync.json: {}", e);
+    }
+    let state = LanState::default();
+    if SyncSettings::load(app).lan_enabled {
+        if let Err(e) = state.start(app) {
+            log::error!("LAN: failed to start: {}", e);
+        }
+    }
+    app.manage(state);
+}
+
+#[tauri::command]
+pub fn lan_set_enabled(
+    app: tauri::AppHandle,
+    lan: State<'_, LanState>,
+    enabled: bool,
+) -> Result<(), String> {
+    log::debug!("Command: lan_set_enabled called: {}", enabled);
+    if enabled {
+        lan.start(&app)?;
+    } else {
+        lan.stop();
+    }
+    SyncSettings::update(&app, |settings| {
+        settings.lan_enabled = enabled;
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn lan_begin_pairing(lan: State<'_, LanState>) -> Result<String, String> {
+    log::debug!("Command: lan_begin_pairing called");
+    lan.with_service(|service| Ok(service.node.begin_pairing()))
+}
+
+#[tauri::command]
+pub async fn lan_discover(lan: State<'_, LanState>) -> Result<Vec<DiscoveredPeer>, String> {
+    log::debug!("Command: lan_discover called");
+    lan.with_service(|service| match &service.discovery {
+        Some(discovery) => discovery.discover(Duration::from_secs(2)),
+        None => Err("mDNS discovery is unavailable on this network".to_string()),
+    })
+}
+
+#[tauri::command]
+pub async fn lan_pair(
+    lan: State<'_, LanState>,
+    address: String,
+    code: String,
+) -> Result<PairedPeer, String> {
+    log::debug!("Command: lan_pair called for {}", address);
+    lan.with_service(|service| service.node.pair_with(&address, &code))
+        .map(|peer| PairedPeer {
+            secret: String::new(),
+            ..peer
+        })
+}
+
+#[tauri::command]
+pub fn lan_list_peers(app: tauri::AppHandle) -> Vec<PairedPeer> {
+    log::debug!("Command: lan_list_peers called");
+    SyncSettings::load(&app).redacted().lan_peers
+}
+
+#[tauri::command]
+pub fn lan_unpair(app: tauri::AppHandle, device_id: String) -> Result<(), String> {
+    log::debug!("Command: lan_unpair called for {}", device_id);
+    secret_store(&app, &device_id)?.clear()?;
+    SyncSettings::update(&app, |settings| {
+        settings.lan_peers.retain(|p| p.device_id != device_id);
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub async fn lan_sync_now(
+    app: tauri::AppHandle,
+    lan: State<'_, LanState>,
+) -> Result<Vec<Note>, String> {
+    log::debug!("Command: lan_sync_now called");
+    let peers = SyncSettings::load(&app)
+        .lan_peers
+        .iter()
+        .map(|peer| with_secret(&app, peer))
+        .collect::<Result<Vec<_>, _>>()?;
+    lan.with_service(|service| {
+        let discovered = match &service.discovery {
+            Some(discovery) => discovery.discover(Duration::from_secs(2))?,
+            None => Vec::new(),
+        };
+
+        let mut notes = None;
+        let mut errors = Vec::new();
+        for peer in &peers {
+            let mut addresses: Vec<String> = discovered
+                .iter()
+                .filter(|d| d.device_id == peer.device_id)
+                .flat_map(|d| d.addresses.clone())
+                .collect();
+            addresses.extend(peer.last_address.clone());
+
+            let result = addresses
+                .iter()
+                .find_map(|address| service.node.sync_with(address, peer).ok());
+            match result {
+                Some(merged) => notes = Some(merged),
+                None => errors.push(peer.name.clone()),
+            }
+        }
+
+        match notes {
+            Some(notes) => {
+                if !errors.is_empty() {
+                    log::warn!("LAN: could not reach {}", errors.join(", "));
+                }
+                Ok(notes)
+            }
+            None if peers.is_empty() => Err("No paired devices".to_string()),
+            None => Err(format!("Could not reach {}", errors.join(", "))),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oversized_hello_is_rejected_before_reading_it() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        client
+            .write_all(&(MAX_HELLO_LEN as u32 + 1).to_be_bytes())
+            .unwrap();
+        let mut channel = Channel::new(server).unwrap();
+        assert_eq!(channel.read_frame().unwrap_err(), "Frame too large");
+    }
+
+    #[test]
+    fn pairing_code_format() {
+        let code = generate_pairing_code();
+        assert_eq!(code.len(), CODE_LEN + 1);
+        assert_eq!(code.chars().nth(CODE_LEN / 2), Some('-'));
+        assert!(code
+            .chars()
+            .filter(|c| *c != '-')
+            .all(|c| CODE_ALPHABET.contains(&(c as u8))));
+    }
+
+    #[test]
+    fn pairing_codes_differ() {
+        assert_ne!(generate_pairing_code(), generate_pairing_code());
+    }
+
+    #[test]
+    fn normalize_ignores_case_separators_and_lookalikes() {
+        assert_eq!(normalize_pairing_code("7kq2m-x9d4a"), "7KQ2MX9D4A");
+        assert_eq!(normalize_pairing_code(" 7KQ2M X9D4A "), "7KQ2MX9D4A");
+        assert_eq!(normalize_pairing_code("O1-IL"), "0111");
+    }
+
+    #[test]
+    fn hex_roundtrip() {
+        let bytes = random_bytes::<32>();
+        assert_eq!(decode_hex(&encode_hex(&bytes)).unwrap(), bytes.to_vec());
+        assert!(decode_hex("abc").is_none());
+        assert!(decode_hex("zz").is_none());
+    }
+
+    #[test]
+    fn keys_depend_on_psk_and_transcript() {
+        let shared = [7u8; 32];
+        let base = derive_keys(&shared, b"code", b"transcript");
+        let other_code = derive_keys(&shared, b"c0de", b"transcript");
+        let other_transcript = derive_keys(&shared, b"code", b"tampered");
+
+        assert_ne!(base.client_to_server, base.server_to_client);
+        assert_ne!(base.client_to_server, other_code.client_to_server);
+        assert_ne!(base.pair_secret, other_transcript.pair_secret);
+    }
+
+    #[test]
+    fn peer_secret_bytes_rejects_bad_hex() {
+        let peer = PairedPeer {
+            secret: "1234".to_string(),
+            ..Default::default()
+        };
+        assert!(peer.secret_bytes().is_none());
+    }
+
+    #[test]
+    fn peer_secrets_are_not_serialized() {
+        let peer = PairedPeer {
+            device_id: "abc".to_string(),
+            secret: "00".repeat(32),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&peer).unwrap();
+        assert!(!json.contains(&peer.secret));
+        let legacy = format!(
+            r#"{{"device_id":"abc","name":"x","secret":"{}"}}"#,
+            peer.secret
+        );
+        let read: PairedPeer = serde_json::from_str(&legacy).unwrap();
+        assert_eq!(read.secret, peer.secret);
+    }
+}
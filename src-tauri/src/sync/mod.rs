@@ -0,0 +1,541 @@
+pub mod drive;
+pub mod folder;
+pub mod lan;
+pub mod webdav;
+
+use crate::notes::{app_data_file, Note};
+use crate::NotesState;
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::State;
+
+/// Name of the shared document every provider syncs (same as the Drive flow).
+pub const SYNC_FILE_NAME: &str = "markdown_notes_sync.json";
+
+/// How many times a conditional write is retried after losing a race.
+const MAX_SYNC_ATTEMPTS: usize = 3;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncProvider {
+    /// Synced by the backend once `connect_google_drive` has stored tokens,
+    /// otherwise by the frontend (`google-drive.ts`).
+    #[default]
+    GoogleDrive,
+    WebDav,
+    Folder,
+}
+
+/// What the last successful sync saw on the remote side.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct SyncCursor {
+    pub last_version: Option<String>,
+    pub last_hash: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SyncSettings {
+    #[serde(default)]
+    pub provider: SyncProvider,
+    /// Tokens from `connect_google_drive` are stored (see `tokens::GoogleSession`).
+    #[serde(default)]
+    pub google_drive_connected: bool,
+    #[serde(default)]
+    pub webdav: Option<webdav::WebDavConfig>,
+    #[serde(default)]
+    pub folder: Option<folder::FolderConfig>,
+    #[serde(default)]
+    pub cursor: SyncCursor,
+    /// Stable identifier of this installation, generated on first use.
+    #[serde(default)]
+    pub device_id: String,
+    /// Whether this device listens for paired peers on the local network.
+    #[serde(default)]
+    pub lan_enabled: bool,
+    #[serde(default)]
+    pub lan_peers: Vec<lan::PairedPeer>,
+}
+
+impl SyncSettings {
+    fn get_settings_path(app: &tauri::AppHandle) -> PathBuf {
+        app_data_file(app, "sync.json")
+    }
+
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        Self::load_from_path(&Self::get_settings_path(app))
+    }
+
+    pub fn load_from_path(path: &PathBuf) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, app: &tauri::AppHandle) -> Result<(), String> {
+        self.save_to_path(&Self::get_settings_path(app))
+    }
+
+    pub fn save_to_path(&self, path: &PathBuf) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, content).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, path).map_err(|e| e.to_string())
+    }
+
+    /// Loads, changes and saves `sync.json` as one step.
+    ///
+    /// Every change goes through here so commands running at the same time (a sync,
+    /// LAN pairing, switching providers) do not overwrite each other's fields.
+    pub fn update<T>(
+        app: &tauri::AppHandle,
+        change: impl FnOnce(&mut SyncSettings) -> Result<T, String>,
+    ) -> Result<T, String> {
+        static SETTINGS_LOCK: Mutex<()> = Mutex::new(());
+        let _guard = SETTINGS_LOCK.lock().map_err(|e| e.to_string())?;
+        let mut settings = Self::load(app);
+        let result = change(&mut settings)?;
+        settings.save(app)?;
+        Ok(result)
+    }
+
+    pub fn device_id(&mut self) -> &str {
+        if self.device_id.is_empty() {
+            self.device_id = uuid::Uuid::new_v4().to_string();
+        }
+        &self.device_id
+    }
+
+    /// Fails if `provider` needs settings that have not been configured yet.
+    pub fn check_provider(&self, provider: SyncProvider) -> Result<(), String> {
+        match provider {
+            SyncProvider::GoogleDrive => Ok(()),
+            SyncProvider::WebDav if self.webdav.is_none() => {
+                Err("WebDAV sync is not configured".to_string())
+            }
+            SyncProvider::Folder if self.folder.is_none() => {
+                Err("Folder sync is not configured".to_string())
+            }
+            SyncProvider::WebDav | SyncProvider::Folder => Ok(()),
+        }
+    }
+
+    /// Copy safe to hand to the frontend (credentials blanked).
+    pub fn redacted(&self) -> Self {
+        let mut settings = self.clone();
+        if let Some(webdav) = settings.webdav.as_mut() {
+            webdav.password.clear();
+        }
+        for peer in settings.lan_peers.iter_mut() {
+            peer.secret.clear();
+        }
+        settings
+    }
+}
+
+/// Remote copy of the note collection together with its revision tag.
+pub struct RemoteSnapshot {
+    pub notes: Vec<Note>,
+    pub version: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum StoreError {
+    /// The remote changed since `expected_version` was read.
+    Conflict,
+    Other(String),
+}
+
+/// A backend holding the whole collection as one document with conditional writes.
+pub trait RemoteDocument {
+    /// Current revision tag, or `None` if the document does not exist yet.
+    fn version(&self) -> Result<Option<String>, String>;
+    fn fetch(&self) -> Result<Option<RemoteSnapshot>, String>;
+    /// Writes `notes` only if the remote is still at `expected_version`
+    /// (`None` = must not exist yet) and returns the new revision tag.
+    fn store(&self, notes: &[Note], expected_version: Option<&str>) -> Result<String, StoreError>;
+}
+
+fn parse_timestamp(timestamp: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(timestamp).ok()
+}
+
+/// Orders `updated_at` values by the instant they name, honouring offsets.
+///
+/// Unparseable values sort before valid ones and among themselves as strings.
+pub fn compare_timestamps(a: &str, b: &str) -> Ordering {
+    (parse_timestamp(a), a).cmp(&(parse_timestamp(b), b))
+}
+
+fn is_newer(candidate: &str, current: &str) -> bool {
+    match (parse_timestamp(candidate), parse_timestamp(current)) {
+        (Some(candidate), Some(current)) => candidate > current,
+        _ => candidate > current,
+    }
+}
+
+/// Merges two note lists by `updated_at`, keeping tombstones (mirrors `SyncLogic.mergeNotes`).
+pub fn merge_notes(local: &[Note], remote: &[Note]) -> Vec<Note> {
+    let mut merged = local.to_vec();
+    for remote_note in remote {
+        match merged.iter_mut().find(|n| n.id == remote_note.id) {
+            Some(existing) => {
+                if is_newer(&remote_note.updated_at, &existing.updated_at) {
+                    *existing = remote_note.clone();
+                }
+            }
+            None => merged.push(remote_note.clone()),
+        }
+    }
+    merged
+}
+
+/// Order-independent fingerprint of a note collection.
+pub fn notes_hash(notes: &[Note]) -> String {
+    let mut sorted: Vec<&Note> = notes.iter().collect();
+    sorted.sort_by(|a, b| a.id.cmp(&b.id));
+    let bytes = serde_json::to_vec(&sorted).unwrap_or_default();
+    Sha256::digest(&bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Pulls, merges and conditionally pushes until the remote accepts the result.
+pub fn sync_document(
+    remote: &dyn RemoteDocument,
+    local: &[Note],
+    cursor: &mut SyncCursor,
+) -> Result<Vec<Note>, String> {
+    for attempt in 1..=MAX_SYNC_ATTEMPTS {
+        let mut version = remote.version()?;
+        let unchanged_remote = version.is_some() && version == cursor.last_version;
+
+        let merged = if unchanged_remote {
+            local.to_vec()
+        } else {
+            match remote.fetch()? {
+                Some(snapshot) => {
+                    let merged = merge_notes(local, &snapshot.notes);
+                    if notes_hash(&snapshot.notes) == notes_hash(&merged) {
+                        log::info!("Sync: remote already up to date");
+                        cursor.last_hash = Some(notes_hash(&merged));
+                        cursor.last_version = Some(snapshot.version);
+                        return Ok(merged);
+                    }
+                    version = Some(snapshot.version);
+                    merged
+                }
+                None => {
+                    version = None;
+                    local.to_vec()
+                }
+            }
+        };
+
+        let hash = notes_hash(&merged);
+        if unchanged_remote && cursor.last_hash.as_deref() == Some(hash.as_str()) {
+            log::debug!("Sync: nothing changed since last sync");
+            return Ok(merged);
+        }
+
+        match remote.store(&merged, version.as_deref()) {
+            Ok(new_version) => {
+                cursor.last_version = Some(new_version);
+                cursor.last_hash = Some(hash);
+                return Ok(merged);
+            }
+            Err(StoreError::Conflict) => {
+                log::warn!("Sync: remote changed during attempt {}, retrying", attempt);
+            }
+            Err(StoreError::Other(e)) => return Err(e),
+        }
+    }
+    Err("Sync failed: the remote kept changing. Please try again.".to_string())
+}
+
+/// Runs `sync` on a snapshot of the store without holding the lock during I/O.
+pub(crate) fn sync_store(
+    app: &tauri::AppHandle,
+    state: &NotesState,
+    sync: impl FnOnce(&[Note]) -> Result<Vec<Note>, String>,
+) -> Result<Vec<Note>, String> {
+    let local = state.0.lock().map_err(|e| e.to_string())?.notes.clone();
+    let merged = sync(&local)?;
+
+    // Fold in anything saved while we were talking to the remote.
+    let mut store = state.0.lock().map_err(|e| e.to_string())?;
+    store.notes = merge_notes(&store.notes, &merged);
+    store.save(app)?;
+    Ok(store.notes.clone())
+}
+
+#[tauri::command]
+pub fn get_sync_settings(app: tauri::AppHandle) -> SyncSettings {
+    log::debug!("Command: get_sync_settings called");
+    SyncSettings::load(&app).redacted()
+}
+
+#[tauri::command]
+pub fn set_sync_provider(app: tauri::AppHandle, provider: SyncProvider) -> Result<(), String> {
+    log::debug!("Command: set_sync_provider called: {:?}", provider);
+    SyncSettings::update(&app, |settings| {
+        settings.check_provider(provider)?;
+        if settings.provider != provider {
+            settings.provider = provider;
+            settings.cursor = SyncCursor::default();
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub async fn sync_now(
+    app: tauri::AppHandle,
+    state: State<'_, NotesState>,
+) -> Result<Vec<Note>, String> {
+    log::debug!("Command: sync_now called");
+    let mut settings = SyncSettings::load(&app);
+    let notes = match settings.provider {
+        SyncProvider::GoogleDrive => {
+            if !settings.google_drive_connected {
+                return Err("Google Drive is not connected".to_string());
+            }
+            let session = crate::tokens::GoogleSession::open(&app);
+            let document = drive::DriveDocument::new(drive::DriveClient::new(
+                session.endpoints().clone(),
+                &session.access_token()?,
+            ));
+            sync_store(&app, &state, |local| {
+                sync_document(&document, local, &mut settings.cursor)
+            })?
+        }
+        SyncProvider::WebDav => {
+            let config = settings
+                .webdav
+                .as_ref()
+                .ok_or("WebDAV sync is not configured")?;
+            let client = webdav::WebDavClient::new(&webdav::with_password(&app, config)?)?;
+            client.ensure_collection()?;
+            sync_store(&app, &state, |local| {
+                sync_document(&client, local, &mut settings.cursor)
+            })?
+        }
+        SyncProvider::Folder => {
+            let root = settings
+                .folder
+                .as_ref()
+                .map(|config| config.path.clone())
+                .ok_or("Folder sync is not configured")?;
+            let folder = folder::FolderSync::new(&root, settings.device_id())?;
+            sync_store(&app, &state, |local| folder.sync(local))?
+        }
+    };
+    // Other commands may have changed sync.json meanwhile; only record how far we got
+    SyncSettings::update(&app, |current| {
+        if current.provider == settings.provider {
+            current.cursor = settings.cursor.clone();
+        }
+        if current.device_id.is_empty() {
+            current.device_id = settings.device_id.clone();
+        }
+        Ok(())
+    })?;
+    log::info!("Sync: completed with {} notes", notes.len());
+    Ok(notes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::create_test_note_at as note;
+    use std::cell::RefCell;
+
+    /// In-memory document with the same conditional-write rules as a server.
+    #[derive(Default)]
+    struct MemoryDocument {
+        content: RefCell<Option<(Vec<Note>, u32)>>,
+        stores: RefCell<u32>,
+    }
+
+    impl RemoteDocument for MemoryDocument {
+        fn version(&self) -> Result<Option<String>, String> {
+            Ok(self.content.borrow().as_ref().map(|(_, v)| v.to_string()))
+        }
+
+        fn fetch(&self) -> Result<Option<RemoteSnapshot>, String> {
+            Ok(self
+                .content
+                .borrow()
+                .as_ref()
+                .map(|(notes, v)| RemoteSnapshot {
+                    notes: notes.clone(),
+                    version: v.to_string(),
+                }))
+        }
+
+        fn store(&self, notes: &[Note], expected: Option<&str>) -> Result<String, StoreError> {
+            let current = self.version().unwrap();
+            if current.as_deref() != expected {
+                return Err(StoreError::Conflict);
+            }
+            let next = current.map(|v| v.parse::<u32>().unwrap() + 1).unwrap_or(1);
+            *self.content.borrow_mut() = Some((notes.to_vec(), next));
+            *self.stores.borrow_mut() += 1;
+            Ok(next.to_string())
+        }
+    }
+
+    // ── merge_notes tests ──────────────────────────────────
+
+    #[test]
+    fn merge_keeps_newer_remote() {
+        let local = vec![note("1", "local", "2026-01-01T00:00:00Z")];
+        let remote = vec![note("1", "remote", "2026-01-02T00:00:00Z")];
+        let merged = merge_notes(&local, &remote);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].title, "remote");
+    }
+
+    #[test]
+    fn merge_keeps_newer_local() {
+        let local = vec![note("1", "local", "2026-01-03T00:00:00Z")];
+        let remote = vec![note("1", "remote", "2026-01-02T00:00:00Z")];
+        assert_eq!(merge_notes(&local, &remote)[0].title, "local");
+    }
+
+    #[test]
+    fn merge_compares_offsets_not_strings() {
+        let local = vec![note("1", "local", "2026-01-01T10:00:00+09:00")];
+        let remote = vec![note("1", "remote", "2026-01-01T02:00:00Z")];
+        assert_eq!(merge_notes(&local, &remote)[0].title, "remote");
+    }
+
+    #[test]
+    fn timestamps_compare_instants_and_fall_back_to_strings() {
+        assert_eq!(
+            compare_timestamps("2026-03-01T10:00:00+09:00", "2026-03-01T02:00:00Z"),
+            Ordering::Less
+        );
+        assert_eq!(compare_timestamps("b", "a"), Ordering::Greater);
+        assert_eq!(
+            compare_timestamps("garbage", "2020-01-01T00:00:00Z"),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn merge_appends_remote_only_notes() {
+        let local = vec![note("1", "a", "2026-01-01T00:00:00Z")];
+        let remote = vec![note("2", "b", "2026-01-01T00:00:00Z")];
+        let merged = merge_notes(&local, &remote);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[1].id, "2");
+    }
+
+    // ── sync_document tests ────────────────────────────────
+
+    #[test]
+    fn sync_creates_missing_document() {
+        let remote = MemoryDocument::default();
+        let mut cursor = SyncCursor::default();
+        let local = vec![note("1", "a", "2026-01-01T00:00:00Z")];
+
+        let merged = sync_document(&remote, &local, &mut cursor).unwrap();
+        assert_eq!(merged.len(), 1);
+        assert_eq!(cursor.last_version.as_deref(), Some("1"));
+        assert_eq!(*remote.stores.borrow(), 1);
+    }
+
+    #[test]
+    fn sync_skips_write_when_nothing_changed() {
+        let remote = MemoryDocument::default();
+        let mut cursor = SyncCursor::default();
+        let local = vec![note("1", "a", "2026-01-01T00:00:00Z")];
+
+        sync_document(&remote, &local, &mut cursor).unwrap();
+        sync_document(&remote, &local, &mut cursor).unwrap();
+        assert_eq!(*remote.stores.borrow(), 1);
+    }
+
+    #[test]
+    fn sync_pulls_without_writing_back_identical_content() {
+        let remote = MemoryDocument::default();
+        *remote.content.borrow_mut() = Some((vec![note("1", "a", "2026-01-01T00:00:00Z")], 7));
+        let mut cursor = SyncCursor::default();
+
+        let merged = sync_document(&remote, &[], &mut cursor).unwrap();
+        assert_eq!(merged.len(), 1);
+        assert_eq!(cursor.last_version.as_deref(), Some("7"));
+        assert_eq!(*remote.stores.borrow(), 0);
+    }
+
+    #[test]
+    fn sync_merges_remote_changes_before_writing() {
+        let remote = MemoryDocument::default();
+        *remote.content.borrow_mut() = Some((vec![note("2", "b", "2026-01-01T00:00:00Z")], 3));
+        let mut cursor = SyncCursor::default();
+        let local = vec![note("1", "a", "2026-01-01T00:00:00Z")];
+
+        let merged = sync_document(&remote, &local, &mut cursor).unwrap();
+        assert_eq!(merged.len(), 2);
+        assert_eq!(remote.content.borrow().as_ref().unwrap().0.len(), 2);
+        assert_eq!(cursor.last_version.as_deref(), Some("4"));
+    }
+
+    #[test]
+    fn settings_redacted_hides_password() {
+        let settings = SyncSettings {
+            webdav: Some(webdav::WebDavConfig {
+                url: "https://dav.example.com/".to_string(),
+                username: "me".to_string(),
+                password: "secret".to_string(),
+            }),
+            lan_peers: vec![lan::PairedPeer {
+                device_id: "peer".to_string(),
+                secret: "00ff".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let redacted = settings.redacted();
+        assert_eq!(redacted.webdav.unwrap().password, "");
+        assert_eq!(redacted.lan_peers[0].secret, "");
+        assert_eq!(settings.webdav.unwrap().password, "secret");
+    }
+
+    #[test]
+    fn saved_settings_leave_out_the_webdav_password() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sync.json");
+        let settings = SyncSettings {
+            webdav: Some(webdav::WebDavConfig {
+                url: "https://dav.example.com/".to_string(),
+                username: "me".to_string(),
+                password: "secret".to_string(),
+            }),
+            ..SyncSettings::default()
+        };
+        settings.save_to_path(&path).unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("secret"));
+        let loaded = SyncSettings::load_from_path(&path);
+        assert_eq!(loaded.webdav.unwrap().username, "me");
+    }
+
+    #[test]
+    fn providers_need_their_settings() {
+        let mut settings = SyncSettings::default();
+        assert!(settings.check_provider(SyncProvider::GoogleDrive).is_ok());
+        assert!(settings.check_provider(SyncProvider::WebDav).is_err());
+        assert!(settings.check_provider(SyncProvider::Folder).is_err());
+        settings.folder = Some(folder::FolderConfig {
+            path: PathBuf::from("/notes"),
+        });
+        assert!(settings.check_provider(SyncProvider::Folder).is_ok());
+    }
+}
@@ -0,0 +1,321 @@
+use super::{RemoteDocument, RemoteSnapshot, StoreError, SyncCursor, SyncProvider, SyncSettings};
+use crate::notes::Note;
+use crate::tokens::PrivateStore;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use url::Url;
+
+/// The password is kept in a `PrivateStore`, never in `sync.json`.
+const PASSWORD_FILE_NAME: &str = "webdav_password.bin";
+
+const PROPFIND_ETAG_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:getetag/></d:prop></d:propfind>"#;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct WebDavConfig {
+    /// Collection the sync file lives in, e.g. `https://dav.example.com/notes/`.
+    pub url: String,
+    #[serde(default)]
+    pub username: String,
+    /// Read from older `sync.json` files but never written back (see `with_password`).
+    #[serde(default, skip_serializing)]
+    pub password: String,
+}
+
+/// Returns `config` with the password from its store.
+///
+/// A password still read from an older `sync.json` is moved to the store first; the
+/// next save of the settings drops it from the file.
+pub fn with_password(
+    app: &tauri::AppHandle,
+    config: &WebDavConfig,
+) -> Result<WebDavConfig, String> {
+    let store = PrivateStore::open(app, PASSWORD_FILE_NAME);
+    let mut config = config.clone();
+    if config.password.is_empty() {
+        if let Some(password) = store.load() {
+            config.password = String::from_utf8(password).map_err(|e| e.to_string())?;
+        }
+    } else {
+        store.save(config.password.as_bytes())?;
+    }
+    Ok(config)
+}
+
+pub struct WebDavClient {
+    agent: ureq::Agent,
+    collection: Url,
+    authorization: Option<String>,
+}
+
+impl WebDavClient {
+    pub fn new(config: &WebDavConfig) -> Result<Self, String> {
+        let mut raw = config.url.trim().to_string();
+        if !raw.ends_with('/') {
+            raw.push('/');
+        }
+        let collection = Url::parse(&raw).map_err(|e| format!("Invalid WebDAV URL: {}", e))?;
+        if !matches!(collection.scheme(), "http" | "https") {
+            return Err("WebDAV URL must use http or https".to_string());
+        }
+
+        let authorization = (!config.username.is_empty()).then(|| {
+            let credentials = format!("{}:{}", config.username, config.password);
+            format!(
+                "Basic {}",
+                base64::engine::general_purpose::STANDARD.encode(credentials)
+            )
+        });
+
+        let agent = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .build();
+
+        Ok(Self {
+            agent,
+            collection,
+            authorization,
+        })
+    }
+
+    fn file_url(&self) -> Url {
+        self.collection
+            .join(super::SYNC_FILE_NAME)
+            .expect("sync file name is a valid relative URL")
+    }
+
+    fn request(&self, method: &str, url: &Url) -> ureq::Request {
+        let request = self.agent.request_url(method, url);
+        match &self.authorization {
+            Some(value) => request.set("Authorization", value),
+            None => request,
+        }
+    }
+
+    fn propfind(&self, url: &Url) -> Result<Option<String>, String> {
+        let result = self
+            .request("PROPFIND", url)
+            .set("Depth", "0")
+            .set("Content-Type", "application/xml; charset=utf-8")
+            .send_string(PROPFIND_ETAG_BODY);
+        match result {
+            Ok(response) => {
+                let body = response.into_string().map_err(|e| e.to_string())?;
+                Ok(Some(body))
+            }
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(e) => Err(describe_error("PROPFIND", e)),
+        }
+    }
+
+    /// Creates the sync collection if the server does not have it yet.
+    pub fn ensure_collection(&self) -> Result<(), String> {
+        if self.propfind(&self.collection)?.is_some() {
+            return Ok(());
+        }
+        log::info!("WebDAV: creating collection {}", self.collection);
+        match self.request("MKCOL", &self.collection).call() {
+            Ok(_) | Err(ureq::Error::Status(405, _)) => Ok(()),
+            Err(e) => Err(describe_error("MKCOL", e)),
+        }
+    }
+}
+
+impl RemoteDocument for WebDavClient {
+    fn version(&self) -> Result<Option<String>, String> {
+        match self.propfind(&self.file_url())? {
+            Some(body) => parse_etag(&body)
+                .map(Some)
+                .ok_or_else(|| "WebDAV server did not report an ETag".to_string()),
+            None => Ok(None),
+        }
+    }
+
+    fn fetch(&self) -> Result<Option<RemoteSnapshot>, String> {
+        let response = match self.request("GET", &self.file_url()).call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(404, _)) => return Ok(None),
+            Err(e) => return Err(describe_error("GET", e)),
+        };
+        let version = response
+            .header("ETag")
+            .map(str::to_string)
+            .ok_or("WebDAV server did not return an ETag")?;
+        let body = response.into_string().map_err(|e| e.to_string())?;
+        let notes = serde_json::from_str(&body)
+            .map_err(|e| format!("Remote sync file is not valid: {}", e))?;
+        Ok(Some(RemoteSnapshot { notes, version }))
+    }
+
+    fn store(&self, notes: &[Note], expected_version: Option<&str>) -> Result<String, StoreError> {
+        let body = serde_json::to_string(notes).map_err(|e| StoreError::Other(e.to_string()))?;
+        let request = self
+            .request("PUT", &self.file_url())
+            .set("Content-Type", "application/json");
+        let request = match expected_version {
+            Some(etag) => request.set("If-Match", etag),
+            None => request.set("If-None-Match", "*"),
+        };
+
+        match request.send_string(&body) {
+            Ok(response) => match response.header("ETag") {
+                Some(etag) => Ok(etag.to_string()),
+                // Some servers omit the ETag on PUT; ask for it instead.
+                None => self
+                    .version()
+                    .and_then(|v| v.ok_or_else(|| "Sync file vanished after upload".to_string()))
+                    .map_err(StoreError::Other),
+            },
+            Err(ureq::Error::Status(412, _)) => Err(StoreError::Conflict),
+            Err(e) => Err(StoreError::Other(describe_error("PUT", e))),
+        }
+    }
+}
+
+fn describe_error(method: &str, error: ureq::Error) -> String {
+    match error {
+        ureq::Error::Status(401, _) | ureq::Error::Status(403, _) => {
+            "WebDAV server rejected the credentials".to_string()
+        }
+        ureq::Error::Status(code, response) => {
+            format!(
+                "WebDAV {} failed: {} {}",
+                method,
+                code,
+                response.status_text()
+            )
+        }
+        ureq::Error::Transport(transport) => format!("WebDAV {} failed: {}", method, transport),
+    }
+}
+
+fn decode_entities(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Extracts the first non-empty `getetag` from a PROPFIND multistatus body.
+pub fn parse_etag(body: &str) -> Option<String> {
+    let mut rest = body;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let end = rest.find('>')?;
+        let tag = &rest[..end];
+        rest = &rest[end + 1..];
+
+        if tag.starts_with('/') || tag.ends_with('/') {
+            continue;
+        }
+        let name = tag.split_whitespace().next().unwrap_or_default();
+        let local_name = name.rsplit(':').next().unwrap_or(name);
+        if local_name.eq_ignore_ascii_case("getetag") {
+            let value = decode_entities(rest[..rest.find('<')?].trim());
+            if !value.is_empty() {
+                return Some(value);
+            }
+        }
+    }
+    None
+}
+
+#[tauri::command]
+pub async fn configure_webdav_sync(
+    app: tauri::AppHandle,
+    url: String,
+    username: String,
+    password: String,
+) -> Result<(), String> {
+    log::debug!("Command: configure_webdav_sync called for {}", url);
+    let config = WebDavConfig {
+        url,
+        username,
+        password,
+    };
+    WebDavClient::new(&config)?.ensure_collection()?;
+
+    let store = PrivateStore::open(&app, PASSWORD_FILE_NAME);
+    if config.password.is_empty() {
+        store.clear()?;
+    } else {
+        store.save(config.password.as_bytes())?;
+    }
+    SyncSettings::update(&app, |settings| {
+        settings.webdav = Some(config);
+        settings.provider = SyncProvider::WebDav;
+        settings.cursor = SyncCursor::default();
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_etag_from_multistatus() {
+        let body = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:">
+  <d:response>
+    <d:href>/notes/markdown_notes_sync.json</d:href>
+    <d:propstat>
+      <d:prop><d:getetag>"abc123"</d:getetag></d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+</d:multistatus>"#;
+        assert_eq!(parse_etag(body).as_deref(), Some("\"abc123\""));
+    }
+
+    #[test]
+    fn parse_etag_decodes_entities_and_other_prefixes() {
+        let body = r#"<D:multistatus xmlns:D="DAV:"><D:response><D:propstat><D:prop>
+<D:getetag>&quot;v2&quot;</D:getetag></D:prop></D:propstat></D:response></D:multistatus>"#;
+        assert_eq!(parse_etag(body).as_deref(), Some("\"v2\""));
+    }
+
+    #[test]
+    fn parse_etag_skips_empty_elements() {
+        let body = r#"<multistatus xmlns="DAV:"><response><propstat><prop><getetag/></prop>
+<status>HTTP/1.1 404 Not Found</status></propstat></response></multistatus>"#;
+        assert_eq!(parse_etag(body), None);
+    }
+
+    #[test]
+    fn client_normalizes_collection_url() {
+        let client = WebDavClient::new(&WebDavConfig {
+            url: "https://dav.example.com/notes".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            client.file_url().as_str(),
+            "https://dav.example.com/notes/markdown_notes_sync.json"
+        );
+        assert!(client.authorization.is_none());
+    }
+
+    #[test]
+    fn client_rejects_non_http_urls() {
+        let result = WebDavClient::new(&WebDavConfig {
+            url: "ftp://dav.example.com/".to_string(),
+            ..Default::default()
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn client_builds_basic_auth_header() {
+        let client = WebDavClient::new(&WebDavConfig {
+            url: "https://dav.example.com/".to_string(),
+            username: "user".to_string(),
+            password: "pass".to_string(),
+        })
+        .unwrap();
+        assert_eq!(client.authorization.as_deref(), Some("Basic dXNlcjpwYXNz"));
+    }
+}
@@ -0,0 +1,423 @@
+use crate::diagnostics::{self, Level};
+use crate::notes::app_data_file;
+use crate::sync::drive::{refresh_tokens, revoke_token, DriveConfig, DriveEndpoints, DriveTokens};
+use crate::sync::{SyncCursor, SyncSettings};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const TOKENS_FILE_NAME: &str = "google_tokens.bin";
+/// Fallback for the sealing key when no OS credential store is available.
+const KEY_FILE_NAME: &str = "token.key";
+/// Account the sealing key is kept under in the OS credential store.
+const KEYCHAIN_USER: &str = "private-store-key";
+const NONCE_LEN: usize = 12;
+/// Access tokens are refreshed this long before Google says they expire.
+const REFRESH_MARGIN_SECS: i64 = 60;
+
+/// Small encrypted, owner-only blob in the app data dir.
+///
+/// The key lives in the OS credential store (Keychain, Credential Manager, Secret
+/// Service). Only when none is available does it fall back to an owner-only key file,
+/// which sits next to the data and so protects it no better than file permissions.
+pub struct PrivateStore {
+    path: PathBuf,
+    key_path: PathBuf,
+    keychain: Option<keyring::Entry>,
+}
+
+impl PrivateStore {
+    /// Store sealed with the key in `key_path`.
+    pub fn new(path: PathBuf, key_path: PathBuf) -> Self {
+        Self {
+            path,
+            key_path,
+            keychain: None,
+        }
+    }
+
+    /// Keeps the key in `entry` instead; a key file found at `key_path` is moved there.
+    pub fn with_keychain(self, entry: keyring::Entry) -> Self {
+        Self {
+            keychain: Some(entry),
+            ..self
+        }
+    }
+
+    /// Store for `file_name` in the app data dir, sharing the app's key.
+    pub fn open(app: &tauri::AppHandle, file_name: &str) -> Self {
+        let store = Self::new(
+            app_data_file(app, file_name),
+            app_data_file(app, KEY_FILE_NAME),
+        );
+        match keyring::Entry::new(&app.config().identifier, KEYCHAIN_USER) {
+            Ok(entry) => store.with_keychain(entry),
+            Err(e) => {
+                log::warn!(
+                    "PrivateStore: no OS credential store ({}), using a key file",
+                    e
+                );
+                store
+            }
+        }
+    }
+
+    fn write_private(path: &Path, bytes: &[u8]) -> Result<(), String> {
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, bytes).map_err(|e| e.to_string())?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&tmp_path, fs::Permissions::from_mode(0o600))
+                .map_err(|e| e.to_string())?;
+        }
+        fs::rename(&tmp_path, path).map_err(|e| e.to_string())
+    }
+
+    fn new_key() -> Result<Vec<u8>, String> {
+        let mut key = vec![0u8; 32];
+        getrandom::getrandom(&mut key).map_err(|e| e.to_string())?;
+        Ok(key)
+    }
+
+    fn file_key(&self, create: bool) -> Result<Option<Vec<u8>>, String> {
+        match fs::read(&self.key_path) {
+            Ok(key) => Ok(Some(key)),
+            Err(_) if create => {
+                let key = Self::new_key()?;
+                Self::write_private(&self.key_path, &key)?;
+                Ok(Some(key))
+            }
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Key from the credential store; an error means the store cannot be used.
+    fn keychain_key(
+        &self,
+        entry: &keyring::Entry,
+        create: bool,
+    ) -> Result<Option<Vec<u8>>, keyring::Error> {
+        match entry.get_secret() {
+            Ok(key) => return Ok(Some(key)),
+            Err(keyring::Error::NoEntry) => {}
+            Err(e) => return Err(e),
+        }
+        // Data sealed before the credential store was used has its key in the file
+        let key = match fs::read(&self.key_path) {
+            Ok(key) if key.len() == 32 => Some(key),
+            _ if create => Self::new_key().ok(),
+            _ => None,
+        };
+        if let Some(key) = &key {
+            entry.set_secret(key)?;
+            if fs::remove_file(&self.key_path).is_ok() {
+                log::info!("PrivateStore: moved the key file to the OS credential store");
+            }
+        }
+        Ok(key)
+    }
+
+    fn cipher(&self, create: bool) -> Result<Option<ChaCha20Poly1305>, String> {
+        let key = match &self.keychain {
+            Some(entry) => match self.keychain_key(entry, create) {
+                Ok(key) => key,
+                Err(e) => {
+                    log::warn!(
+                        "PrivateStore: OS credential store unavailable ({}), using {:?}",
+                        e,
+                        self.key_path
+                    );
+                    self.file_key(create)?
+                }
+            },
+            None => self.file_key(create)?,
+        };
+        match key {
+            Some(key) if key.len() == 32 => Ok(Some(ChaCha20Poly1305::new(Key::from_slice(&key)))),
+            Some(_) => Err("Token key is corrupt".to_string()),
+            None => Ok(None),
+        }
+    }
+
+    pub fn save(&self, plaintext: &[u8]) -> Result<(), String> {
+        let cipher = self.cipher(true)?.ok_or("Token key unavailable")?;
+        let mut nonce = [0u8; NONCE_LEN];
+        getrandom::getrandom(&mut nonce).map_err(|e| e.to_string())?;
+        let sealed = cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|_| "Encryption failed".to_string())?;
+        Self::write_private(&self.path, &[&nonce[..], &sealed].concat())
+    }
+
+    /// Returns `None` when nothing is stored or the blob cannot be decrypted.
+    pub fn load(&self) -> Option<Vec<u8>> {
+        let data = fs::read(&self.path).ok()?;
+        let cipher = match self.cipher(false) {
+            Ok(Some(cipher)) => cipher,
+            Ok(None) => return None,
+            Err(e) => {
+                log::warn!("PrivateStore: {}", e);
+                return None;
+            }
+        };
+        if data.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, sealed) = data.split_at(NONCE_LEN);
+        cipher
+            .decrypt(Nonce::from_slice(nonce), sealed)
+            .map_err(|_| log::warn!("PrivateStore: cannot decrypt {:?}", self.path))
+            .ok()
+    }
+
+    pub fn clear(&self) -> Result<(), String> {
+        match fs::remove_file(&self.path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+/// Everything needed to call Google on the user's behalf.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GoogleCredentials {
+    pub config: DriveConfig,
+    pub tokens: DriveTokens,
+}
+
+/// Persistent Google sign-in with transparent access-token refresh.
+pub struct GoogleSession {
+    store: PrivateStore,
+    endpoints: DriveEndpoints,
+}
+
+impl GoogleSession {
+    pub fn new(store: PrivateStore, endpoints: DriveEndpoints) -> Self {
+        Self { store, endpoints }
+    }
+
+    pub fn open(app: &tauri::AppHandle) -> Self {
+        Self::new(
+            PrivateStore::open(app, TOKENS_FILE_NAME),
+            DriveEndpoints::default(),
+        )
+    }
+
+    pub fn endpoints(&self) -> &DriveEndpoints {
+        &self.endpoints
+    }
+
+    pub fn load(&self) -> Option<GoogleCredentials> {
+        serde_json::from_slice(&self.store.load()?).ok()
+    }
+
+    pub fn save(&self, credentials: &GoogleCredentials) -> Result<(), String> {
+        let bytes = serde_json::to_vec(credentials).map_err(|e| e.to_string())?;
+        self.store.save(&bytes)
+    }
+
+    /// Returns a valid access token, refreshing it first if it is about to expire.
+    pub fn access_token(&self) -> Result<String, String> {
+        let mut credentials = self.load().ok_or("Not signed in to Google")?;
+        let now = chrono::Utc::now().timestamp();
+        let expiring = credentials
+            .tokens
+            .expires_at
+            .is_some_and(|expires_at| expires_at - REFRESH_MARGIN_SECS <= now);
+        if !expiring {
+            return Ok(credentials.tokens.access_token);
+        }
+
+        let refresh_token = credentials
+            .tokens
+            .refresh_token
+            .clone()
+            .ok_or("Google Drive session expired. Please sign in again.")?;
+        log::info!("Google: refreshing access token");
+        credentials.tokens = refresh_tokens(&self.endpoints, &credentials.config, &refresh_token)
+            .inspect_err(|e| {
+            diagnostics::record(
+                Level::Error,
+                "auth",
+                "Token refresh failed",
+                &[("error", e)],
+            )
+        })?;
+        self.save(&credentials)?;
+        Ok(credentials.tokens.access_token)
+    }
+
+    /// Revokes the grant at Google and forgets the stored tokens.
+    ///
+    /// Local tokens are removed even if Google cannot be reached.
+    pub fn sign_out(&self) -> Result<(), String> {
+        let revoked = match self.load() {
+            Some(credentials) => {
+                let token = credentials
+                    .tokens
+                    .refresh_token
+                    .unwrap_or(credentials.tokens.access_token);
+                revoke_token(&self.endpoints, &token)
+            }
+            None => Ok(()),
+        };
+        self.store.clear()?;
+        if let Err(e) = &revoked {
+            log::warn!("Google: {}", e);
+        }
+        Ok(())
+    }
+}
+
+#[tauri::command]
+pub async fn get_access_token(app: tauri::AppHandle) -> Result<String, String> {
+    log::debug!("Command: get_access_token called");
+    GoogleSession::open(&app).access_token()
+}
+
+#[tauri::command]
+pub async fn sign_out(app: tauri::AppHandle) -> Result<(), String> {
+    log::debug!("Command: sign_out called");
+    GoogleSession::open(&app).sign_out()?;
+
+    SyncSettings::update(&app, |settings| {
+        if settings.google_drive_connected {
+            settings.google_drive_connected = false;
+            settings.cursor = SyncCursor::default();
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn store(dir: &Path) -> PrivateStore {
+        PrivateStore::new(dir.join(TOKENS_FILE_NAME), dir.join(KEY_FILE_NAME))
+    }
+
+    #[test]
+    fn private_store_roundtrip() {
+        let dir = tempdir().unwrap();
+        let store = store(dir.path());
+        assert!(store.load().is_none());
+
+        store.save(b"refresh-token-123").unwrap();
+        assert_eq!(store.load().unwrap(), b"refresh-token-123");
+
+        let raw = fs::read(dir.path().join(TOKENS_FILE_NAME)).unwrap();
+        assert!(!raw
+            .windows(b"refresh-token-123".len())
+            .any(|w| w == b"refresh-token-123"));
+    }
+
+    #[test]
+    fn private_store_rejects_tampering() {
+        let dir = tempdir().unwrap();
+        let store = store(dir.path());
+        store.save(b"secret").unwrap();
+
+        let path = dir.path().join(TOKENS_FILE_NAME);
+        let mut raw = fs::read(&path).unwrap();
+        let last = raw.len() - 1;
+        raw[last] ^= 1;
+        fs::write(&path, raw).unwrap();
+        assert!(store.load().is_none());
+    }
+
+    #[test]
+    fn private_store_without_key_loads_nothing() {
+        let dir = tempdir().unwrap();
+        let store = store(dir.path());
+        store.save(b"secret").unwrap();
+        fs::remove_file(dir.path().join(KEY_FILE_NAME)).unwrap();
+        assert!(store.load().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_store_files_are_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempdir().unwrap();
+        store(dir.path()).save(b"secret").unwrap();
+        for name in [TOKENS_FILE_NAME, KEY_FILE_NAME] {
+            let mode = fs::metadata(dir.path().join(name))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600, "{}", name);
+        }
+    }
+
+    #[test]
+    fn private_store_moves_the_key_file_into_the_keychain() {
+        keyring::set_default_credential_builder(keyring::mock::default_credential_builder());
+        let dir = tempdir().unwrap();
+        store(dir.path()).save(b"secret").unwrap();
+
+        let entry = keyring::Entry::new("markdown-notes-test", KEYCHAIN_USER).unwrap();
+        let store = store(dir.path()).with_keychain(entry);
+        assert_eq!(store.load().unwrap(), b"secret");
+        assert!(!dir.path().join(KEY_FILE_NAME).exists());
+
+        store.save(b"rotated").unwrap();
+        assert_eq!(store.load().unwrap(), b"rotated");
+        assert!(!dir.path().join(KEY_FILE_NAME).exists());
+    }
+
+    #[test]
+    fn clear_is_idempotent() {
+        let dir = tempdir().unwrap();
+        let store = store(dir.path());
+        store.clear().unwrap();
+        store.save(b"secret").unwrap();
+        store.clear().unwrap();
+        assert!(store.load().is_none());
+    }
+
+    #[test]
+    fn fresh_token_is_returned_without_refresh() {
+        let dir = tempdir().unwrap();
+        // Unroutable endpoints: any network call would fail the test
+        let endpoints = DriveEndpoints {
+            token_url: "http://127.0.0.1:9/token".to_string(),
+            revoke_url: "http://127.0.0.1:9/revoke".to_string(),
+            ..Default::default()
+        };
+        let session = GoogleSession::new(store(dir.path()), endpoints);
+        session
+            .save(&GoogleCredentials {
+                config: DriveConfig::default(),
+                tokens: DriveTokens {
+                    access_token: "fresh".to_string(),
+                    refresh_token: Some("r".to_string()),
+                    expires_at: Some(chrono::Utc::now().timestamp() + 3600),
+                },
+            })
+            .unwrap();
+        assert_eq!(session.access_token().unwrap(), "fresh");
+    }
+
+    #[test]
+    fn expired_token_without_refresh_token_requires_sign_in() {
+        let dir = tempdir().unwrap();
+        let session = GoogleSession::new(store(dir.path()), DriveEndpoints::default());
+        session
+            .save(&GoogleCredentials {
+                config: DriveConfig::default(),
+                tokens: DriveTokens {
+                    access_token: "stale".to_string(),
+                    refresh_token: None,
+                    expires_at: Some(0),
+                },
+            })
+            .unwrap();
+        assert!(session.access_token().unwrap_err().contains("sign in"));
+    }
+}
@@ -0,0 +1,303 @@
+use crate::notes::Note;
+use crate::sync::compare_timestamps;
+use crate::NotesState;
+use std::sync::Mutex;
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::TrayIconBuilder;
+use tauri::{Manager, Wry};
+
+const TRAY_ID: &str = "main";
+/// Recently updated notes listed in the tray menu.
+pub const RECENT_NOTES_COUNT: usize = 5;
+/// Longer titles are shortened in the menu.
+const MAX_TITLE_CHARS: usize = 40;
+const NOTE_ID_PREFIX: &str = "note:";
+
+#[derive(Debug, PartialEq)]
+pub enum TrayAction {
+    NewNote,
+    OpenNote(String),
+    ShowAll,
+    HideAll,
+    ReleaseClickThrough,
+    Quit,
+}
+
+impl TrayAction {
+    pub fn id(&self) -> String {
+        match self {
+            TrayAction::NewNote => "new-note".to_string(),
+            TrayAction::OpenNote(id) => format!("{}{}", NOTE_ID_PREFIX, id),
+            TrayAction::ShowAll => "show-all".to_string(),
+            TrayAction::HideAll => "hide-all".to_string(),
+            TrayAction::ReleaseClickThrough => "release-click-through".to_string(),
+            TrayAction::Quit => "quit".to_string(),
+        }
+    }
+
+    pub fn parse(id: &str) -> Option<Self> {
+        match id {
+            "new-note" => Some(TrayAction::NewNote),
+            "show-all" => Some(TrayAction::ShowAll),
+            "hide-all" => Some(TrayAction::HideAll),
+            "release-click-through" => Some(TrayAction::ReleaseClickThrough),
+            "quit" => Some(TrayAction::Quit),
+            _ => id
+                .strip_prefix(NOTE_ID_PREFIX)
+                .map(|note_id| TrayAction::OpenNote(note_id.to_string())),
+        }
+    }
+}
+
+/// Id and menu label of the most recently updated notes, newest first.
+pub fn recent_notes(notes: &[Note], count: usize) -> Vec<(String, String)> {
+    let mut recent: Vec<&Note> = notes.iter().filter(|n| !n.deleted).collect();
+    recent.sort_by(|a, b| compare_timestamps(&b.updated_at, &a.updated_at));
+    recent
+        .into_iter()
+        .take(count)
+        .map(|note| {
+            let title = note.title.trim();
+            let label = if title.chars().count() > MAX_TITLE_CHARS {
+                let short: String = title.chars().take(MAX_TITLE_CHARS - 1).collect();
+                format!("{}…", short)
+            } else if title.is_empty() {
+                "(無題)".to_string()
+            } else {
+                title.to_string()
+            };
+            (note.id.clone(), label)
+        })
+        .collect()
+}
+
+/// Recent entries currently shown, to skip rebuilding an unchanged menu.
+#[derive(Default)]
+pub struct TrayState(Mutex<Vec<(String, String)>>);
+
+fn build_menu(app: &tauri::AppHandle, recent: &[(String, String)]) -> tauri::Result<Menu<Wry>> {
+    let menu = Menu::new(app)?;
+    menu.append(&MenuItem::with_id(
+        app,
+        TrayAction::NewNote.id(),
+        "新規ノート",
+        true,
+        None::<&str>,
+    )?)?;
+    if !recent.is_empty() {
+        menu.append(&PredefinedMenuItem::separator(app)?)?;
+        for (id, label) in recent {
+            menu.append(&MenuItem::with_id(
+                app,
+                TrayAction::OpenNote(id.clone()).id(),
+                label,
+                true,
+                None::<&str>,
+            )?)?;
+        }
+    }
+    menu.append(&PredefinedMenuItem::separator(app)?)?;
+    menu.append(&MenuItem::with_id(
+        app,
+        TrayAction::ShowAll.id(),
+        "すべてのノートを表示",
+        true,
+        None::<&str>,
+    )?)?;
+    menu.append(&MenuItem::with_id(
+        app,
+        TrayAction::HideAll.id(),
+        "すべてのノートを隠す",
+        true,
+        None::<&str>,
+    )?)?;
+    menu.append(&MenuItem::with_id(
+        app,
+        TrayAction::ReleaseClickThrough.id(),
+        "クリック透過を解除",
+        true,
+        None::<&str>,
+    )?)?;
+    menu.append(&PredefinedMenuItem::separator(app)?)?;
+    menu.append(&MenuItem::with_id(
+        app,
+        TrayAction::Quit.id(),
+        "終了",
+        true,
+        None::<&str>,
+    )?)?;
+    Ok(menu)
+}
+
+fn handle_action(app: &tauri::AppHandle, action: TrayAction) {
+    log::debug!("Tray: {:?}", action);
+    match action {
+        TrayAction::NewNote => {
+            crate::open_window_later(app, |app| {
+                if let Err(e) = crate::create_and_open_note(app) {
+                    log::error!("Tray: cannot create note: {}", e);
+                }
+            });
+        }
+        TrayAction::OpenNote(note_id) => {
+            crate::open_window_later(app, move |app| {
+                if let Err(e) = crate::show_note_window(app, &note_id) {
+                    log::error!("Tray: cannot open note {}: {}", note_id, e);
+                }
+            });
+        }
+        TrayAction::ShowAll => {
+            for window in app.webview_windows().values() {
+                window.unminimize().ok();
+                window.show().ok();
+            }
+            if let Some(main) = app.get_webview_window("main") {
+                main.set_focus().ok();
+            }
+        }
+        TrayAction::HideAll => {
+            for (label, window) in app.webview_windows() {
+                if label != "main" {
+                    window.hide().ok();
+                }
+            }
+        }
+        TrayAction::ReleaseClickThrough => {
+            if let Err(e) = crate::release_click_through(app) {
+                log::error!("Tray: cannot release click-through: {}", e);
+            }
+        }
+        TrayAction::Quit => app.exit(0),
+    }
+}
+
+/// Registers the tray icon; its recent notes come from `NotesState`.
+pub fn init(app: &tauri::AppHandle) -> tauri::Result<()> {
+    let recent = app
+        .state::<NotesState>()
+        .0
+        .lock()
+        .map(|store| recent_notes(&store.notes, RECENT_NOTES_COUNT))
+        .unwrap_or_default();
+    let menu = build_menu(app, &recent)?;
+    app.manage(TrayState(Mutex::new(recent)));
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("Markdown Notes")
+        .menu(&menu)
+        .show_menu_on_left_click(true)
+        .on_menu_event(|app, event| {
+            if let Some(action) = TrayAction::parse(event.id().as_ref()) {
+                handle_action(app, action);
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+    Ok(())
+}
+
+/// Updates the recent notes in the tray menu after the notes changed.
+pub fn refresh(app: &tauri::AppHandle, notes: &[Note]) {
+    let Some(state) = app.try_state::<TrayState>() else {
+        return;
+    };
+    let recent = recent_notes(notes, RECENT_NOTES_COUNT);
+    {
+        let Ok(mut shown) = state.0.lock() else {
+            return;
+        };
+        if *shown == recent {
+            return;
+        }
+        *shown = recent.clone();
+    }
+
+    // Callers may hold the notes lock; building menus must not block on the main thread
+    let handle = app.clone();
+    let dispatched = app.run_on_main_thread(move || {
+        let updated =
+            build_menu(&handle, &recent).and_then(|menu| match handle.tray_by_id(TRAY_ID) {
+                Some(tray) => tray.set_menu(Some(menu)),
+                None => Ok(()),
+            });
+        if let Err(e) = updated {
+            log::warn!("Tray: cannot update menu: {}", e);
+        }
+    });
+    if let Err(e) = dispatched {
+        log::warn!("Tray: {}", e);
+    }
+}
+
+/// Hides the main window instead of closing it while the tray icon exists.
+pub fn on_window_event(window: &tauri::Window, event: &tauri::WindowEvent) {
+    if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+        if window.label() == "main" && window.app_handle().tray_by_id(TRAY_ID).is_some() {
+            api.prevent_close();
+            window.hide().ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::create_test_note_at as note;
+
+    #[test]
+    fn actions_roundtrip_through_menu_ids() {
+        for action in [
+            TrayAction::NewNote,
+            TrayAction::OpenNote("abc-123".to_string()),
+            TrayAction::ShowAll,
+            TrayAction::HideAll,
+            TrayAction::ReleaseClickThrough,
+            TrayAction::Quit,
+        ] {
+            assert_eq!(TrayAction::parse(&action.id()), Some(action));
+        }
+        assert_eq!(TrayAction::parse("unknown"), None);
+    }
+
+    #[test]
+    fn recent_notes_are_newest_first_without_deleted() {
+        let mut deleted = note("d", "Deleted", "2026-12-01");
+        deleted.deleted = true;
+        let notes = [
+            note("a", "Old", "2026-01-01"),
+            deleted,
+            note("b", "New", "2026-06-01"),
+            note("c", "Middle", "2026-03-01"),
+        ];
+        let recent = recent_notes(&notes, 2);
+        assert_eq!(
+            recent,
+            [
+                ("b".to_string(), "New".to_string()),
+                ("c".to_string(), "Middle".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn recent_notes_compare_instants_across_offsets() {
+        let notes = [
+            note("tokyo", "Tokyo", "2026-03-01T10:00:00+09:00"),
+            note("utc", "UTC", "2026-03-01T02:00:00Z"),
+        ];
+        let recent = recent_notes(&notes, 1);
+        assert_eq!(recent[0].0, "utc");
+    }
+
+    #[test]
+    fn long_and_empty_titles_get_readable_labels() {
+        let long = "x".repeat(100);
+        let notes = [note("a", &long, "2"), note("b", "  ", "1")];
+        let recent = recent_notes(&notes, 5);
+        assert_eq!(recent[0].1.chars().count(), MAX_TITLE_CHARS);
+        assert!(recent[0].1.ends_with('…'));
+        assert_eq!(recent[1].1, "(無題)");
+    }
+}
@@ -0,0 +1,139 @@
+use app_lib::cli::{execute, parse};
+use app_lib::notes::{with_store_lock, NotesStore};
+use std::path::Path;
+
+use tempfile::tempdir;
+
+fn run(store: &Path, args: &[&str], stdin: &str) -> Result<String, String> {
+    let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+    let invocation = parse(&args)?;
+    let mut out = Vec::new();
+    execute(store, invocation.command, &mut stdin.as_bytes(), &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn test_new_edit_show_delete() {
+    let dir = tempdir().unwrap();
+    let store = dir.path().join("notes.json");
+
+    let id = run(&store, &["new", "--title", "Plan", "--content", "- a"], "").unwrap();
+    let id = id.trim();
+
+    run(&store, &["edit", id, "--append", "-"], "- b\n").unwrap();
+    assert_eq!(run(&store, &["show", id], "").unwrap(), "- a\n- b\n");
+
+    let listed = run(&store, &["list"], "").unwrap();
+    assert!(listed.starts_with(id));
+    assert!(listed.trim_end().ends_with("\tPlan"));
+
+    run(&store, &["delete", id], "").unwrap();
+    assert_eq!(run(&store, &["list"], "").unwrap(), "");
+    assert!(run(&store, &["list", "--all"], "").unwrap().contains(id));
+    assert!(run(&store, &["show", id], "").is_err());
+}
+
+#[test]
+fn test_search_is_case_insensitive() {
+    let dir = tempdir().unwrap();
+    let store = dir.path().join("notes.json");
+    run(
+        &store,
+        &["new", "--title", "Groceries", "--content", "Milk"],
+        "",
+    )
+    .unwrap();
+    run(&store, &["new", "--title", "Work"], "").unwrap();
+
+    let found = run(&store, &["search", "milk"], "").unwrap();
+    assert_eq!(found.lines().count(), 1);
+    assert!(found.contains("Groceries"));
+}
+
+#[test]
+fn test_export_and_import_roundtrip() {
+    let dir = tempdir().unwrap();
+    let store = dir.path().join("notes.json");
+    run(
+        &store,
+        &["new", "--title", "One", "--content", "# One\n"],
+        "",
+    )
+    .unwrap();
+    run(&store, &["new", "--title", "Two"], "").unwrap();
+
+    let json = dir.path().join("export.json");
+    run(&store, &["export", json.to_str().unwrap()], "").unwrap();
+    let markdown = dir.path().join("md");
+    run(&store, &["export", markdown.to_str().unwrap()], "").unwrap();
+    assert_eq!(std::fs::read_dir(&markdown).unwrap().count(), 2);
+
+    let other = dir.path().join("other.json");
+    run(&other, &["import", json.to_str().unwrap()], "").unwrap();
+    assert_eq!(NotesStore::load_from_path(&other).notes.len(), 2);
+
+    let file = std::fs::read_dir(&markdown)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| std::fs::read_to_string(path).unwrap().starts_with("# One"))
+        .unwrap();
+    let fresh = dir.path().join("fresh.json");
+    run(&fresh, &["import", file.to_str().unwrap()], "").unwrap();
+    let imported = NotesStore::load_from_path(&fresh);
+    assert_eq!(imported.notes[0].title, "One");
+}
+
+#[test]
+fn test_app_store_keeps_cli_changes() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("notes.json");
+    let first = run(&path, &["new", "--title", "First"], "").unwrap();
+
+    // The app loaded the store before the CLI added a note
+    let mut app_store = NotesStore::load_from_path(&path);
+    let second = run(&path, &["new", "--title", "Second"], "").unwrap();
+    app_store.delete_note(first.trim());
+
+    with_store_lock(&path, || {
+        app_store.reconcile(NotesStore::load_from_path(&path).notes);
+        app_store.save_to_path(&path)
+    })
+    .unwrap();
+
+    let saved = NotesStore::load_from_path(&path);
+    assert!(saved.get_note(second.trim()).is_some());
+    assert!(saved.get_note(first.trim()).is_none());
+}
+
+/// Standard input that checks the store is not locked while it is read.
+struct UnlockedStdin<'a> {
+    store: &'a Path,
+    text: &'a [u8],
+}
+
+impl std::io::Read for UnlockedStdin<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        with_store_lock(self.store, || Ok(())).expect("stdin read while the store was locked");
+        self.text.read(buf)
+    }
+}
+
+#[test]
+fn test_stdin_is_read_before_locking_the_store() {
+    let dir = tempdir().unwrap();
+    let store = dir.path().join("notes.json");
+    let args: Vec<String> = ["new", "--content", "-"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let mut stdin = UnlockedStdin {
+        store: &store,
+        text: b"from a pipe",
+    };
+    let mut out = Vec::new();
+    execute(&store, parse(&args).unwrap().command, &mut stdin, &mut out).unwrap();
+    assert_eq!(
+        NotesStore::load_from_path(&store).notes[0].content,
+        "from a pipe"
+    );
+}
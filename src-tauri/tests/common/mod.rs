@@ -0,0 +1,30 @@
+//! Helpers shared by the integration tests; each test crate uses only some of them.
+#![allow(dead_code)]
+
+use app_lib::notes::Note;
+
+pub fn note(title: &str, updated_at: &str) -> Note {
+    Note {
+        title: title.to_string(),
+        updated_at: updated_at.to_string(),
+        ..Note::new()
+    }
+}
+
+pub fn header<'a>(request: &'a tiny_http::Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str())
+}
+
+/// Answers a mock-server request with `body` and extra `headers`.
+pub fn respond(request: tiny_http::Request, status: u16, body: String, headers: &[(&str, &str)]) {
+    let mut response = tiny_http::Response::from_string(body).with_status_code(status);
+    for (name, value) in headers {
+        response
+            .add_header(tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap());
+    }
+    request.respond(response).ok();
+}
@@ -0,0 +1,257 @@
+mod common;
+
+use app_lib::sync::drive::{
+    exchange_code, DriveClient, DriveConfig, DriveDocument, DriveEndpoints,
+};
+use app_lib::sync::{sync_document, RemoteDocument, StoreError, SyncCursor};
+use common::{header, note, respond};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+const ACCESS_TOKEN: &str = "good-token";
+
+struct DriveFile {
+    name: String,
+    content: String,
+    version: u64,
+}
+
+#[derive(Default)]
+struct DriveState {
+    files: HashMap<String, DriveFile>,
+    next_id: u32,
+}
+
+const JSON: &[(&str, &str)] = &[("Content-Type", "application/json")];
+
+fn metadata(id: &str, file: &DriveFile) -> String {
+    format!(r#"{{"id":"{}","version":"{}"}}"#, id, file.version)
+}
+
+/// Splits a `multipart/related` body into its metadata and media parts.
+fn parse_multipart(content_type: &str, body: &str) -> (serde_json::Value, String) {
+    let boundary = content_type.split("boundary=").nth(1).unwrap();
+    let parts: Vec<&str> = body
+        .split(&format!("--{}", boundary))
+        .filter(|p| !p.trim().is_empty() && p.trim() != "--")
+        .map(|p| p.split_once("\r\n\r\n").unwrap().1.trim_end_matches("\r\n"))
+        .collect();
+    (
+        serde_json::from_str(parts[0]).unwrap(),
+        parts[1].to_string(),
+    )
+}
+
+/// Minimal Drive v3 + OAuth token endpoint stand-in.
+fn start_drive_server() -> (DriveEndpoints, Arc<Mutex<DriveState>>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let base = format!(
+        "http://127.0.0.1:{}",
+        server.server_addr().to_ip().unwrap().port()
+    );
+    let state = Arc::new(Mutex::new(DriveState::default()));
+
+    let shared = Arc::clone(&state);
+    std::thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let url = url::Url::parse(&format!("http://mock{}", request.url())).unwrap();
+            let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).ok();
+            let mut drive = shared.lock().unwrap();
+            let method = request.method().as_str().to_string();
+
+            if url.path() == "/token" {
+                let form: HashMap<String, String> = url::form_urlencoded::parse(body.as_bytes())
+                    .into_owned()
+                    .collect();
+                if form.get("code").map(String::as_str) == Some("good-code")
+                    && form.get("code_verifier").map(String::as_str) == Some("verifier")
+                    && form.get("grant_type").map(String::as_str) == Some("authorization_code")
+                {
+                    let tokens = format!(
+                        r#"{{"access_token":"{}","refresh_token":"refresh","expires_in":3599,"token_type":"Bearer"}}"#,
+                        ACCESS_TOKEN
+                    );
+                    respond(request, 200, tokens, JSON);
+                } else {
+                    let error = r#"{"error":"invalid_grant","error_description":"Bad Request"}"#;
+                    respond(request, 400, error.to_string(), JSON);
+                }
+                continue;
+            }
+
+            if header(&request, "Authorization") != Some(&format!("Bearer {}", ACCESS_TOKEN)) {
+                respond(request, 401, r#"{"error":{"code":401}}"#.to_string(), JSON);
+                continue;
+            }
+
+            let segments: Vec<&str> = url.path().trim_start_matches('/').split('/').collect();
+            match (method.as_str(), segments.as_slice()) {
+                ("GET", ["drive", "v3", "files"]) => {
+                    let q = query.get("q").cloned().unwrap_or_default();
+                    let files: Vec<String> = drive
+                        .files
+                        .iter()
+                        .filter(|(_, f)| q.contains(&format!("name = '{}'", f.name)))
+                        .map(|(id, f)| metadata(id, f))
+                        .collect();
+                    respond(
+                        request,
+                        200,
+                        format!(r#"{{"files":[{}]}}"#, files.join(",")),
+                        JSON,
+                    );
+                }
+                ("GET", ["drive", "v3", "files", id]) => match drive.files.get(*id) {
+                    Some(file) if query.get("alt").map(String::as_str) == Some("media") => {
+                        respond(request, 200, file.content.clone(), JSON)
+                    }
+                    Some(file) => respond(request, 200, metadata(id, file), JSON),
+                    None => respond(request, 404, "{}".to_string(), JSON),
+                },
+                ("POST", ["upload", "drive", "v3", "files"]) => {
+                    assert_eq!(
+                        query.get("uploadType").map(String::as_str),
+                        Some("multipart")
+                    );
+                    let content_type = header(&request, "Content-Type").unwrap().to_string();
+                    let (meta, content) = parse_multipart(&content_type, &body);
+                    drive.next_id += 1;
+                    let id = format!("file{}", drive.next_id);
+                    let file = DriveFile {
+                        name: meta["name"].as_str().unwrap().to_string(),
+                        content,
+                        version: 1,
+                    };
+                    let reply = metadata(&id, &file);
+                    drive.files.insert(id, file);
+                    respond(request, 200, reply, JSON);
+                }
+                ("PATCH", ["upload", "drive", "v3", "files", id]) => {
+                    assert_eq!(query.get("uploadType").map(String::as_str), Some("media"));
+                    match drive.files.get_mut(*id) {
+                        Some(file) => {
+                            file.content = body;
+                            file.version += 1;
+                            let reply = metadata(id, file);
+                            respond(request, 200, reply, JSON);
+                        }
+                        None => respond(request, 404, "{}".to_string(), JSON),
+                    }
+                }
+                _ => respond(request, 404, "{}".to_string(), JSON),
+            }
+        }
+    });
+
+    let endpoints = DriveEndpoints {
+        token_url: format!("{}/token", base),
+        revoke_url: format!("{}/revoke", base),
+        api_base: format!("{}/drive/v3", base),
+        upload_base: format!("{}/upload/drive/v3", base),
+    };
+    (endpoints, state)
+}
+
+fn config() -> DriveConfig {
+    DriveConfig {
+        client_id: "client".to_string(),
+        client_secret: "secret".to_string(),
+    }
+}
+
+#[test]
+fn test_drive_token_exchange() {
+    let (endpoints, _state) = start_drive_server();
+    let tokens = exchange_code(
+        &endpoints,
+        &config(),
+        "good-code",
+        "verifier",
+        "http://localhost:51737/",
+    )
+    .unwrap();
+    assert_eq!(tokens.access_token, ACCESS_TOKEN);
+    assert_eq!(tokens.refresh_token.as_deref(), Some("refresh"));
+    assert!(tokens.expires_at.unwrap() > chrono::Utc::now().timestamp());
+
+    let err = exchange_code(&endpoints, &config(), "bad-code", "verifier", "x").unwrap_err();
+    assert!(err.contains("Bad Request"), "unexpected error: {}", err);
+}
+
+#[test]
+fn test_drive_create_find_download() {
+    let (endpoints, state) = start_drive_server();
+    let client = DriveClient::new(endpoints, ACCESS_TOKEN);
+    assert!(client.find_file("notes.json").unwrap().is_none());
+
+    let created = client.create("notes.json", r#"[{"a":1}]"#).unwrap();
+    assert_eq!(created.version, "1");
+    assert_eq!(state.lock().unwrap().files[&created.id].name, "notes.json");
+
+    let found = client.find_file("notes.json").unwrap().unwrap();
+    assert_eq!(found, created);
+    assert_eq!(client.download(&found.id).unwrap(), r#"[{"a":1}]"#);
+}
+
+#[test]
+fn test_drive_conditional_update() {
+    let (endpoints, _state) = start_drive_server();
+    let client = DriveClient::new(endpoints, ACCESS_TOKEN);
+    let created = client.create("notes.json", "[]").unwrap();
+
+    let updated = client
+        .update(&created.id, "[1]", Some(&created.version))
+        .unwrap();
+    assert_eq!(updated.version, "2");
+
+    assert_eq!(
+        client.update(&created.id, "[2]", Some(&created.version)),
+        Err(StoreError::Conflict)
+    );
+    assert_eq!(client.download(&created.id).unwrap(), "[1]");
+}
+
+#[test]
+fn test_drive_two_devices_converge() {
+    let (endpoints, state) = start_drive_server();
+    let device_a = DriveDocument::new(DriveClient::new(endpoints.clone(), ACCESS_TOKEN));
+    let device_b = DriveDocument::new(DriveClient::new(endpoints, ACCESS_TOKEN));
+
+    let note_a = note("From A", "2026-01-01T00:00:00Z");
+    let mut cursor_a = SyncCursor::default();
+    let merged_a = sync_document(&device_a, std::slice::from_ref(&note_a), &mut cursor_a).unwrap();
+    assert_eq!(merged_a.len(), 1);
+
+    let note_b = note("From B", "2026-01-02T00:00:00Z");
+    let mut cursor_b = SyncCursor::default();
+    let merged_b = sync_document(&device_b, std::slice::from_ref(&note_b), &mut cursor_b).unwrap();
+    assert_eq!(merged_b.len(), 2);
+    assert_eq!(cursor_b.last_version.as_deref(), Some("2"));
+
+    let merged_a = sync_document(&device_a, &merged_a, &mut cursor_a).unwrap();
+    assert!(merged_a.iter().any(|n| n.id == note_b.id));
+
+    // Both devices wrote to the same single file
+    assert_eq!(state.lock().unwrap().files.len(), 1);
+    assert_eq!(device_a.version().unwrap().as_deref(), Some("2"));
+}
+
+#[test]
+fn test_drive_document_refuses_blind_create() {
+    let (endpoints, _state) = start_drive_server();
+    let device_a = DriveDocument::new(DriveClient::new(endpoints.clone(), ACCESS_TOKEN));
+    let device_b = DriveDocument::new(DriveClient::new(endpoints, ACCESS_TOKEN));
+
+    device_a.store(&[], None).unwrap();
+    assert_eq!(device_b.store(&[], None), Err(StoreError::Conflict));
+}
+
+#[test]
+fn test_drive_expired_token() {
+    let (endpoints, _state) = start_drive_server();
+    let client = DriveClient::new(endpoints, "expired");
+    let err = client.find_file("notes.json").unwrap_err();
+    assert!(err.contains("sign in again"), "unexpected error: {}", err);
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use common::note;
+
+use app_lib::history::GitRepo;
+use app_lib::notes::Note;
+use std::path::Path;
+use std::process::Command;
+use tempfile::tempdir;
+
+fn init_bare(path: &Path) {
+    let status = Command::new("git")
+        .args(["init", "-q", "--bare"])
+        .arg(path)
+        .status()
+        .expect("git must be installed to run these tests");
+    assert!(status.success());
+}
+
+#[test]
+fn test_snapshot_commits_only_changes() {
+    let dir = tempdir().expect("failed to create temp dir");
+    let repo = GitRepo::open_or_init(dir.path()).unwrap();
+
+    let mut first = Note {
+        content: "day 1".to_string(),
+        ..note("Diary", "2026-01-01T00:00:00Z")
+    };
+    assert!(repo
+        .snapshot(std::slice::from_ref(&first))
+        .unwrap()
+        .is_some());
+    assert!(repo
+        .snapshot(std::slice::from_ref(&first))
+        .unwrap()
+        .is_none());
+
+    // Window moves are not part of the history
+    first.window_state.x = 500;
+    assert!(repo
+        .snapshot(std::slice::from_ref(&first))
+        .unwrap()
+        .is_none());
+
+    first.content = "day 2".to_string();
+    assert!(repo
+        .snapshot(std::slice::from_ref(&first))
+        .unwrap()
+        .is_some());
+    assert!(dir.path().join(format!("notes/{}.md", first.id)).exists());
+}
+
+#[test]
+fn test_note_log_and_revisions() {
+    let dir = tempdir().expect("failed to create temp dir");
+    let repo = GitRepo::open_or_init(dir.path()).unwrap();
+
+    let mut diary = Note {
+        content: "first draft".to_string(),
+        ..note("Diary", "2026-01-01T00:00:00Z")
+    };
+    let other = Note {
+        content: "".to_string(),
+        ..note("Other", "2026-01-01T00:00:00Z")
+    };
+    repo.snapshot(&[diary.clone(), other.clone()]).unwrap();
+    diary.content = "second draft".to_string();
+    repo.snapshot(&[diary.clone(), other.clone()]).unwrap();
+
+    let log = repo.note_log(&diary.id).unwrap();
+    assert_eq!(log.len(), 2);
+    assert_eq!(log[0].message, "Update \"Diary\"");
+    assert_eq!(log[1].message, "Update 2 notes");
+    assert_eq!(repo.note_log(&other.id).unwrap().len(), 1);
+
+    let old = repo.note_at(&diary.id, &log[1].commit).unwrap();
+    assert_eq!(old.content, "first draft");
+    assert!(repo.note_at("../secret", &log[1].commit).is_err());
+}
+
+#[test]
+fn test_push_and_pull_through_bare_remote() {
+    let remote = tempdir().expect("failed to create temp dir");
+    let dir_a = tempdir().expect("failed to create temp dir");
+    let dir_b = tempdir().expect("failed to create temp dir");
+    init_bare(remote.path());
+    let remote_url = remote.path().to_str().unwrap();
+
+    // Device A publishes a note
+    let repo_a = GitRepo::open_or_init(dir_a.path()).unwrap();
+    repo_a.set_remote(remote_url).unwrap();
+    let from_a = Note {
+        content: "hello".to_string(),
+        ..note("From A", "2026-01-01T00:00:00Z")
+    };
+    repo_a.snapshot(std::slice::from_ref(&from_a)).unwrap();
+    repo_a.push().unwrap();
+
+    // Device B has its own unrelated history, pulls and merges
+    let repo_b = GitRepo::open_or_init(dir_b.path()).unwrap();
+    repo_b.set_remote(remote_url).unwrap();
+    let from_b = Note {
+        content: "world".to_string(),
+        ..note("From B", "2026-01-02T00:00:00Z")
+    };
+    repo_b.snapshot(std::slice::from_ref(&from_b)).unwrap();
+
+    let remote_notes = repo_b.fetch().unwrap().expect("remote has new commits");
+    assert_eq!(remote_notes.len(), 1);
+    assert_eq!(remote_notes[0].id, from_a.id);
+    repo_b
+        .complete_merge(&[from_b.clone(), remote_notes[0].clone()])
+        .unwrap();
+    repo_b.push().unwrap();
+
+    // Device A now fast-forwards to see both notes
+    let pulled = repo_a.fetch().unwrap().expect("remote has new commits");
+    assert_eq!(pulled.len(), 2);
+    assert!(pulled
+        .iter()
+        .any(|n| n.id == from_b.id && n.content == "world"));
+
+    // Nothing new once B's merge has been taken
+    repo_a.complete_merge(&pulled).unwrap();
+    assert!(repo_a.fetch().unwrap().is_none());
+}
+
+#[test]
+fn test_fetch_from_empty_remote() {
+    let remote = tempdir().expect("failed to create temp dir");
+    let dir = tempdir().expect("failed to create temp dir");
+    init_bare(remote.path());
+
+    let repo = GitRepo::open_or_init(dir.path()).unwrap();
+    repo.set_remote(remote.path().to_str().unwrap()).unwrap();
+    assert!(repo.fetch().unwrap().is_none());
+}
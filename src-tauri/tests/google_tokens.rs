@@ -0,0 +1,143 @@
+use app_lib::sync::drive::{DriveConfig, DriveEndpoints, DriveTokens};
+use app_lib::tokens::{GoogleCredentials, GoogleSession, PrivateStore};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tempfile::tempdir;
+
+/// Form bodies received by the mock token and revoke endpoints, keyed by path.
+type Received = Arc<Mutex<Vec<(String, HashMap<String, String>)>>>;
+
+fn start_token_server() -> (DriveEndpoints, Received) {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let base = format!(
+        "http://127.0.0.1:{}",
+        server.server_addr().to_ip().unwrap().port()
+    );
+    let received: Received = Arc::default();
+
+    let shared = Arc::clone(&received);
+    std::thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).ok();
+            let form: HashMap<String, String> = url::form_urlencoded::parse(body.as_bytes())
+                .into_owned()
+                .collect();
+            let path = request.url().to_string();
+            shared.lock().unwrap().push((path.clone(), form.clone()));
+
+            let (status, reply) = match path.as_str() {
+                "/token" if form.get("refresh_token").map(String::as_str) == Some("refresh-1") => (
+                    200,
+                    r#"{"access_token":"access-2","expires_in":3599,"token_type":"Bearer"}"#,
+                ),
+                "/token" => (400, r#"{"error":"invalid_grant"}"#),
+                "/revoke" => (200, "{}"),
+                _ => (404, "{}"),
+            };
+            request
+                .respond(tiny_http::Response::from_string(reply).with_status_code(status))
+                .ok();
+        }
+    });
+
+    let endpoints = DriveEndpoints {
+        token_url: format!("{}/token", base),
+        revoke_url: format!("{}/revoke", base),
+        ..Default::default()
+    };
+    (endpoints, received)
+}
+
+fn session(dir: &Path, endpoints: DriveEndpoints) -> GoogleSession {
+    GoogleSession::new(
+        PrivateStore::new(dir.join("google_tokens.bin"), dir.join("token.key")),
+        endpoints,
+    )
+}
+
+fn credentials(refresh_token: &str, expires_at: i64) -> GoogleCredentials {
+    GoogleCredentials {
+        config: DriveConfig {
+            client_id: "client".to_string(),
+            client_secret: "secret".to_string(),
+        },
+        tokens: DriveTokens {
+            access_token: "access-1".to_string(),
+            refresh_token: Some(refresh_token.to_string()),
+            expires_at: Some(expires_at),
+        },
+    }
+}
+
+#[test]
+fn test_expired_access_token_is_refreshed_and_persisted() {
+    let dir = tempdir().expect("failed to create temp dir");
+    let (endpoints, received) = start_token_server();
+    let google = session(dir.path(), endpoints.clone());
+    let almost_expired = chrono::Utc::now().timestamp() + 10;
+    google
+        .save(&credentials("refresh-1", almost_expired))
+        .unwrap();
+
+    assert_eq!(google.access_token().unwrap(), "access-2");
+    {
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let (path, form) = &received[0];
+        assert_eq!(path, "/token");
+        assert_eq!(form["grant_type"], "refresh_token");
+        assert_eq!(form["client_id"], "client");
+    }
+
+    // A new session over the same files sees the refreshed token without another request
+    let reopened = session(dir.path(), endpoints);
+    let stored = reopened.load().unwrap();
+    assert_eq!(stored.tokens.access_token, "access-2");
+    assert_eq!(stored.tokens.refresh_token.as_deref(), Some("refresh-1"));
+    assert_eq!(reopened.access_token().unwrap(), "access-2");
+    assert_eq!(received.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn test_rejected_refresh_is_an_error() {
+    let dir = tempdir().expect("failed to create temp dir");
+    let (endpoints, _received) = start_token_server();
+    let google = session(dir.path(), endpoints);
+    google.save(&credentials("revoked", 0)).unwrap();
+
+    let err = google.access_token().unwrap_err();
+    assert!(err.contains("invalid_grant"), "unexpected error: {}", err);
+}
+
+#[test]
+fn test_sign_out_revokes_and_forgets_tokens() {
+    let dir = tempdir().expect("failed to create temp dir");
+    let (endpoints, received) = start_token_server();
+    let google = session(dir.path(), endpoints);
+    google.save(&credentials("refresh-1", 0)).unwrap();
+
+    google.sign_out().unwrap();
+    assert!(google.load().is_none());
+    assert!(google.access_token().is_err());
+
+    let received = received.lock().unwrap();
+    let (path, form) = received.last().unwrap();
+    assert_eq!(path, "/revoke");
+    assert_eq!(form["token"], "refresh-1");
+}
+
+#[test]
+fn test_sign_out_without_network_still_forgets_tokens() {
+    let dir = tempdir().expect("failed to create temp dir");
+    let endpoints = DriveEndpoints {
+        revoke_url: "http://127.0.0.1:9/revoke".to_string(),
+        ..Default::default()
+    };
+    let google = session(dir.path(), endpoints);
+    google.save(&credentials("refresh-1", 0)).unwrap();
+
+    google.sign_out().unwrap();
+    assert!(google.load().is_none());
+}
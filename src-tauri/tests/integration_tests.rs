@@ -87,6 +87,7 @@ fn test_load_non_existent_file() {
 }
 
 #[test]
+#[allow(unused_mut)]
 fn test_concurrent_addition_to_store() {
     use std::sync::{Arc, Mutex};
     use std::thread;
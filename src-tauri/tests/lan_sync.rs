@@ -0,0 +1,135 @@
+mod common;
+
+use app_lib::notes::Note;
+use app_lib::sync::lan::{LanHost, LanIdentity, LanNode, PairedPeer};
+use app_lib::sync::merge_notes;
+use common::note;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct MemoryHost {
+    notes: Mutex<Vec<Note>>,
+    peers: Mutex<Vec<PairedPeer>>,
+}
+
+impl LanHost for MemoryHost {
+    fn local_notes(&self) -> Result<Vec<Note>, String> {
+        Ok(self.notes.lock().unwrap().clone())
+    }
+
+    fn apply_notes(&self, notes: &[Note]) -> Result<Vec<Note>, String> {
+        let mut local = self.notes.lock().unwrap();
+        *local = merge_notes(&local, notes);
+        Ok(local.clone())
+    }
+
+    fn peer(&self, device_id: &str) -> Option<PairedPeer> {
+        self.peers
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|p| p.device_id == device_id)
+            .cloned()
+    }
+
+    fn save_peer(&self, peer: PairedPeer) -> Result<(), String> {
+        let mut peers = self.peers.lock().unwrap();
+        peers.retain(|p| p.device_id != peer.device_id);
+        peers.push(peer);
+        Ok(())
+    }
+}
+
+fn start_node(device_id: &str, notes: Vec<Note>) -> (LanNode, Arc<MemoryHost>) {
+    let host = Arc::new(MemoryHost {
+        notes: Mutex::new(notes),
+        ..Default::default()
+    });
+    let identity = LanIdentity {
+        device_id: device_id.to_string(),
+        name: device_id.to_uppercase(),
+    };
+    let node = LanNode::start(
+        identity,
+        host.clone(),
+        SocketAddr::from(([127, 0, 0, 1], 0)),
+    )
+    .unwrap();
+    (node, host)
+}
+
+#[test]
+fn test_pair_then_sync_converges() {
+    let (laptop, laptop_host) =
+        start_node("laptop", vec![note("From laptop", "2026-01-01T00:00:00Z")]);
+    let (desktop, desktop_host) = start_node(
+        "desktop",
+        vec![note("From desktop", "2026-01-01T00:00:00Z")],
+    );
+
+    // The user reads the code off the desktop and types it (sloppily) on the laptop
+    let code = desktop.begin_pairing().to_lowercase().replace('-', " ");
+    let address = desktop.local_addr().to_string();
+    let paired = laptop.pair_with(&address, &code).unwrap();
+    assert_eq!(paired.device_id, "desktop");
+
+    let desktop_side = desktop_host
+        .peer("laptop")
+        .expect("desktop stored the pairing");
+    assert_eq!(desktop_side.secret, paired.secret);
+
+    let merged = laptop.sync_with(&address, &paired).unwrap();
+    assert_eq!(merged.len(), 2);
+    assert_eq!(desktop_host.local_notes().unwrap().len(), 2);
+    assert_eq!(laptop_host.local_notes().unwrap().len(), 2);
+}
+
+#[test]
+fn test_wrong_code_is_rejected() {
+    let (laptop, laptop_host) = start_node("laptop", Vec::new());
+    let (desktop, desktop_host) = start_node("desktop", Vec::new());
+
+    let code = desktop.begin_pairing();
+    let wrong = if code.starts_with('A') {
+        "BBBBB-BBBBB"
+    } else {
+        "AAAAA-AAAAA"
+    };
+    let address = desktop.local_addr().to_string();
+
+    assert!(laptop.pair_with(&address, wrong).is_err());
+    assert!(laptop_host.peer("desktop").is_none());
+    assert!(desktop_host.peer("laptop").is_none());
+
+    // The window stays open for a correct retry
+    assert!(laptop.pair_with(&address, &code).is_ok());
+}
+
+#[test]
+fn test_pairing_code_is_single_use() {
+    let (laptop, _) = start_node("laptop", Vec::new());
+    let (phone, _) = start_node("phone", Vec::new());
+    let (desktop, _) = start_node("desktop", Vec::new());
+
+    let code = desktop.begin_pairing();
+    let address = desktop.local_addr().to_string();
+    laptop.pair_with(&address, &code).unwrap();
+    assert!(phone.pair_with(&address, &code).is_err());
+}
+
+#[test]
+fn test_sync_requires_pairing() {
+    let (laptop, _) = start_node("laptop", vec![note("Secret", "2026-01-01T00:00:00Z")]);
+    let (desktop, desktop_host) = start_node("desktop", Vec::new());
+
+    let forged = PairedPeer {
+        device_id: "desktop".to_string(),
+        name: "Desktop".to_string(),
+        secret: "11".repeat(32),
+        last_address: None,
+    };
+    let address = desktop.local_addr().to_string();
+    assert!(laptop.sync_with(&address, &forged).is_err());
+    assert!(desktop_host.local_notes().unwrap().is_empty());
+}
@@ -0,0 +1,111 @@
+use app_lib::linked::{read_linked, reload, write_back, LineEnding, LinkedState, WriteBack};
+use app_lib::notes::Note;
+use std::fs;
+
+use tempfile::tempdir;
+
+#[test]
+fn test_edits_are_written_back_in_the_file_format() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("todo.md");
+    fs::write(&path, b"\xEF\xBB\xBF# Todo\r\n- milk\r\n").unwrap();
+
+    let (mut note, mut link) = read_linked(&path).unwrap();
+    assert_eq!(note.title, "todo");
+    assert_eq!(note.content, "# Todo\n- milk\n");
+    assert_eq!(link.line_ending, LineEnding::Crlf);
+
+    // Saving unchanged content does not touch the file
+    assert_eq!(
+        write_back(&mut note, &mut link).unwrap(),
+        WriteBack::Unchanged
+    );
+
+    note.content.push_str("- eggs\n");
+    assert_eq!(
+        write_back(&mut note, &mut link).unwrap(),
+        WriteBack::Written
+    );
+    assert_eq!(
+        fs::read(&path).unwrap(),
+        b"\xEF\xBB\xBF# Todo\r\n- milk\r\n- eggs\r\n"
+    );
+}
+
+#[test]
+fn test_external_edits_are_reloaded() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("plan.md");
+    fs::write(&path, "v1\n").unwrap();
+    let (mut note, mut link) = read_linked(&path).unwrap();
+    assert!(!reload(&mut note, &mut link).unwrap());
+
+    // Another editor rewrites the file; force a different mtime
+    fs::write(&path, "v2 from elsewhere\n").unwrap();
+    link.modified_ms = Some(0);
+
+    assert_eq!(
+        write_back(&mut note, &mut link).unwrap(),
+        WriteBack::Reloaded
+    );
+    assert_eq!(note.content, "v2 from elsewhere\n");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "v2 from elsewhere\n");
+}
+
+#[test]
+fn test_edits_on_both_sides_are_a_conflict() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("plan.md");
+    fs::write(&path, "v1\n").unwrap();
+    let (mut note, mut link) = read_linked(&path).unwrap();
+
+    fs::write(&path, "v2 from elsewhere\n").unwrap();
+    link.modified_ms = Some(0);
+
+    note.content = "local edit\n".to_string();
+    assert_eq!(
+        write_back(&mut note, &mut link).unwrap(),
+        WriteBack::Conflict("local edit\n".to_string())
+    );
+    assert_eq!(note.content, "v2 from elsewhere\n");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "v2 from elsewhere\n");
+}
+
+#[test]
+fn test_missing_file_is_left_alone() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("gone.md");
+    fs::write(&path, "text\n").unwrap();
+    let (mut note, mut link) = read_linked(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    note.content = "edited\n".to_string();
+    assert_eq!(
+        write_back(&mut note, &mut link).unwrap(),
+        WriteBack::Unchanged
+    );
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_links_are_kept_out_of_synced_notes() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("local.md");
+    fs::write(&path, "text\n").unwrap();
+    let (note, link) = read_linked(&path).unwrap();
+
+    let links_path = dir.path().join("linked_files.json");
+    let state = LinkedState::load(links_path.clone());
+    state
+        .update(|links| links.insert(note.id.clone(), link.clone()))
+        .unwrap();
+    assert_eq!(LinkedState::load(links_path).get(&note.id), Some(link));
+
+    // A note arriving from another device cannot carry a path with it
+    let json = serde_json::to_string(&note).unwrap();
+    assert!(!json.contains("local.md"));
+    let remote = r#"{"id":"x","title":"t","content":"","created_at":"","updated_at":"",
+        "linked_file":{"path":"/home/me/.ssh/id_rsa","hash":""}}"#;
+    let remote: Note = serde_json::from_str(remote).unwrap();
+    assert!(!serde_json::to_string(&remote).unwrap().contains("id_rsa"));
+}
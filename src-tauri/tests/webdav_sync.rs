@@ -0,0 +1,190 @@
+mod common;
+
+use app_lib::sync::webdav::{WebDavClient, WebDavConfig};
+use app_lib::sync::{sync_document, RemoteDocument, StoreError, SyncCursor};
+use common::{header, note, respond};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+const AUTHORIZATION: &str = "Basic dXNlcjpwYXNz"; // user:pass
+
+#[derive(Default)]
+struct DavState {
+    collections: HashSet<String>,
+    files: HashMap<String, (String, u32)>,
+}
+
+fn etag(version: u32) -> String {
+    format!("\"v{}\"", version)
+}
+
+/// Minimal WebDAV stand-in: PROPFIND/MKCOL/GET/PUT with ETag preconditions.
+fn start_dav_server() -> (String, Arc<Mutex<DavState>>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let port = server.server_addr().to_ip().unwrap().port();
+    let state = Arc::new(Mutex::new(DavState::default()));
+    state.lock().unwrap().collections.insert("/".to_string());
+
+    let shared = Arc::clone(&state);
+    std::thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            if header(&request, "Authorization") != Some(AUTHORIZATION) {
+                respond(request, 401, String::new(), &[]);
+                continue;
+            }
+
+            let path = request.url().to_string();
+            let parent = format!("{}/", path.rsplit_once('/').unwrap().0);
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).ok();
+            let mut dav = shared.lock().unwrap();
+
+            match request.method().as_str() {
+                "PROPFIND" => {
+                    if let Some((_, version)) = dav.files.get(&path) {
+                        let xml = format!(
+                            "<d:multistatus xmlns:d=\"DAV:\"><d:response><d:href>{}</d:href>\
+                             <d:propstat><d:prop><d:getetag>{}</d:getetag></d:prop>\
+                             <d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response></d:multistatus>",
+                            path,
+                            etag(*version).replace('"', "&quot;")
+                        );
+                        respond(request, 207, xml, &[]);
+                    } else if dav.collections.contains(&path) {
+                        respond(
+                            request,
+                            207,
+                            "<d:multistatus xmlns:d=\"DAV:\"/>".into(),
+                            &[],
+                        );
+                    } else {
+                        respond(request, 404, String::new(), &[]);
+                    }
+                }
+                "MKCOL" => {
+                    dav.collections.insert(path);
+                    respond(request, 201, String::new(), &[]);
+                }
+                "GET" => match dav.files.get(&path) {
+                    Some((content, version)) => respond(
+                        request,
+                        200,
+                        content.clone(),
+                        &[("ETag", etag(*version).as_str())],
+                    ),
+                    None => respond(request, 404, String::new(), &[]),
+                },
+                "PUT" => {
+                    if !dav.collections.contains(&parent) {
+                        respond(request, 409, String::new(), &[]);
+                        continue;
+                    }
+                    let current = dav.files.get(&path).map(|(_, v)| *v);
+                    let precondition_ok = match (header(&request, "If-Match"), current) {
+                        (Some(expected), Some(v)) => expected == etag(v),
+                        (Some(_), None) => false,
+                        (None, current) => {
+                            header(&request, "If-None-Match") != Some("*") || current.is_none()
+                        }
+                    };
+                    if !precondition_ok {
+                        respond(request, 412, String::new(), &[]);
+                        continue;
+                    }
+                    let version = current.unwrap_or(0) + 1;
+                    dav.files.insert(path, (body, version));
+                    respond(
+                        request,
+                        201,
+                        String::new(),
+                        &[("ETag", etag(version).as_str())],
+                    );
+                }
+                _ => respond(request, 405, String::new(), &[]),
+            }
+        }
+    });
+
+    (format!("http://127.0.0.1:{}/notes", port), state)
+}
+
+fn client(url: &str, password: &str) -> WebDavClient {
+    WebDavClient::new(&WebDavConfig {
+        url: url.to_string(),
+        username: "user".to_string(),
+        password: password.to_string(),
+    })
+    .unwrap()
+}
+
+#[test]
+fn test_webdav_two_devices_converge() {
+    let (url, state) = start_dav_server();
+    let dav = client(&url, "pass");
+    dav.ensure_collection()
+        .expect("collection should be created");
+    assert!(state.lock().unwrap().collections.contains("/notes/"));
+
+    // Device A uploads the first copy
+    let note_a = note("From A", "2026-01-01T00:00:00Z");
+    let mut cursor_a = SyncCursor::default();
+    let merged_a = sync_document(&dav, std::slice::from_ref(&note_a), &mut cursor_a).unwrap();
+    assert_eq!(merged_a.len(), 1);
+    assert_eq!(cursor_a.last_version.as_deref(), Some("\"v1\""));
+
+    // Device B merges its own note with A's
+    let note_b = note("From B", "2026-01-02T00:00:00Z");
+    let mut cursor_b = SyncCursor::default();
+    let merged_b = sync_document(&dav, std::slice::from_ref(&note_b), &mut cursor_b).unwrap();
+    assert_eq!(merged_b.len(), 2);
+    assert_eq!(cursor_b.last_version.as_deref(), Some("\"v2\""));
+
+    // Device A picks up B's note; nothing new to upload
+    let merged_a = sync_document(&dav, &merged_a, &mut cursor_a).unwrap();
+    assert_eq!(merged_a.len(), 2);
+    assert!(merged_a.iter().any(|n| n.id == note_b.id));
+    assert_eq!(
+        state.lock().unwrap().files["/notes/markdown_notes_sync.json"].1,
+        2
+    );
+}
+
+#[test]
+fn test_webdav_stale_etag_is_a_conflict() {
+    let (url, _state) = start_dav_server();
+    let dav = client(&url, "pass");
+    dav.ensure_collection().unwrap();
+
+    let first = dav
+        .store(&[note("One", "2026-01-01T00:00:00Z")], None)
+        .unwrap();
+    dav.store(&[], Some(&first)).unwrap();
+
+    assert_eq!(dav.store(&[], Some(&first)), Err(StoreError::Conflict));
+    assert_eq!(dav.store(&[], None), Err(StoreError::Conflict));
+}
+
+#[test]
+fn test_webdav_fetch_roundtrip() {
+    let (url, _state) = start_dav_server();
+    let dav = client(&url, "pass");
+    dav.ensure_collection().unwrap();
+    assert!(dav.version().unwrap().is_none());
+    assert!(dav.fetch().unwrap().is_none());
+
+    let original = note("Roundtrip", "2026-01-01T00:00:00Z");
+    let version = dav.store(std::slice::from_ref(&original), None).unwrap();
+
+    assert_eq!(dav.version().unwrap(), Some(version.clone()));
+    let snapshot = dav.fetch().unwrap().expect("file should exist");
+    assert_eq!(snapshot.version, version);
+    assert_eq!(snapshot.notes[0].id, original.id);
+    assert_eq!(snapshot.notes[0].title, "Roundtrip");
+}
+
+#[test]
+fn test_webdav_rejects_bad_credentials() {
+    let (url, _state) = start_dav_server();
+    let err = client(&url, "wrong").ensure_collection().unwrap_err();
+    assert!(err.contains("credentials"), "unexpected error: {}", err);
+}
@@ -150,26 +150,6 @@ export async function initGoogleDrive(): Promise<void> {
   });
 }
 
-/**
- * PKCE Helpers
- */
-async function generatePKCE(): Promise<{ verifier: string; challenge: string }> {
-  const verifier = Array.from(crypto.getRandomValues(new Uint8Array(32)))
-    .map((b) => b.toString(16).padStart(2, '0'))
-    .join('');
-
-  const encoder = new TextEncoder();
-  const data = encoder.encode(verifier);
-  const hash = await crypto.subtle.digest('SHA-256', data);
-
-  const challenge = btoa(String.fromCharCode(...new Uint8Array(hash)))
-    .replace(/\+/g, '-')
-    .replace(/\//g, '_')
-    .replace(/=+$/, '');
-
-  return { verifier, challenge };
-}
-
 /**
  * Sign in to Google
  */
@@ -191,91 +171,50 @@ export async function signInGoogleDrive(
   if (isTauri() && !silent) {
     const { invoke } = await import('@tauri-apps/api/core');
 
-    const redirectUri = 'http://localhost:51737/';
     await invoke('frontend_log', {
       level: 'info',
       message: '[SYNC] Starting Auth (PKCE) for WSL2/Desktop',
     });
-    await invoke('frontend_log', {
-      level: 'info',
-      message: `[SYNC] redirect_uri set to: ${redirectUri}`,
-    });
 
     try {
-      // 1. Generate PKCE
-      const { verifier, challenge } = await generatePKCE();
-
-      // 2. Build Auth URL safely
       if (!CLIENT_ID_DESKTOP) {
         throw new Error('Google CLIENT_ID_DESKTOP is missing. Please check your .env file.');
       }
 
-      const params = new URLSearchParams({
-        client_id: CLIENT_ID_DESKTOP,
-        redirect_uri: redirectUri,
-        response_type: 'code',
-        scope: SCOPES,
-        prompt: 'select_account',
-        code_challenge: challenge,
-        code_challenge_method: 'S256',
-      });
-      const authUrl = `https://accounts.google.com/o/oauth2/v2/auth?${params.toString()}`;
-      await invoke('frontend_log', {
-        level: 'info',
-        message: `[SYNC] Auth URL generated (client_id: ${CLIENT_ID_DESKTOP?.substring(0, 10)}...)`,
-      });
-
-      // 3. Start loopback server and wait for code
-      await invoke('frontend_log', {
-        level: 'info',
-        message: '[SYNC] Starting loopback server on 51737...',
-      });
-      const codePromise = invoke('start_google_auth_server');
-
-      // 4. Open auth URL in system browser
+      // 1. The backend generates PKCE + state, opens the browser and waits for the callback
       await invoke('frontend_log', {
         level: 'info',
-        message: '[SYNC] Opening external browser...',
+        message: '[SYNC] Starting loopback server and opening external browser...',
       });
-      await invoke('open_external_url', { url: authUrl });
-
-      const code = await codePromise;
+      const auth = await invoke<{ code: string; code_verifier: string; redirect_uri: string }>(
+        'start_google_auth_server',
+        { clientId: CLIENT_ID_DESKTOP, ephemeralPort: true }
+      );
       await invoke('frontend_log', {
         level: 'info',
         message: '[SYNC] Authorization code received from loopback server',
       });
 
-      // 5. Exchange code for token
+      // 2. Exchange code for token
       await invoke('frontend_log', {
         level: 'info',
         message: '[SYNC] Exchanging code for token...',
       });
-      const tokenResponse = await fetch('https://oauth2.googleapis.com/token', {
-        method: 'POST',
-        headers: { 'Content-Type': 'application/x-www-form-urlencoded' },
-        body: new URLSearchParams({
-          code: code as string,
-          client_id: CLIENT_ID_DESKTOP,
-          client_secret: CLIENT_SECRET_DESKTOP,
-          redirect_uri: redirectUri,
-          grant_type: 'authorization_code',
-          code_verifier: verifier,
-        }),
-      });
-
-      await invoke('frontend_log', {
-        level: 'info',
-        message: `[SYNC] Token exchange status: ${tokenResponse.status}`,
+      // The backend keeps the tokens and performs Drive sync itself
+      const accessToken = await invoke<string>('connect_google_drive', {
+        code: auth.code,
+        codeVerifier: auth.code_verifier,
+        redirectUri: auth.redirect_uri,
+        clientId: CLIENT_ID_DESKTOP,
+        clientSecret: CLIENT_SECRET_DESKTOP,
+      }).catch(async (e) => {
+        await invoke('frontend_log', {
+          level: 'error',
+          message: `[SYNC] Token exchange error: ${e}`,
+        });
+        throw new Error(`${e}`);
       });
-      const tokenData = await tokenResponse.json();
-
-      if (tokenData.error) {
-        const errorLog = `[SYNC] Token exchange error: ${tokenData.error} - ${tokenData.error_description || 'no description'}`;
-        await invoke('frontend_log', { level: 'error', message: errorLog });
-        throw new Error(`Token exchange failed: ${tokenData.error_description || tokenData.error}`);
-      }
 
-      const accessToken = tokenData.access_token;
       if (accessToken) {
         await invoke('frontend_log', {
           level: 'info',
@@ -287,7 +226,7 @@ export async function signInGoogleDrive(
       } else {
         await invoke('frontend_log', {
           level: 'error',
-          message: `[SYNC] No access token in response: ${JSON.stringify(tokenData).substring(0, 100)}...`,
+          message: '[SYNC] No access token in response',
         });
         throw new Error('No access token received from Google');
       }
@@ -336,6 +275,14 @@ export function signOutGoogleDrive(): void {
   localStorage.removeItem('markdown_editor_gdrive_enabled');
 }
 
+/**
+ * Use an access token obtained elsewhere (the Tauri backend keeps and refreshes it)
+ */
+export function setGoogleDriveAccessToken(accessToken: string): void {
+  gapi.client.setToken({ access_token: accessToken });
+  localStorage.setItem('markdown_editor_gdrive_enabled', 'true');
+}
+
 /**
  * Check if there was a previous session
  */
@@ -1,4 +1,4 @@
-import { invoke } from '@tauri-apps/api/core';
+import { convertFileSrc, invoke } from '@tauri-apps/api/core';
 import { getCurrentWindow } from '@tauri-apps/api/window';
 import { listen } from '@tauri-apps/api/event';
 import { readTextFile } from '@tauri-apps/plugin-fs';
@@ -13,9 +13,10 @@ import {
   readGoogleDriveSyncFile,
   saveToGoogleDrive,
   isGoogleDriveLoggedIn,
+  setGoogleDriveAccessToken,
 } from './google-drive.js';
 import { SyncLogic } from './sync-logic.js';
-import { Adapter, Note } from './types';
+import { Adapter, Note, WindowPrefs } from './types';
 
 // Global flag to help GoogleDriveService detect Tauri environment
 window.IS_TAURI_ADAPTER = true;
@@ -105,6 +106,17 @@ export const TauriAdapter: Adapter = {
   // Sync operations
   async initSync() {
     await initGoogleDrive();
+    const settings = await invoke<{ google_drive_connected: boolean }>('get_sync_settings');
+    if (settings.google_drive_connected) {
+      // Tokens live in the backend, which refreshes them as needed
+      try {
+        setGoogleDriveAccessToken(await invoke<string>('get_access_token'));
+        await this.syncWithDrive();
+      } catch (e) {
+        console.log('Tauri auto-sync not available:', e);
+      }
+      return;
+    }
     if (hasPreviousGoogleDriveSession()) {
       try {
         const lastUser = localStorage.getItem('markdown_editor_last_synced_user');
@@ -121,7 +133,12 @@ export const TauriAdapter: Adapter = {
   },
 
   async signOut() {
-    signOutGoogleDrive();
+    try {
+      await invoke('sign_out');
+    } finally {
+      // Forget the session in the UI even if revoking it failed
+      signOutGoogleDrive();
+    }
   },
 
   async signIn() {
@@ -140,6 +157,15 @@ export const TauriAdapter: Adapter = {
       return;
     }
 
+    // Non-Drive providers (WebDAV, ...) are synced entirely by the Rust backend
+    const settings = await invoke<{ provider: string; google_drive_connected: boolean }>(
+      'get_sync_settings'
+    );
+    if (settings.provider !== 'google_drive') {
+      await invoke('sync_now');
+      return;
+    }
+
     if (!isGoogleDriveLoggedIn()) {
       // console.log('[DEBUG] TauriAdapter: Sync skipped - Not logged in');
       return;
@@ -168,6 +194,12 @@ export const TauriAdapter: Adapter = {
         localStorage.setItem('markdown_editor_last_synced_user', currentUser);
       }
 
+      // Signed in through the loopback flow: the backend owns the Drive traffic
+      if (settings.google_drive_connected) {
+        await invoke('sync_now');
+        return;
+      }
+
       const file = await findGoogleDriveSyncFile();
       const localNotes = await invoke<Note[]>('get_all_notes');
 
@@ -269,6 +301,28 @@ export const TauriAdapter: Adapter = {
     return await currentWindow.innerSize();
   },
 
+  async setNoteWindowPrefs(noteId: string, prefs: WindowPrefs): Promise<WindowPrefs> {
+    return await invoke('set_note_window_prefs', { noteId, prefs });
+  },
+
+  onWindowPrefsChanged(callback: (prefs: WindowPrefs) => void) {
+    const unlisten = getCurrentWindow().listen<WindowPrefs>('window-prefs-changed', (event) =>
+      callback(event.payload)
+    );
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  },
+
+  onNoteUpdated(callback: (note: Note) => void) {
+    const unlisten = getCurrentWindow().listen<Note>('note-updated', (event) =>
+      callback(event.payload)
+    );
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  },
+
   async updateWindowState(noteId: string, x: number, y: number, width: number, height: number) {
     return await invoke('update_window_state', { noteId, x, y, width, height });
   },
@@ -281,9 +335,13 @@ export const TauriAdapter: Adapter = {
 
     let u: (() => void) | undefined;
 
-    unlistenPromise.then((fn: any) => {
-      u = fn;
-    });
+    unlistenPromise
+      .then((fn: any) => {
+        u = fn;
+        // Only now may the backend deliver files and searches queued at launch
+        return invoke('launch_ready');
+      })
+      .catch(console.error);
     return () => {
       if (u) u();
     };
@@ -292,4 +350,19 @@ export const TauriAdapter: Adapter = {
   async readTextFile(path: string) {
     return await readTextFile(path);
   },
+
+  async openLinkedFile(path: string): Promise<Note> {
+    return await invoke('open_linked_file', { path });
+  },
+
+  async addAttachment(data: Uint8Array, extension: string): Promise<string> {
+    // Sent as a raw body; JSON would inflate screenshots several times over
+    return await invoke('add_attachment', data, {
+      headers: { 'x-attachment-extension': extension },
+    });
+  },
+
+  attachmentUrl(name: string) {
+    return convertFileSrc(name, 'attachment');
+  },
 };
@@ -1,3 +1,11 @@
+export interface WindowPrefs {
+  always_on_top: boolean;
+  opacity: number;
+  compact: boolean;
+  pinned_to_desktop: boolean;
+  click_through: boolean;
+}
+
 export interface Note {
   id: string;
   title: string;
@@ -11,7 +19,10 @@ export interface Note {
     y: number;
     width: number;
     height: number;
+    monitor?: string | null;
+    scale_factor?: number | null;
   };
+  window_prefs?: WindowPrefs;
 }
 
 export interface Adapter {
@@ -42,6 +53,9 @@ export interface Adapter {
   onWindowResized(callback: (payload: any) => void): () => void;
   getWindowPosition(): Promise<{ x: number; y: number }>;
   getWindowSize(): Promise<{ width: number; height: number }>;
+  onWindowPrefsChanged?(callback: (prefs: WindowPrefs) => void): () => void;
+  setNoteWindowPrefs?(noteId: string, prefs: WindowPrefs): Promise<WindowPrefs>;
+  onNoteUpdated?(callback: (note: Note) => void): () => void;
   updateWindowState(
     noteId: string,
     x: number,
@@ -53,4 +67,9 @@ export interface Adapter {
   // Events
   onFileOpen(callback: (payload: any) => void): () => void;
   readTextFile(path: string): Promise<string>;
+  openLinkedFile?(path: string): Promise<Note>;
+
+  // Attachments
+  addAttachment?(data: Uint8Array, extension: string): Promise<string>;
+  attachmentUrl?(name: string): string;
 }
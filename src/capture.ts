@@ -0,0 +1,42 @@
+import { invoke } from '@tauri-apps/api/core';
+import { getCurrentWindow } from '@tauri-apps/api/window';
+
+const HIDE_DELAY_MS = 600;
+
+const textArea = document.getElementById('capture-text') as HTMLTextAreaElement;
+const status = document.getElementById('capture-status');
+const submitButton = document.getElementById('capture-submit');
+
+async function submit() {
+  const text = textArea.value;
+  if (!text.trim()) return;
+
+  try {
+    await invoke('append_to_note', { noteId: null, text });
+    textArea.value = '';
+    if (status) status.textContent = '追加しました';
+    setTimeout(() => {
+      if (status) status.textContent = 'Ctrl+Enter で追加 / Esc で閉じる';
+      getCurrentWindow().hide().catch(console.error);
+    }, HIDE_DELAY_MS);
+  } catch (error) {
+    console.error('Failed to append to inbox:', error);
+    if (status) status.textContent = `エラー: ${error}`;
+  }
+}
+
+textArea.addEventListener('keydown', (e) => {
+  if (e.key === 'Enter' && (e.ctrlKey || e.metaKey)) {
+    e.preventDefault();
+    submit().catch(console.error);
+  } else if (e.key === 'Escape') {
+    getCurrentWindow().hide().catch(console.error);
+  }
+});
+
+submitButton?.addEventListener('click', () => {
+  submit().catch(console.error);
+});
+
+// The window is reused, so focus the text box every time it is shown
+window.addEventListener('focus', () => textArea.focus());
@@ -11,6 +11,8 @@ export const STORAGE_KEY_LINE_HEIGHT = 'note-line-height';
 export const DEFAULT_LINE_HEIGHT = '1.4';
 
 export const EVENT_OPEN_FILE = 'open-file';
+// Markdown links to stored attachments look like `![alt](attachment:<name>)`
+export const ATTACHMENT_LINK_PREFIX = 'attachment:';
 export const EVENT_TAURI_ERROR = 'tauri://error';
 
 export const NOTE_COLOR_DEFAULT = '#fef3c7';
@@ -1,6 +1,7 @@
 import { getAdapter } from './adapters/index.js';
 import { escapeHtml, renderMarkdown, getFileNameFromPath, resolveRelativeUrl } from './utils.js';
 import { Adapter } from './adapters/types';
+import { ATTACHMENT_LINK_PREFIX } from './constants.js';
 
 let adapter: Adapter | null = null;
 
@@ -47,7 +48,11 @@ async function renderNotes(filter = '') {
 
       // Use renderMarkdown for preview (truncate to 200 chars for performance/size)
       const previewContent = note.content.substring(0, 500);
-      const previewHtml = renderMarkdown(previewContent);
+      const previewHtml = renderMarkdown(previewContent, (url) =>
+        url.startsWith(ATTACHMENT_LINK_PREFIX) && adapter?.attachmentUrl
+          ? adapter.attachmentUrl(url.slice(ATTACHMENT_LINK_PREFIX.length))
+          : url
+      );
 
       return `
             <div class="note-card" data-id="${note.id}" style="animation-delay: ${index * 0.05}s">
@@ -133,6 +138,13 @@ async function createNewNote() {
 async function handleFileOpen(filePath: string) {
   if (!adapter) adapter = await getAdapter();
   try {
+    // The backend keeps the note and the file in sync from here on
+    if (adapter.openLinkedFile) {
+      await adapter.openLinkedFile(filePath);
+      await renderNotes();
+      return;
+    }
+
     const content = await adapter.readTextFile(filePath);
 
     // Create a new note with this content
@@ -198,6 +210,22 @@ document.addEventListener('DOMContentLoaded', async () => {
     });
   }
 
+  // Global search shortcut and search links; registered before onFileOpen, whose
+  // ready handshake also releases searches queued on a cold start
+  if ((window as any).__TAURI__) {
+    const { listen } = await import('@tauri-apps/api/event');
+    await listen<string | null>('open-search', (event) => {
+      const search = document.getElementById('search-input') as HTMLInputElement | null;
+      // Search links carry a query; the shortcut only focuses the box
+      if (search && typeof event.payload === 'string') {
+        search.value = event.payload;
+        renderNotes(event.payload).catch(console.error);
+      }
+      search?.focus();
+      search?.select();
+    }).catch(console.error);
+  }
+
   // Listen for file open events
   adapter.onFileOpen(async (filePath) => {
     // console.log('[DEBUG] main.js: onFileOpen event received:', filePath);
@@ -256,9 +284,6 @@ document.addEventListener('DOMContentLoaded', async () => {
   // Tauri-specific event listener for sync requests from separate windows
 
   if ((window as any).__TAURI__) {
-    adapter.onFileOpen(() => {
-      /* already handled by adapter.onFileOpen */
-    });
     // Use Tauri's listen directly for request-sync if available
     import('@tauri-apps/api/event')
       .then(({ listen }) => {
@@ -266,6 +291,10 @@ document.addEventListener('DOMContentLoaded', async () => {
           // console.log('Sync requested from Tauri sub-window');
           triggerSync().catch(console.error);
         });
+        // Files dropped on any window were imported by the backend
+        listen('notes-imported', () => {
+          renderNotes().catch(console.error);
+        });
       })
       .catch(() => {});
   }
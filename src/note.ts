@@ -2,13 +2,13 @@ import { getAdapter } from './adapters/index.js';
 import { Crepe, CrepeFeature } from '@milkdown/crepe';
 import '@milkdown/crepe/theme/common/style.css';
 import '@milkdown/crepe/theme/frame.css';
-import { extractTitle, removeExtraListBlankLines } from './utils.js';
+import { extractTitle, mergeBackendUpdate, removeExtraListBlankLines } from './utils.js';
 import { setupTableAutoComplete } from './table-utils.js';
 import { listener, listenerCtx } from '@milkdown/plugin-listener';
 import { insertHardbreakCommand } from '@milkdown/preset-commonmark';
-import { callCommand } from '@milkdown/utils';
+import { callCommand, insert } from '@milkdown/utils';
 import { remarkStringifyOptionsCtx, remarkPluginsCtx, editorViewCtx } from '@milkdown/core';
-import { Adapter, Note } from './adapters/types';
+import { Adapter, Note, WindowPrefs } from './adapters/types';
 import { splitListItem } from '@milkdown/prose/schema-list';
 import {
   AUTO_SAVE_DELAY_MS,
@@ -21,6 +21,7 @@ import {
   NOTE_COLOR_DEFAULT,
   MOVE_DEBOUNCE_MS,
   RESIZE_DEBOUNCE_MS,
+  ATTACHMENT_LINK_PREFIX,
 } from './constants.js';
 
 let adapter: Adapter | null = null;
@@ -45,6 +46,40 @@ function getNoteIdFromUrl(): string | null {
   return params.get('id');
 }
 
+// Markdown keeps `attachment:<name>`; the webview loads it through the attachment protocol
+function attachmentDisplayUrl(url: string): string {
+  if (url.startsWith(ATTACHMENT_LINK_PREFIX) && adapter?.attachmentUrl) {
+    return adapter.attachmentUrl(url.slice(ATTACHMENT_LINK_PREFIX.length));
+  }
+  return url;
+}
+
+async function uploadAttachment(file: File): Promise<string> {
+  if (!adapter?.addAttachment) throw new Error('Attachments are not supported');
+  const fromName = file.name.includes('.') ? file.name.split('.').pop() : '';
+  const extension = fromName || file.type.split('/')[1] || '';
+  const name = await adapter.addAttachment(new Uint8Array(await file.arrayBuffer()), extension);
+  return `${ATTACHMENT_LINK_PREFIX}${name}`;
+}
+
+// Pasted screenshots are stored as attachments and linked at the cursor
+async function handleImagePaste(e: ClipboardEvent) {
+  const file = Array.from(e.clipboardData?.files || []).find((f) => f.type.startsWith('image/'));
+  if (!file || !adapter?.addAttachment) return;
+  e.preventDefault();
+  e.stopPropagation();
+
+  const markdown = `![${file.name.replace(/\.[^.]+$/, '')}](${await uploadAttachment(file)})`;
+  const sourceEditor = document.getElementById('source-editor') as HTMLTextAreaElement | null;
+  if (isEditorMode && sourceEditor) {
+    const { selectionStart, selectionEnd } = sourceEditor;
+    sourceEditor.setRangeText(markdown, selectionStart, selectionEnd, 'end');
+    sourceEditor.dispatchEvent(new Event('input'));
+  } else if (crepeInstance) {
+    crepeInstance.editor.action(insert(markdown));
+  }
+}
+
 // Initialize Milkdown Crepe editor with content
 async function initEditor(content: string) {
   const editorEl = document.getElementById('editor');
@@ -71,6 +106,12 @@ async function initEditor(content: string) {
       features: {
         [CrepeFeature.Latex]: false, // Disable LaTeX for simplicity
       },
+      featureConfigs: {
+        [CrepeFeature.ImageBlock]: {
+          onUpload: uploadAttachment,
+          proxyDomURL: attachmentDisplayUrl,
+        },
+      },
     });
 
     // Define a simple remark plugin to parse <br> tags back into break nodes
@@ -462,9 +503,77 @@ function setupSettings() {
   });
 }
 
+type WindowPrefToggle = 'always_on_top' | 'pinned_to_desktop' | 'compact' | 'click_through';
+
+const PREF_CHECKBOXES: [string, WindowPrefToggle][] = [
+  ['pref-always-on-top', 'always_on_top'],
+  ['pref-pinned', 'pinned_to_desktop'],
+  ['pref-compact', 'compact'],
+  ['pref-click-through', 'click_through'],
+];
+
+function currentWindowPrefs(): WindowPrefs {
+  return (
+    noteData?.window_prefs ?? {
+      always_on_top: false,
+      opacity: 1,
+      compact: false,
+      pinned_to_desktop: false,
+      click_through: false,
+    }
+  );
+}
+
+async function updateWindowPrefs(change: Partial<WindowPrefs>) {
+  if (!noteId || !noteData || !adapter?.setNoteWindowPrefs) return;
+  const prefs = await adapter.setNoteWindowPrefs(noteId, { ...currentWindowPrefs(), ...change });
+  noteData.window_prefs = prefs;
+  applyWindowPrefs(prefs);
+}
+
+// Window preferences are stored by the backend, which also applies them to the window
+function setupWindowPrefs() {
+  const section = document.getElementById('window-prefs');
+  const opacityRange = document.getElementById('pref-opacity') as HTMLInputElement | null;
+  if (!section || !opacityRange || !adapter?.setNoteWindowPrefs) return;
+  section.classList.remove('hidden');
+
+  opacityRange.addEventListener('input', () => {
+    document.documentElement.style.opacity = opacityRange.value;
+  });
+  opacityRange.addEventListener('change', () => {
+    updateWindowPrefs({ opacity: Number(opacityRange.value) }).catch(console.error);
+  });
+
+  for (const [id, key] of PREF_CHECKBOXES) {
+    const checkbox = document.getElementById(id) as HTMLInputElement | null;
+    if (!checkbox) continue;
+    checkbox.addEventListener('change', async () => {
+      // Once enabled the note ignores the mouse, so say how to get it back first
+      if (key === 'click_through' && checkbox.checked && adapter) {
+        const confirmed = await adapter.confirm(
+          'このノートはクリックできなくなります。トレイメニューの「クリック透過を解除」で元に戻せます。',
+          {
+            title: 'クリック透過',
+            kind: 'warning',
+            okLabel: '有効にする',
+            cancelLabel: 'キャンセル',
+          }
+        );
+        if (!confirmed) {
+          checkbox.checked = false;
+          return;
+        }
+      }
+      await updateWindowPrefs({ [key]: checkbox.checked }).catch(console.error);
+    });
+  }
+}
+
 // Setup event listeners
 function setupEventListeners() {
   setupSettings();
+  setupWindowPrefs();
   const btnToggle = document.getElementById('btn-toggle');
   if (btnToggle) {
     btnToggle.addEventListener('click', toggleEditorMode);
@@ -508,6 +617,15 @@ function setupEventListeners() {
     }
   }
 
+  // Capture phase, so the editor never sees the image itself
+  document.addEventListener(
+    'paste',
+    (e) => {
+      handleImagePaste(e).catch((error) => console.error('Failed to paste image:', error));
+    },
+    true
+  );
+
   // Keyboard shortcuts
   document.addEventListener('keydown', (e) => {
     if (e.ctrlKey || e.metaKey) {
@@ -526,6 +644,39 @@ function setupEventListeners() {
 
   // Save window state on move/resize
   if (adapter) {
+    if (adapter.onNoteUpdated) {
+      // The backend changed this note (e.g. quick capture appended to it)
+      adapter.onNoteUpdated(async (note) => {
+        const hadPendingSave = saveTimeout !== null;
+        if (saveTimeout) {
+          clearTimeout(saveTimeout);
+          saveTimeout = null;
+        }
+        const sourceEditor = document.getElementById('source-editor') as HTMLTextAreaElement;
+        const local = isEditorMode && sourceEditor ? sourceEditor.value : getEditorContent();
+        // Typing not yet saved is merged in rather than dropped
+        const content = hadPendingSave
+          ? mergeBackendUpdate(lastSavedContent, local, note.content)
+          : note.content;
+        noteData = note;
+        lastSavedContent = note.content;
+        if (isEditorMode && sourceEditor) {
+          sourceEditor.value = content;
+        } else {
+          await setEditorContent(content);
+        }
+        if (content !== note.content) await saveNote();
+      });
+    }
+
+    if (adapter.onWindowPrefsChanged) {
+      adapter.onWindowPrefsChanged((prefs) => {
+        // Keep the in-memory note current so the next save does not revert them
+        if (noteData) noteData.window_prefs = prefs;
+        applyWindowPrefs(prefs);
+      });
+    }
+
     let moveTimeout: ReturnType<typeof setTimeout> | null = null;
 
     if (adapter.onWindowMoved) {
@@ -548,6 +699,19 @@ function setupEventListeners() {
   }
 }
 
+// Apply the parts of the sticky-note preferences the window itself cannot
+function applyWindowPrefs(prefs: WindowPrefs | undefined) {
+  document.documentElement.style.opacity = String(prefs?.opacity ?? 1);
+  document.body.classList.toggle('compact', prefs?.compact ?? false);
+
+  const opacityRange = document.getElementById('pref-opacity') as HTMLInputElement | null;
+  if (opacityRange) opacityRange.value = String(prefs?.opacity ?? 1);
+  for (const [id, key] of PREF_CHECKBOXES) {
+    const checkbox = document.getElementById(id) as HTMLInputElement | null;
+    if (checkbox) checkbox.checked = prefs?.[key] ?? false;
+  }
+}
+
 // Initialize
 
 document.addEventListener('DOMContentLoaded', async () => {
@@ -593,6 +757,8 @@ document.addEventListener('DOMContentLoaded', async () => {
       const noteTitle = document.getElementById('note-title');
       if (noteTitle) noteTitle.textContent = noteData.title;
 
+      applyWindowPrefs(noteData.window_prefs);
+
       if (noteData.color) {
         document.documentElement.style.setProperty('--note-color', noteData.color);
         const activeOpt = document.querySelector(`.color-option[data-color="${noteData.color}"]`);
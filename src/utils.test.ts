@@ -9,6 +9,7 @@ import {
   findTableContext,
   canDeleteTableRow,
   resolveRelativeUrl,
+  mergeBackendUpdate,
   DEFAULT_TITLE,
   MAX_TITLE_LENGTH,
   MAX_PREVIEW_LENGTH,
@@ -160,6 +161,14 @@ describe('renderMarkdown', () => {
     // @ts-expect-error: Intentional null for testing
     expect(renderMarkdown(null)).toBe('');
   });
+
+  it('maps image URLs when a resolver is given', () => {
+    const result = renderMarkdown('![shot](attachment:abc.png)', (url) =>
+      url.replace('attachment:', 'http://attachment.localhost/')
+    );
+    expect(result).toContain('src="http://attachment.localhost/abc.png"');
+    expect(renderMarkdown('![shot](a.png)')).toContain('src="a.png"');
+  });
 });
 
 describe('removeExtraListBlankLines', () => {
@@ -409,3 +418,25 @@ describe('resolveRelativeUrl', () => {
     );
   });
 });
+
+// ── mergeBackendUpdate ─────────────────────────────────
+
+describe('mergeBackendUpdate', () => {
+  it('takes the backend copy when nothing was typed', () => {
+    expect(mergeBackendUpdate('a\n', 'a\n', 'b\n')).toBe('b\n');
+  });
+
+  it('adds appended text after unsaved edits', () => {
+    expect(mergeBackendUpdate('# Inbox\n', '# Inbox\n\ntyping', '# Inbox\n\n- memo\n')).toBe(
+      '# Inbox\n\ntyping\n\n- memo\n'
+    );
+  });
+
+  it('keeps unsaved edits over other backend changes', () => {
+    expect(mergeBackendUpdate('old\n', 'old\nmine\n', 'replaced\n')).toBe('old\nmine\n');
+  });
+
+  it('does not start with a blank line when everything was cleared', () => {
+    expect(mergeBackendUpdate('a', '', 'a\n\n![x](y)\n')).toBe('![x](y)\n');
+  });
+});
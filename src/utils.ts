@@ -12,11 +12,20 @@ export const MAX_PREVIEW_LENGTH = 100;
 /**
  * Render markdown to HTML.
  * @param {string} content - Markdown content
+ * @param {Function} [resolveImageUrl] - Maps image URLs, e.g. attachment links, for display
  * @returns {string} Rendered HTML
  */
-export function renderMarkdown(content: string): string {
+export function renderMarkdown(
+  content: string,
+  resolveImageUrl?: (url: string) => string
+): string {
   if (!content) return '';
-  return marked.parse(content) as string;
+  if (!resolveImageUrl) return marked.parse(content) as string;
+  return marked.parse(content, {
+    walkTokens: (token) => {
+      if (token.type === 'image') token.href = resolveImageUrl(token.href);
+    },
+  }) as string;
 }
 
 /**
@@ -106,6 +115,23 @@ export function getFileNameFromPath(filePath: string): string {
   );
 }
 
+/**
+ * Combine unsaved editor text with a copy of the note the backend changed.
+ * Text the backend appended (quick capture, dropped images) is added after the local edits;
+ * any other change gives way to the local edits so typing is never lost.
+ * @param {string} base - Content the editor last saved
+ * @param {string} local - Current editor content
+ * @param {string} remote - Content sent by the backend
+ * @returns {string} Content to show and save
+ */
+export function mergeBackendUpdate(base: string, local: string, remote: string): string {
+  if (local === base) return remote;
+  const body = base.trimEnd();
+  if (remote === base || !remote.startsWith(body)) return local;
+  const appended = remote.slice(body.length);
+  return local.trim() ? local.trimEnd() + appended : appended.trimStart();
+}
+
 /**
  * Find table context from ancestor node information.
  * Traverses from deepest to shallowest to find table and table_row nodes.
@@ -13,6 +13,7 @@ export default defineConfig({
       input: {
         main: resolve(__dirname, 'src/index.html'),
         note: resolve(__dirname, 'src/note.html'),
+        capture: resolve(__dirname, 'src/capture.html'),
       },
     },
   },